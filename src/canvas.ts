@@ -1,4 +1,4 @@
-import { Canvas } from './wasm/pkg/wasm';
+import { Canvas, DeltaMode, Modifier, MouseButton, PointerType } from './wasm/pkg/wasm';
 
 function getRelativeCoords(canvas: HTMLCanvasElement, x: number, y: number){
     const rect = canvas.getBoundingClientRect();
@@ -9,42 +9,188 @@ function getRelativeCoords(canvas: HTMLCanvasElement, x: number, y: number){
     };
 }
 
+function getModifiers(e: MouseEvent | KeyboardEvent) {
+    return (e.shiftKey ? Modifier.Shift : 0)
+        | (e.ctrlKey ? Modifier.Ctrl : 0)
+        | (e.altKey ? Modifier.Alt : 0)
+        | (e.metaKey ? Modifier.Meta : 0);
+}
+
+function getPointerType(e: MouseEvent) {
+    if (!(e instanceof PointerEvent)) return PointerType.Mouse;
+    if (e.pointerType === 'touch') return PointerType.Touch;
+    if (e.pointerType === 'pen') return PointerType.Pen;
+    return PointerType.Mouse;
+}
+
+function readFile(file: File, as: 'text' | 'dataURL') {
+    return new Promise<string>((resolve, reject) => {
+        const reader = new FileReader();
+        reader.onload = () => resolve(reader.result as string);
+        reader.onerror = () => reject(reader.error);
+        if (as === 'text') reader.readAsText(file);
+        else reader.readAsDataURL(file);
+    });
+}
+
+function imageSize(src: string) {
+    return new Promise<{width: number, height: number}>((resolve) => {
+        const image = new Image();
+        image.onload = () => resolve({ width: image.naturalWidth, height: image.naturalHeight });
+        image.onerror = () => resolve({ width: 100, height: 100 });
+        image.src = src;
+    });
+}
+
+async function readDropItems(data: DataTransfer | null) {
+    if (data == null) return [];
+
+    const items = [];
+    for (const file of Array.from(data.files)) {
+        if (file.type === 'image/svg+xml') {
+            items.push({ type: 'svg', markup: await readFile(file, 'text') });
+        } else if (file.type.startsWith('image/')) {
+            const src = await readFile(file, 'dataURL');
+            items.push({ type: 'image', src, ...await imageSize(src) });
+        }
+    }
+
+    const text = data.getData('text/plain');
+    if (items.length === 0 && text) {
+        items.push({ type: 'text', text, style: { font_size: 20, fill_color: 'white' } });
+    }
+    return items;
+}
+
 export function canvasMain(id: string) {
     const canvas = new Canvas(id, "black");
     const jsCanvas = canvas.get_js_canvas();
     if (jsCanvas == null) return;
 
-    document.addEventListener('mousemove', (e) => {
-        const {x, y} = getRelativeCoords(jsCanvas, e.clientX, e.clientY);
-        canvas.on_mouse_move(x, y, Date.now());
-    });
+    jsCanvas.style.touchAction = 'none';
 
-    document.addEventListener('mousedown', (e) => {
+    const onPointer = (handler: typeof canvas.on_pointer_down) => (e: PointerEvent) => {
         const {x, y} = getRelativeCoords(jsCanvas, e.clientX, e.clientY);
-        canvas.on_mouse_down(x, y, Date.now());
+        const button = e.button < 0 ? undefined : e.button as MouseButton;
+        handler.call(canvas, e.pointerId, x, y, Date.now(), getModifiers(e), button, getPointerType(e));
+    };
+
+    document.addEventListener('pointermove', onPointer(canvas.on_pointer_move));
+    document.addEventListener('pointerdown', onPointer(canvas.on_pointer_down));
+    document.addEventListener('pointerup', onPointer(canvas.on_pointer_up));
+    document.addEventListener('pointercancel', (e) => {
+        canvas.on_pointer_cancel(e.pointerId, Date.now());
     });
 
-    document.addEventListener('mouseup', (e) => {
+    jsCanvas.addEventListener('wheel', (e) => {
+        e.preventDefault();
         const {x, y} = getRelativeCoords(jsCanvas, e.clientX, e.clientY);
-        canvas.on_mouse_up(x, y, Date.now());
-    });
+        canvas.on_wheel(e.deltaX, e.deltaY, x, y, e.deltaMode as DeltaMode, getModifiers(e));
+    }, { passive: false });
 
+    // Keys the canvas acts on, like Backspace while editing text or Mod+Z,
+    // would otherwise also reach the browser.
     document.addEventListener('keydown', (e) => {
-        canvas.on_key_down(e.key, Date.now());
+        if (canvas.on_key_down(e.key, Date.now(), getModifiers(e))) {
+            e.preventDefault();
+        }
     });
 
     document.addEventListener('keyup', (e) => {
-        canvas.on_key_up(e.key, Date.now());
+        canvas.on_key_up(e.key, Date.now(), getModifiers(e));
     });
     
     document.addEventListener('keypress', (e) => {
-        canvas.on_key_press(e.key, Date.now());
+        canvas.on_key_press(e.key, Date.now(), getModifiers(e));
+    });
+
+    // Copy, cut and paste go through the system clipboard, so nodes can be
+    // pasted into canvases in other tabs. The canvas' own bindings for them
+    // would only reach its internal clipboard.
+    for (const chord of ['Mod+C', 'Mod+X', 'Mod+V']) {
+        canvas.unbind_key(chord);
+    }
+
+    for (const type of ['copy', 'cut'] as const) {
+        document.addEventListener(type, (e) => {
+            const text = type === 'copy' ? canvas.copy_selection() : canvas.cut_selection();
+            if (text === undefined || e.clipboardData == null) return;
+            e.clipboardData.setData('text/plain', text);
+            e.preventDefault();
+        });
+    }
+
+    document.addEventListener('paste', (e) => {
+        const text = e.clipboardData?.getData('text/plain');
+        if (!text) return;
+        try {
+            canvas.paste(undefined, undefined, text);
+            e.preventDefault();
+        } catch {
+            // Not nodes copied from a canvas; leave it to the browser.
+        }
+    });
+
+    // Files and text dragged in from other apps become nodes where they
+    // are dropped.
+    jsCanvas.addEventListener('dragover', (e) => {
+        e.preventDefault();
+        const {x, y} = getRelativeCoords(jsCanvas, e.clientX, e.clientY);
+        canvas.on_drag_over(x, y);
+    });
+
+    jsCanvas.addEventListener('dragleave', () => {
+        canvas.on_drag_leave();
+    });
+
+    jsCanvas.addEventListener('drop', async (e) => {
+        e.preventDefault();
+        const {x, y} = getRelativeCoords(jsCanvas, e.clientX, e.clientY);
+        const items = await readDropItems(e.dataTransfer);
+        try {
+            canvas.on_drop(x, y, items);
+        } catch (err) {
+            console.warn(err);
+        }
+    });
+
+    // Right clicks and long presses open a menu of the actions the canvas
+    // reports as available, instead of the browser's own.
+    jsCanvas.addEventListener('contextmenu', (e) => e.preventDefault());
+
+    const menu = document.createElement('div');
+    menu.style.cssText = 'position: fixed; display: none; background: white; border: 1px solid #ccc; font: 13px sans-serif;';
+    document.body.appendChild(menu);
+    // Keep clicks on the menu from reaching the canvas underneath.
+    for (const type of ['pointerdown', 'pointerup', 'pointermove']) {
+        menu.addEventListener(type, (e) => e.stopPropagation());
+    }
+    document.addEventListener('pointerdown', () => {
+        menu.style.display = 'none';
+    });
+
+    canvas.on('context_menu', (e: { screen_x: number, screen_y: number, actions: string[] }) => {
+        const rect = jsCanvas.getBoundingClientRect();
+        menu.replaceChildren(...e.actions.map((action) => {
+            const item = document.createElement('div');
+            item.textContent = action.replace(/_/g, ' ');
+            item.style.cssText = 'padding: 4px 12px; cursor: pointer;';
+            item.addEventListener('click', () => {
+                canvas.run_action(action);
+                menu.style.display = 'none';
+            });
+            return item;
+        }));
+        menu.style.left = `${rect.left + e.screen_x}px`;
+        menu.style.top = `${rect.top + e.screen_y}px`;
+        menu.style.display = e.actions.length > 0 ? 'block' : 'none';
     });
 
     const ball = canvas.add_circle(500, 100, 50, {
         fill_color: 'red',
         stroke_width: 5,
         stroke_color: 'blue',
+        cursor: 'grab',
     });
     
     const bat = canvas.add_rect(100, 100, 15, 100, {
@@ -58,22 +204,25 @@ export function canvasMain(id: string) {
     const text = canvas.add_text("Hello World", 431, 503, {
         font_size: 20,
         fill_color: 'white',
+        cursor: 'text',
     });
 
+    // The paddles only slide up and down, inside the canvas.
+    for (const paddle of [bat, computer]) {
+        paddle.set_drag_options({
+            axis: 'y',
+            bounds: { type: 'rect', left: 0, top: 0, width: jsCanvas.width, height: jsCanvas.height },
+        });
+    }
+
     void ball;
-    void bat;
-    void computer;
 
-    let x = 0;
-    let y = 0;
+    // Set once, so dragging and rotating the text afterwards sticks.
+    text.translate(100, 50);
+    text.rotation((25 * Math.PI) / 180, "tl");
 
     const renderFn = async () => {
-        text.translate(x + 100, y + 50);
-        text.rotation(25, "tl");
         canvas.render();
-
-        // x += 1;
-        // y += 1;
         requestAnimationFrame(renderFn);
     };
 
@@ -0,0 +1,104 @@
+use std::{cell::Cell, rc::Weak};
+
+use wasm_bindgen::prelude::*;
+
+use super::context::Context;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_name = queueMicrotask)]
+    fn queue_microtask(callback: &JsValue);
+}
+
+thread_local! {
+    static HOOK_DEPTH: Cell<u32> = const { Cell::new(0) };
+}
+
+/// Calls `callback` with `arg` once the canvas method running now has
+/// returned, so the callback is free to call back into the canvas.
+pub(crate) fn defer(callback: &js_sys::Function, arg: &JsValue) {
+    queue_microtask(&callback.bind1(&JsValue::NULL, arg));
+}
+
+/// Runs `f` once the canvas method running now has returned.
+pub(crate) fn defer_with(f: impl FnOnce() + 'static) {
+    queue_microtask(&Closure::once_into_js(f));
+}
+
+/// Reaches a `Context` from work deferred past the canvas call that queued
+/// it, such as a JS action, noticing when the canvas has been freed since.
+pub(crate) struct ContextHandle {
+    ptr: *mut Context,
+    alive: Weak<()>,
+}
+
+impl ContextHandle {
+    pub(crate) fn new(context: &mut Context) -> Self {
+        Self { ptr: context, alive: std::rc::Rc::downgrade(&context.alive) }
+    }
+
+    /// Runs `f` on the context, or returns `None` when the canvas is gone or
+    /// busy with a hook. Deferred work runs outside any canvas call, so the
+    /// context is not borrowed elsewhere; `f` must not call into JS.
+    pub(crate) fn with<R>(&self, f: impl FnOnce(&mut Context) -> R) -> Option<R> {
+        if self.alive.strong_count() == 0 || is_in_hook() {
+            return None;
+        }
+        Some(f(unsafe { &mut *self.ptr }))
+    }
+}
+
+/// Marks a JS hook as running for as long as it is alive.
+struct Hook;
+
+impl Hook {
+    fn enter() -> Self {
+        HOOK_DEPTH.with(|depth| depth.set(depth.get() + 1));
+        Hook
+    }
+}
+
+impl Drop for Hook {
+    fn drop(&mut self) {
+        HOOK_DEPTH.with(|depth| depth.set(depth.get() - 1));
+    }
+}
+
+/// Calls a hook whose result the canvas needs right away, such as a drag or
+/// wheel handler. The canvas is still busy meanwhile: its methods throw on
+/// their own, and node handles throw while `is_in_hook` holds.
+pub(crate) fn call_hook(callback: &js_sys::Function, arg: &JsValue) -> Result<JsValue, JsValue> {
+    let _hook = Hook::enter();
+    callback.call1(&JsValue::NULL, arg)
+}
+
+pub(crate) fn is_in_hook() -> bool {
+    HOOK_DEPTH.with(Cell::get) > 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn handles_notice_a_dropped_context() {
+        let mut context = Context::headless();
+        let handle = ContextHandle::new(&mut context);
+        assert_eq!(handle.with(|ctx| ctx.nodes.len()), Some(0));
+        drop(context);
+        assert_eq!(handle.with(|ctx| ctx.nodes.len()), None);
+    }
+
+    #[test]
+    fn hooks_are_tracked_until_they_return() {
+        assert!(!is_in_hook());
+        {
+            let _outer = Hook::enter();
+            let inner = Hook::enter();
+            assert!(is_in_hook());
+            drop(inner);
+            assert!(is_in_hook());
+        }
+        assert!(!is_in_hook());
+    }
+}
@@ -0,0 +1,209 @@
+use gloo_utils::format::JsValueSerdeExt;
+use wasm_bindgen::JsValue;
+
+use super::{event::PointerType, geometry::Point, theme::merge};
+
+/// Timing and distance limits that tell clicks, double clicks and drags
+/// apart, configurable per canvas. Times are in milliseconds and distances
+/// in screen pixels.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ClickSettings {
+    /// Longest press that still counts as a click.
+    pub click_time: f64,
+    /// Longest gap between two clicks that form a double click.
+    pub double_click_time: f64,
+    /// Farthest the second click of a double click may land from the first.
+    pub double_click_distance: f64,
+    /// Same for the taps of a double-tap, which fingers place less exactly.
+    pub double_tap_distance: f64,
+    /// How far the pointer has to travel while pressed before a drag starts;
+    /// shorter jitter leaves the press a click.
+    pub drag_threshold: f64,
+}
+
+impl Default for ClickSettings {
+    fn default() -> Self {
+        Self {
+            click_time: 500.0,
+            double_click_time: 500.0,
+            double_click_distance: 4.0,
+            double_tap_distance: 24.0,
+            drag_threshold: 3.0,
+        }
+    }
+}
+
+impl ClickSettings {
+    /// Returns a copy of the settings with the (possibly partial) JS object
+    /// `patch` applied on top.
+    pub(crate) fn patch(&self, patch: &JsValue) -> Result<Self, JsValue> {
+        let patch: serde_json::Value = patch
+            .into_serde()
+            .map_err(|err| JsValue::from_str(&format!("invalid click settings: {}", err)))?;
+        let mut settings = serde_json::to_value(self).map_err(|err| JsValue::from_str(&err.to_string()))?;
+        merge(&mut settings, patch);
+        serde_json::from_value(settings).map_err(|err| JsValue::from_str(&format!("invalid click settings: {}", err)))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Press {
+    point: Point,
+    time: f64,
+    pointer_type: PointerType,
+    is_drag: bool,
+}
+
+/// A click that the next one may continue.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct LastClick {
+    point: Point,
+    time: f64,
+    count: u32,
+}
+
+fn distance(a: Point, b: Point) -> f64 {
+    ((a.x - b.x).powi(2) + (a.y - b.y).powi(2)).sqrt()
+}
+
+/// Turns the primary button's presses, moves and releases into clicks and
+/// drags, and counts clicks in a row from the `ClickSettings` alone, for
+/// mice and touches alike. Points are in scene coordinates; `scale` is the
+/// view zoom, so the distance limits stay the same on screen. Timestamps may
+/// arrive out of order, so only their distance matters.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ClickRecognizer {
+    pub(crate) settings: ClickSettings,
+    press: Option<Press>,
+    last_click: Option<LastClick>,
+}
+
+impl ClickRecognizer {
+    pub(crate) fn press(&mut self, point: Point, time: f64, pointer_type: PointerType) {
+        self.press = Some(Press { point, time, pointer_type, is_drag: false });
+    }
+
+    /// Tracks a move of the pressed pointer and returns whether the press
+    /// has turned into a drag.
+    pub(crate) fn moved(&mut self, point: Point, scale: f64) -> bool {
+        let threshold = self.settings.drag_threshold / scale;
+        let Some(press) = &mut self.press else {
+            return false;
+        };
+        if !press.is_drag && distance(press.point, point) > threshold {
+            press.is_drag = true;
+        }
+        press.is_drag
+    }
+
+    pub(crate) fn is_dragging(&self) -> bool {
+        self.press.is_some_and(|press| press.is_drag)
+    }
+
+    /// Ends the press and returns how many clicks in a row it completes, or
+    /// `None` when it was a drag, too long, or had no press.
+    pub(crate) fn release(&mut self, point: Point, time: f64, scale: f64) -> Option<u32> {
+        let press = self.press.take()?;
+        if press.is_drag || (time - press.time).abs() > self.settings.click_time {
+            self.last_click = None;
+            return None;
+        }
+
+        let max_distance = match press.pointer_type {
+            PointerType::Touch => self.settings.double_tap_distance,
+            _ => self.settings.double_click_distance,
+        } / scale;
+        let count = match self.last_click {
+            Some(last)
+                if (time - last.time).abs() <= self.settings.double_click_time
+                    && distance(last.point, point) <= max_distance =>
+            {
+                last.count + 1
+            }
+            _ => 1,
+        };
+        self.last_click = Some(LastClick { point, time, count });
+        Some(count)
+    }
+
+    /// Forgets the current press, e.g. when a multi-touch gesture takes
+    /// over, so it ends as neither click nor drag.
+    pub(crate) fn cancel(&mut self) {
+        self.press = None;
+        self.last_click = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(x: f64, y: f64) -> Point {
+        Point { x, y }
+    }
+
+    fn click(clicks: &mut ClickRecognizer, at: Point, time: f64, pointer_type: PointerType) -> Option<u32> {
+        clicks.press(at, time, pointer_type);
+        clicks.release(at, time + 50.0, 1.0)
+    }
+
+    #[test]
+    fn clicks_in_a_row_are_counted_within_the_configured_limits() {
+        let mut clicks = ClickRecognizer::default();
+        assert_eq!(click(&mut clicks, point(0.0, 0.0), 0.0, PointerType::Mouse), Some(1));
+        assert_eq!(click(&mut clicks, point(3.0, 0.0), 300.0, PointerType::Mouse), Some(2));
+        assert_eq!(click(&mut clicks, point(3.0, 0.0), 600.0, PointerType::Mouse), Some(3));
+
+        // Too late, then too far.
+        assert_eq!(click(&mut clicks, point(3.0, 0.0), 1200.0, PointerType::Mouse), Some(1));
+        assert_eq!(click(&mut clicks, point(10.0, 0.0), 1300.0, PointerType::Mouse), Some(1));
+    }
+
+    #[test]
+    fn changed_settings_apply_to_the_next_click() {
+        let mut clicks = ClickRecognizer::default();
+        clicks.settings.double_click_time = 100.0;
+        clicks.settings.double_click_distance = 20.0;
+        assert_eq!(click(&mut clicks, point(0.0, 0.0), 0.0, PointerType::Mouse), Some(1));
+        assert_eq!(click(&mut clicks, point(15.0, 0.0), 90.0, PointerType::Mouse), Some(2));
+        assert_eq!(click(&mut clicks, point(15.0, 0.0), 300.0, PointerType::Mouse), Some(1));
+    }
+
+    #[test]
+    fn taps_allow_more_distance_than_clicks() {
+        let mut clicks = ClickRecognizer::default();
+        assert_eq!(click(&mut clicks, point(0.0, 0.0), 0.0, PointerType::Touch), Some(1));
+        assert_eq!(click(&mut clicks, point(20.0, 0.0), 200.0, PointerType::Touch), Some(2));
+
+        assert_eq!(click(&mut clicks, point(0.0, 0.0), 1000.0, PointerType::Mouse), Some(1));
+        assert_eq!(click(&mut clicks, point(20.0, 0.0), 1200.0, PointerType::Mouse), Some(1));
+    }
+
+    #[test]
+    fn distances_are_measured_on_screen() {
+        let mut clicks = ClickRecognizer::default();
+        clicks.press(point(0.0, 0.0), 0.0, PointerType::Mouse);
+        assert_eq!(clicks.release(point(0.0, 0.0), 50.0, 4.0), Some(1));
+        clicks.press(point(2.0, 0.0), 100.0, PointerType::Mouse);
+        assert_eq!(clicks.release(point(2.0, 0.0), 150.0, 4.0), Some(1));
+    }
+
+    #[test]
+    fn drags_and_long_presses_are_not_clicks() {
+        let mut clicks = ClickRecognizer::default();
+        clicks.press(point(0.0, 0.0), 0.0, PointerType::Mouse);
+        assert!(!clicks.moved(point(2.0, 0.0), 1.0));
+        assert!(clicks.moved(point(10.0, 0.0), 1.0));
+        assert_eq!(clicks.release(point(10.0, 0.0), 50.0, 1.0), None);
+
+        clicks.press(point(0.0, 0.0), 100.0, PointerType::Mouse);
+        assert_eq!(clicks.release(point(0.0, 0.0), 700.0, 1.0), None);
+
+        // A drag also ends any run of clicks.
+        assert_eq!(click(&mut clicks, point(0.0, 0.0), 1000.0, PointerType::Mouse), Some(1));
+        clicks.press(point(0.0, 0.0), 1100.0, PointerType::Mouse);
+        clicks.moved(point(10.0, 0.0), 1.0);
+        assert_eq!(clicks.release(point(0.0, 0.0), 1150.0, 1.0), None);
+        assert_eq!(click(&mut clicks, point(0.0, 0.0), 1200.0, PointerType::Mouse), Some(1));
+    }
+}
@@ -0,0 +1,171 @@
+use super::{context::Context, geometry::Point, node::{Node, NodeId}, path};
+
+/// Guards against clip chains that loop back on themselves
+/// (`a` clipped to `b` clipped to `a`).
+const MAX_CLIP_DEPTH: usize = 8;
+
+/// Region a node's content is restricted to. Shape clips are expressed in
+/// the node's untransformed coordinates and therefore move and rotate with
+/// it; `Node` clips follow the outline of another node wherever it is.
+///
+/// Clips apply to one node each. This tree has no groups or parent nodes,
+/// so clipping a group, or inheriting a parent's clip, is not supported; to
+/// clip several nodes to one region, such as the content of a scrollable
+/// panel, clip each of them to the same node with `Node`.
+///
+/// Hit-testing follows the same region with or without a canvas: path clips
+/// are tested against their outline, with curves flattened into short lines.
+#[derive(Debug, Clone, PartialEq, PartialOrd, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Clip {
+    Rect { left: f64, top: f64, width: f64, height: f64 },
+    Circle { center_x: f64, center_y: f64, radius: f64 },
+    Path { d: String },
+    Node { node: NodeId },
+}
+
+impl Clip {
+    /// Intersects the current clip region of `ctx` with this clip. Must be
+    /// called between `save` and `restore`.
+    pub(crate) fn apply(&self, ctx: &Context, owner: &Node) {
+        self.apply_with_depth(ctx, owner, 0);
+    }
+
+    fn apply_with_depth(&self, ctx: &Context, owner: &Node, depth: usize) {
+        if depth >= MAX_CLIP_DEPTH {
+            return;
+        }
+
+        let target = match self {
+            Clip::Node { node } => match ctx.get_node(*node) {
+                Some(target) => target,
+                None => return,
+            },
+            _ => owner,
+        };
+
+        if let (Clip::Node { .. }, Some(clip)) = (self, &target.get_style().clip) {
+            clip.apply_with_depth(ctx, target, depth + 1);
+        }
+
+        let (style, rect) = (target.get_style(), target.get_rect());
+        style.apply_transform(ctx.context(), rect);
+        match self {
+            Clip::Rect { left, top, width, height } => {
+                ctx.context().begin_path();
+                ctx.context().rect(*left, *top, *width, *height);
+                ctx.context().clip();
+            }
+            Clip::Circle { center_x, center_y, radius } => {
+                ctx.context().begin_path();
+                let _ = ctx.context().arc(*center_x, *center_y, *radius, 0.0, 2.0 * std::f64::consts::PI);
+                ctx.context().clip();
+            }
+            Clip::Path { d } => {
+                if let Ok(path) = web_sys::Path2d::new_with_path_string(d) {
+                    ctx.context().clip_with_path_2d(&path);
+                }
+            }
+            Clip::Node { .. } => {
+                ctx.context().begin_path();
+                target.trace_path(ctx.context());
+                ctx.context().clip();
+            }
+        }
+        style.undo_transform(ctx.context(), rect);
+    }
+
+    /// Whether the canvas point `(x, y)` lies inside the clip region.
+    pub(crate) fn contains(&self, ctx: &Context, owner: &Node, x: f64, y: f64) -> bool {
+        self.contains_with_depth(ctx, owner, x, y, 0)
+    }
+
+    fn contains_with_depth(&self, ctx: &Context, owner: &Node, x: f64, y: f64, depth: usize) -> bool {
+        if depth >= MAX_CLIP_DEPTH {
+            return true;
+        }
+
+        let (lx, ly) = owner.get_style().to_local(x, y, owner.get_rect());
+        match self {
+            Clip::Rect { left, top, width, height } => {
+                lx >= *left && lx <= left + width && ly >= *top && ly <= top + height
+            }
+            Clip::Circle { center_x, center_y, radius } => {
+                let (dx, dy) = (lx - center_x, ly - center_y);
+                (dx * dx + dy * dy).sqrt() <= *radius
+            }
+            Clip::Path { d } => path::contains(&path::flatten(d), Point { x: lx, y: ly }),
+            Clip::Node { node } => match ctx.get_node(*node) {
+                Some(target) => {
+                    let is_inside = target.contains(x, y);
+                    match &target.get_style().clip {
+                        Some(clip) if is_inside => clip.contains_with_depth(ctx, target, x, y, depth + 1),
+                        _ => is_inside,
+                    }
+                }
+                None => true,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::internal::style::Style;
+
+    /// A 100x100 rect at the origin with `clip`, above a mask circle of
+    /// radius 20 around (80, 80).
+    fn context(clip: Clip) -> Context {
+        let mut ctx = Context::headless();
+        ctx.add_circle(Point { x: 80.0, y: 80.0 }, 20.0, Style::default());
+        ctx.add_rect(0.0, 0.0, 100.0, 100.0, Style { clip: Some(clip), ..Default::default() });
+        ctx
+    }
+
+    fn hits(ctx: &Context, x: f64, y: f64) -> bool {
+        let owner = &ctx.nodes[1];
+        owner.get_style().clip.as_ref().is_none_or(|clip| clip.contains(ctx, owner, x, y))
+    }
+
+    #[test]
+    fn rect_and_circle_clips_cut_the_node() {
+        let ctx = context(Clip::Rect { left: 10.0, top: 10.0, width: 30.0, height: 30.0 });
+        assert!(hits(&ctx, 20.0, 20.0));
+        assert!(!hits(&ctx, 50.0, 20.0));
+
+        let ctx = context(Clip::Circle { center_x: 50.0, center_y: 50.0, radius: 10.0 });
+        assert!(hits(&ctx, 55.0, 55.0));
+        assert!(!hits(&ctx, 5.0, 5.0));
+    }
+
+    #[test]
+    fn shape_clips_move_with_the_node() {
+        let mut ctx = context(Clip::Rect { left: 0.0, top: 0.0, width: 10.0, height: 10.0 });
+        ctx.nodes[1].get_style_mut().translate(50.0, 0.0);
+        assert!(hits(&ctx, 55.0, 5.0));
+        assert!(!hits(&ctx, 5.0, 5.0));
+    }
+
+    #[test]
+    fn node_clips_follow_the_other_node_and_its_clip() {
+        let mut ctx = context(Clip::Node { node: 1 });
+        assert!(hits(&ctx, 80.0, 80.0));
+        assert!(!hits(&ctx, 20.0, 20.0));
+
+        ctx.nodes[0].get_style_mut().clip = Some(Clip::Rect { left: 80.0, top: 0.0, width: 100.0, height: 200.0 });
+        assert!(hits(&ctx, 90.0, 80.0));
+        assert!(!hits(&ctx, 70.0, 80.0));
+
+        // Clips to a node that is gone leave the node whole.
+        let ctx = context(Clip::Node { node: 9 });
+        assert!(hits(&ctx, 20.0, 20.0));
+    }
+
+    #[test]
+    fn path_clips_are_hit_tested_without_a_canvas() {
+        let ctx = context(Clip::Path { d: "M0 0 H50 L0 50 Z".to_string() });
+        assert!(hits(&ctx, 10.0, 10.0));
+        assert!(!hits(&ctx, 40.0, 40.0));
+    }
+}
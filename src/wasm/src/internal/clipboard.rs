@@ -0,0 +1,202 @@
+use std::{cell::RefCell, collections::HashMap};
+
+use super::{
+    clip::Clip,
+    geometry::{Circle, Image, Line, Point, Rectangle, Text},
+    node::{Node, NodeId},
+    style::Style,
+};
+
+/// Marks clipboard text produced by `copy_selection`, so pasting unrelated
+/// text is rejected instead of misread.
+const CLIPBOARD_FORMAT: &str = "canvas-nodes";
+const CLIPBOARD_VERSION: u32 = 1;
+/// Offset of pasted or duplicated nodes when no position is given, so the
+/// copy does not hide the original.
+pub(crate) const PASTE_OFFSET: f64 = 10.0;
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Shape {
+    Line { start_x: f64, start_y: f64, end_x: f64, end_y: f64 },
+    Rect { left: f64, top: f64, width: f64, height: f64 },
+    Circle { center_x: f64, center_y: f64, radius: f64 },
+    Text { text: String, x: f64, y: f64 },
+    Image { src: String, left: f64, top: f64, width: f64, height: f64 },
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+struct ClipboardNode {
+    /// Id on the canvas it was copied from, so clips between copied nodes
+    /// can be pointed at the pasted copies.
+    id: NodeId,
+    #[serde(flatten)]
+    shape: Shape,
+    style: Style,
+}
+
+/// Copied nodes in the portable JSON form put on the clipboard.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub(crate) struct ClipboardData {
+    format: String,
+    version: u32,
+    nodes: Vec<ClipboardNode>,
+}
+
+impl ClipboardData {
+    pub(crate) fn from_nodes<'a>(nodes: impl IntoIterator<Item = &'a Node>) -> Self {
+        let nodes = nodes
+            .into_iter()
+            .map(|node| {
+                let shape = match node {
+                    Node::Line(line, ..) => Shape::Line {
+                        start_x: line.start.x,
+                        start_y: line.start.y,
+                        end_x: line.end.x,
+                        end_y: line.end.y,
+                    },
+                    Node::Rect(rect, ..) => Shape::Rect {
+                        left: rect.left,
+                        top: rect.top,
+                        width: rect.width,
+                        height: rect.height,
+                    },
+                    Node::Circle(circle, ..) => Shape::Circle {
+                        center_x: circle.center.x,
+                        center_y: circle.center.y,
+                        radius: circle.radius,
+                    },
+                    Node::Text(text, ..) => Shape::Text {
+                        text: text.text.clone(),
+                        x: text.position.x,
+                        y: text.position.y,
+                    },
+                    Node::Image(image, ..) => Shape::Image {
+                        src: image.src.clone(),
+                        left: image.rect.left,
+                        top: image.rect.top,
+                        width: image.rect.width,
+                        height: image.rect.height,
+                    },
+                };
+                ClipboardNode { id: node.id(), shape, style: node.get_style().clone() }
+            })
+            .collect();
+
+        Self {
+            format: CLIPBOARD_FORMAT.to_string(),
+            version: CLIPBOARD_VERSION,
+            nodes,
+        }
+    }
+
+    pub(crate) fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+
+    pub(crate) fn from_json(json: &str) -> Result<Self, String> {
+        let data: Self = serde_json::from_str(json).map_err(|err| format!("invalid clipboard data: {}", err))?;
+        if data.format != CLIPBOARD_FORMAT || data.version > CLIPBOARD_VERSION {
+            return Err(format!("unsupported clipboard format `{}` version {}", data.format, data.version));
+        }
+        Ok(data)
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Ids the nodes had on the canvas they were copied from.
+    pub(crate) fn ids(&self) -> Vec<NodeId> {
+        self.nodes.iter().map(|node| node.id).collect()
+    }
+
+    /// Builds the copied nodes, giving them the fresh `ids` in order. Clips
+    /// that pointed at another copied node follow its copy; clips to a node
+    /// that was not copied keep pointing at it while `exists` says it is on
+    /// the canvas pasted into, as after a duplicate, and are dropped
+    /// otherwise. Text nodes are left unmeasured.
+    pub(crate) fn to_nodes(&self, ids: &[NodeId], exists: impl Fn(NodeId) -> bool) -> Vec<Node> {
+        let copies: HashMap<NodeId, NodeId> = self.nodes.iter().map(|node| node.id).zip(ids.iter().copied()).collect();
+
+        self.nodes
+            .iter()
+            .zip(ids)
+            .map(|(copied, &id)| {
+                let mut style = copied.style.clone();
+                if let Some(Clip::Node { node }) = &style.clip {
+                    style.clip = match copies.get(node) {
+                        Some(&copy) => Some(Clip::Node { node: copy }),
+                        None if exists(*node) => Some(Clip::Node { node: *node }),
+                        None => None,
+                    };
+                }
+
+                match copied.shape.clone() {
+                    Shape::Line { start_x, start_y, end_x, end_y } => {
+                        let line = Line { start: Point { x: start_x, y: start_y }, end: Point { x: end_x, y: end_y } };
+                        Node::Line(line, style, true, id)
+                    }
+                    Shape::Rect { left, top, width, height } => {
+                        Node::Rect(Rectangle { left, top, width, height }, style, true, id)
+                    }
+                    Shape::Circle { center_x, center_y, radius } => {
+                        Node::Circle(Circle { center: Point { x: center_x, y: center_y }, radius }, style, true, id)
+                    }
+                    Shape::Text { text, x, y } => {
+                        let text = Text { text, position: Point { x, y }, shape: RefCell::new(None) };
+                        Node::Text(text, style, true, id)
+                    }
+                    Shape::Image { src, left, top, width, height } => {
+                        Node::Image(Image { src, rect: Rectangle { left, top, width, height } }, style, true, id)
+                    }
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(id: NodeId, clip: Option<Clip>) -> Node {
+        let style = Style { clip, ..Default::default() };
+        Node::Rect(Rectangle { left: 0.0, top: 0.0, width: 10.0, height: 10.0 }, style, false, id)
+    }
+
+    fn clip_of(node: &Node) -> Option<Clip> {
+        node.get_style().clip.clone()
+    }
+
+    #[test]
+    fn clips_follow_copied_nodes_and_are_dropped_otherwise() {
+        let mask = rect(1, None);
+        let masked = rect(2, Some(Clip::Node { node: 1 }));
+        let orphan = rect(3, Some(Clip::Node { node: 7 }));
+        let data = ClipboardData::from_json(&ClipboardData::from_nodes([&mask, &masked, &orphan]).to_json()).unwrap();
+
+        let nodes = data.to_nodes(&[11, 12, 13], |_| false);
+        assert_eq!(nodes.iter().map(Node::id).collect::<Vec<_>>(), vec![11, 12, 13]);
+        assert_eq!(clip_of(&nodes[1]), Some(Clip::Node { node: 11 }));
+        assert_eq!(clip_of(&nodes[2]), None);
+    }
+
+    #[test]
+    fn clips_to_nodes_still_on_the_canvas_are_kept() {
+        let masked = rect(2, Some(Clip::Node { node: 1 }));
+        let data = ClipboardData::from_nodes([&masked]);
+        let nodes = data.to_nodes(&[11], |id| id == 1);
+        assert_eq!(clip_of(&nodes[0]), Some(Clip::Node { node: 1 }));
+    }
+
+    #[test]
+    fn other_text_is_not_pasted() {
+        assert!(ClipboardData::from_json("hello").is_err());
+        assert!(ClipboardData::from_json(r#"{"format":"other","version":1,"nodes":[]}"#).is_err());
+    }
+}
@@ -0,0 +1,423 @@
+use std::{fmt, str::FromStr};
+
+use wasm_bindgen::prelude::*;
+
+/// An sRGB color with straight (non-premultiplied) alpha in `0.0..=1.0`.
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColorError(String);
+
+impl fmt::Display for ColorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid color `{}`", self.0)
+    }
+}
+
+impl std::error::Error for ColorError {}
+
+const NAMED_COLORS: &[(&str, u32)] = &[
+    ("aliceblue", 0xf0f8ff), ("antiquewhite", 0xfaebd7), ("aqua", 0x00ffff),
+    ("aquamarine", 0x7fffd4), ("azure", 0xf0ffff), ("beige", 0xf5f5dc),
+    ("bisque", 0xffe4c4), ("black", 0x000000), ("blanchedalmond", 0xffebcd),
+    ("blue", 0x0000ff), ("blueviolet", 0x8a2be2), ("brown", 0xa52a2a),
+    ("burlywood", 0xdeb887), ("cadetblue", 0x5f9ea0), ("chartreuse", 0x7fff00),
+    ("chocolate", 0xd2691e), ("coral", 0xff7f50), ("cornflowerblue", 0x6495ed),
+    ("cornsilk", 0xfff8dc), ("crimson", 0xdc143c), ("cyan", 0x00ffff),
+    ("darkblue", 0x00008b), ("darkcyan", 0x008b8b), ("darkgoldenrod", 0xb8860b),
+    ("darkgray", 0xa9a9a9), ("darkgreen", 0x006400), ("darkgrey", 0xa9a9a9),
+    ("darkkhaki", 0xbdb76b), ("darkmagenta", 0x8b008b), ("darkolivegreen", 0x556b2f),
+    ("darkorange", 0xff8c00), ("darkorchid", 0x9932cc), ("darkred", 0x8b0000),
+    ("darksalmon", 0xe9967a), ("darkseagreen", 0x8fbc8f), ("darkslateblue", 0x483d8b),
+    ("darkslategray", 0x2f4f4f), ("darkslategrey", 0x2f4f4f), ("darkturquoise", 0x00ced1),
+    ("darkviolet", 0x9400d3), ("deeppink", 0xff1493), ("deepskyblue", 0x00bfff),
+    ("dimgray", 0x696969), ("dimgrey", 0x696969), ("dodgerblue", 0x1e90ff),
+    ("firebrick", 0xb22222), ("floralwhite", 0xfffaf0), ("forestgreen", 0x228b22),
+    ("fuchsia", 0xff00ff), ("gainsboro", 0xdcdcdc), ("ghostwhite", 0xf8f8ff),
+    ("gold", 0xffd700), ("goldenrod", 0xdaa520), ("gray", 0x808080),
+    ("green", 0x008000), ("greenyellow", 0xadff2f), ("grey", 0x808080),
+    ("honeydew", 0xf0fff0), ("hotpink", 0xff69b4), ("indianred", 0xcd5c5c),
+    ("indigo", 0x4b0082), ("ivory", 0xfffff0), ("khaki", 0xf0e68c),
+    ("lavender", 0xe6e6fa), ("lavenderblush", 0xfff0f5), ("lawngreen", 0x7cfc00),
+    ("lemonchiffon", 0xfffacd), ("lightblue", 0xadd8e6), ("lightcoral", 0xf08080),
+    ("lightcyan", 0xe0ffff), ("lightgoldenrodyellow", 0xfafad2), ("lightgray", 0xd3d3d3),
+    ("lightgreen", 0x90ee90), ("lightgrey", 0xd3d3d3), ("lightpink", 0xffb6c1),
+    ("lightsalmon", 0xffa07a), ("lightseagreen", 0x20b2aa), ("lightskyblue", 0x87cefa),
+    ("lightslategray", 0x778899), ("lightslategrey", 0x778899), ("lightsteelblue", 0xb0c4de),
+    ("lightyellow", 0xffffe0), ("lime", 0x00ff00), ("limegreen", 0x32cd32),
+    ("linen", 0xfaf0e6), ("magenta", 0xff00ff), ("maroon", 0x800000),
+    ("mediumaquamarine", 0x66cdaa), ("mediumblue", 0x0000cd), ("mediumorchid", 0xba55d3),
+    ("mediumpurple", 0x9370db), ("mediumseagreen", 0x3cb371), ("mediumslateblue", 0x7b68ee),
+    ("mediumspringgreen", 0x00fa9a), ("mediumturquoise", 0x48d1cc), ("mediumvioletred", 0xc71585),
+    ("midnightblue", 0x191970), ("mintcream", 0xf5fffa), ("mistyrose", 0xffe4e1),
+    ("moccasin", 0xffe4b5), ("navajowhite", 0xffdead), ("navy", 0x000080),
+    ("oldlace", 0xfdf5e6), ("olive", 0x808000), ("olivedrab", 0x6b8e23),
+    ("orange", 0xffa500), ("orangered", 0xff4500), ("orchid", 0xda70d6),
+    ("palegoldenrod", 0xeee8aa), ("palegreen", 0x98fb98), ("paleturquoise", 0xafeeee),
+    ("palevioletred", 0xdb7093), ("papayawhip", 0xffefd5), ("peachpuff", 0xffdab9),
+    ("peru", 0xcd853f), ("pink", 0xffc0cb), ("plum", 0xdda0dd),
+    ("powderblue", 0xb0e0e6), ("purple", 0x800080), ("rebeccapurple", 0x663399),
+    ("red", 0xff0000), ("rosybrown", 0xbc8f8f), ("royalblue", 0x4169e1),
+    ("saddlebrown", 0x8b4513), ("salmon", 0xfa8072), ("sandybrown", 0xf4a460),
+    ("seagreen", 0x2e8b57), ("seashell", 0xfff5ee), ("sienna", 0xa0522d),
+    ("silver", 0xc0c0c0), ("skyblue", 0x87ceeb), ("slateblue", 0x6a5acd),
+    ("slategray", 0x708090), ("slategrey", 0x708090), ("snow", 0xfffafa),
+    ("springgreen", 0x00ff7f), ("steelblue", 0x4682b4), ("tan", 0xd2b48c),
+    ("teal", 0x008080), ("thistle", 0xd8bfd8), ("tomato", 0xff6347),
+    ("turquoise", 0x40e0d0), ("violet", 0xee82ee), ("wheat", 0xf5deb3),
+    ("white", 0xffffff), ("whitesmoke", 0xf5f5f5), ("yellow", 0xffff00),
+    ("yellowgreen", 0x9acd32),
+];
+
+fn clamp_channel(value: f64) -> u8 {
+    value.round().clamp(0.0, 255.0) as u8
+}
+
+/// Parses a finite CSS `<number>`; Rust's own spellings such as `inf` or
+/// `NaN` are rejected.
+fn parse_number(token: &str) -> Option<f64> {
+    let is_css_number = token.chars().all(|c| c.is_ascii_digit() || matches!(c, '.' | '+' | '-' | 'e' | 'E'));
+    token.parse::<f64>().ok().filter(|value| is_css_number && value.is_finite())
+}
+
+/// A `<number>` or a `<percentage>` argument of a color function.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Component {
+    Number(f64),
+    Percentage(f64),
+}
+
+impl Component {
+    fn parse(token: &str) -> Option<Self> {
+        match token.strip_suffix('%') {
+            Some(percentage) => parse_number(percentage).map(Self::Percentage),
+            None => parse_number(token).map(Self::Number),
+        }
+    }
+
+    /// The value, with 100% mapping to `scale`.
+    fn scaled(self, scale: f64) -> f64 {
+        match self {
+            Self::Number(value) => value,
+            Self::Percentage(percentage) => percentage / 100.0 * scale,
+        }
+    }
+
+    fn is_percentage(self) -> bool {
+        matches!(self, Self::Percentage(_))
+    }
+}
+
+/// Parses an `<angle>` into degrees; a bare number is already in degrees.
+fn parse_hue(token: &str) -> Option<f64> {
+    let units = [("deg", 1.0), ("grad", 0.9), ("rad", 180.0 / std::f64::consts::PI), ("turn", 360.0)];
+    for (unit, factor) in units {
+        if let Some(value) = token.strip_suffix(unit) {
+            return parse_number(value).map(|v| v * factor);
+        }
+    }
+    parse_number(token)
+}
+
+/// Arguments of a color function: three channels and an optional alpha.
+struct Arguments<'a> {
+    channels: Vec<&'a str>,
+    alpha: Option<&'a str>,
+    /// Whether they were separated by commas, the legacy syntax.
+    is_legacy: bool,
+}
+
+/// Splits the arguments of a color function, accepting both the legacy
+/// comma-separated form and the space-separated form with `/ alpha`.
+fn split_arguments(args: &str) -> Option<Arguments<'_>> {
+    if args.contains(',') {
+        let parts: Vec<&str> = args.split(',').map(str::trim).collect();
+        let (channels, alpha) = match parts.len() {
+            3 => (parts, None),
+            4 => (parts[..3].to_vec(), Some(parts[3])),
+            _ => return None,
+        };
+        return Some(Arguments { channels, alpha, is_legacy: true });
+    }
+
+    let (channels, alpha) = match args.split_once('/') {
+        Some((channels, alpha)) => (channels, Some(alpha.trim())),
+        None => (args, None),
+    };
+    let channels: Vec<&str> = channels.split_whitespace().collect();
+    if channels.len() != 3 {
+        return None;
+    }
+    Some(Arguments { channels, alpha, is_legacy: false })
+}
+
+fn hue_to_rgb(p: f64, q: f64, t: f64) -> f64 {
+    let t = t.rem_euclid(1.0);
+    if t < 1.0 / 6.0 {
+        p + (q - p) * 6.0 * t
+    } else if t < 0.5 {
+        q
+    } else if t < 2.0 / 3.0 {
+        p + (q - p) * (2.0 / 3.0 - t) * 6.0
+    } else {
+        p
+    }
+}
+
+impl Color {
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Self {
+        Self { r, g, b, a: 1.0 }
+    }
+
+    pub const fn from_hex(hex: u32) -> Self {
+        Self::rgb((hex >> 16) as u8, (hex >> 8) as u8, hex as u8)
+    }
+
+    /// Builds a color from hue in degrees and saturation/lightness in `0.0..=1.0`.
+    pub fn from_hsl(h: f64, s: f64, l: f64, a: f64) -> Self {
+        let (h, s, l) = (h.rem_euclid(360.0) / 360.0, s.clamp(0.0, 1.0), l.clamp(0.0, 1.0));
+        let q = if l < 0.5 { l * (1.0 + s) } else { l + s - l * s };
+        let p = 2.0 * l - q;
+        Self {
+            r: clamp_channel(hue_to_rgb(p, q, h + 1.0 / 3.0) * 255.0),
+            g: clamp_channel(hue_to_rgb(p, q, h) * 255.0),
+            b: clamp_channel(hue_to_rgb(p, q, h - 1.0 / 3.0) * 255.0),
+            a: a.clamp(0.0, 1.0),
+        }
+    }
+
+    /// Hue in degrees, saturation and lightness in `0.0..=1.0`.
+    pub fn to_hsl(self) -> (f64, f64, f64) {
+        let (r, g, b) = (self.r as f64 / 255.0, self.g as f64 / 255.0, self.b as f64 / 255.0);
+        let (max, min) = (r.max(g).max(b), r.min(g).min(b));
+        let l = (max + min) / 2.0;
+        if max == min {
+            return (0.0, 0.0, l);
+        }
+
+        let d = max - min;
+        let s = if l > 0.5 { d / (2.0 - max - min) } else { d / (max + min) };
+        let h = if max == r {
+            (g - b) / d + if g < b { 6.0 } else { 0.0 }
+        } else if max == g {
+            (b - r) / d + 2.0
+        } else {
+            (r - g) / d + 4.0
+        };
+        (h * 60.0, s, l)
+    }
+
+    fn parse_hex(hex: &str) -> Option<Self> {
+        if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+        let digit = |i: usize| u8::from_str_radix(&hex[i..i + 1], 16).ok().map(|d| d * 17);
+        let pair = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+        let (r, g, b, a) = match hex.len() {
+            3 => (digit(0)?, digit(1)?, digit(2)?, 255),
+            4 => (digit(0)?, digit(1)?, digit(2)?, digit(3)?),
+            6 => (pair(0)?, pair(2)?, pair(4)?, 255),
+            8 => (pair(0)?, pair(2)?, pair(4)?, pair(6)?),
+            _ => return None,
+        };
+        Some(Self { r, g, b, a: a as f64 / 255.0 })
+    }
+
+    /// Parses the arguments of `rgb()`, `hsl()` and their `a` aliases. The
+    /// red, green and blue channels must be all numbers or all percentages,
+    /// and the legacy comma syntax needs percentages for saturation and
+    /// lightness, like in CSS.
+    fn parse_function(name: &str, args: &str) -> Option<Self> {
+        let Arguments { channels, alpha, is_legacy } = split_arguments(args)?;
+        let alpha = match alpha {
+            Some(alpha) => Component::parse(alpha)?.scaled(1.0),
+            None => 1.0,
+        };
+
+        match name {
+            "rgb" | "rgba" => {
+                let [r, g, b] = [channels[0], channels[1], channels[2]].map(Component::parse);
+                let (r, g, b) = (r?, g?, b?);
+                if r.is_percentage() != g.is_percentage() || g.is_percentage() != b.is_percentage() {
+                    return None;
+                }
+                let channel = |component: Component| clamp_channel(component.scaled(255.0));
+                Some(Self { r: channel(r), g: channel(g), b: channel(b), a: alpha.clamp(0.0, 1.0) })
+            }
+            "hsl" | "hsla" => {
+                let h = parse_hue(channels[0])?;
+                let (s, l) = (Component::parse(channels[1])?, Component::parse(channels[2])?);
+                if is_legacy && !(s.is_percentage() && l.is_percentage()) {
+                    return None;
+                }
+                Some(Self::from_hsl(h, s.scaled(100.0) / 100.0, l.scaled(100.0) / 100.0, alpha))
+            }
+            _ => None,
+        }
+    }
+
+    pub fn to_css(self) -> String {
+        if self.a >= 1.0 {
+            format!("#{:02x}{:02x}{:02x}", self.r, self.g, self.b)
+        } else {
+            let alpha = (self.a * 1000.0).round() / 1000.0;
+            format!("rgba({}, {}, {}, {})", self.r, self.g, self.b, alpha)
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl Color {
+    #[wasm_bindgen(constructor)]
+    pub fn parse(value: &str) -> Result<Color, JsValue> {
+        value.parse().map_err(|err: ColorError| JsValue::from_str(&err.to_string()))
+    }
+
+    #[wasm_bindgen(js_name = toString)]
+    pub fn css_string(&self) -> String {
+        self.to_css()
+    }
+
+    /// Raises HSL lightness by `amount` (`0.0..=1.0`).
+    pub fn lighten(&self, amount: f64) -> Color {
+        let (h, s, l) = self.to_hsl();
+        Self::from_hsl(h, s, l + amount, self.a)
+    }
+
+    /// Lowers HSL lightness by `amount` (`0.0..=1.0`).
+    pub fn darken(&self, amount: f64) -> Color {
+        self.lighten(-amount)
+    }
+
+    /// Linear blend towards `other`; a `weight` of 0 keeps `self`, 1 yields `other`.
+    pub fn mix(&self, other: &Color, weight: f64) -> Color {
+        let t = weight.clamp(0.0, 1.0);
+        let lerp = |a: u8, b: u8| clamp_channel(a as f64 + (b as f64 - a as f64) * t);
+        Color {
+            r: lerp(self.r, other.r),
+            g: lerp(self.g, other.g),
+            b: lerp(self.b, other.b),
+            a: self.a + (other.a - self.a) * t,
+        }
+    }
+
+    pub fn with_alpha(&self, alpha: f64) -> Color {
+        Color { a: alpha.clamp(0.0, 1.0), ..*self }
+    }
+}
+
+impl FromStr for Color {
+    type Err = ColorError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let value = s.trim().to_ascii_lowercase();
+        let color = if let Some(hex) = value.strip_prefix('#') {
+            Self::parse_hex(hex)
+        } else if let Some((name, args)) = value.split_once('(') {
+            args.strip_suffix(')')
+                .and_then(|args| Self::parse_function(name, args.trim()))
+        } else if value == "transparent" {
+            Some(Color { a: 0.0, ..Color::rgb(0, 0, 0) })
+        } else {
+            NAMED_COLORS
+                .iter()
+                .find(|(name, _)| *name == value)
+                .map(|(_, hex)| Color::from_hex(*hex))
+        };
+        color.ok_or_else(|| ColorError(s.to_string()))
+    }
+}
+
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_css())
+    }
+}
+
+impl serde::Serialize for Color {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_css())
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Color {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        value.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rgba(r: u8, g: u8, b: u8, a: f64) -> Color {
+        Color { r, g, b, a }
+    }
+
+    #[test]
+    fn parses_css_colors() {
+        let cases = [
+            ("#f80", rgba(255, 136, 0, 1.0)),
+            ("#f808", rgba(255, 136, 0, 136.0 / 255.0)),
+            ("#FF8800", rgba(255, 136, 0, 1.0)),
+            ("#ff880080", rgba(255, 136, 0, 128.0 / 255.0)),
+            ("rgb(255, 136, 0)", rgba(255, 136, 0, 1.0)),
+            ("rgba(255, 136, 0, 0.5)", rgba(255, 136, 0, 0.5)),
+            ("rgb(100%, 50%, 0%)", rgba(255, 128, 0, 1.0)),
+            ("rgb(255 136 0 / 25%)", rgba(255, 136, 0, 0.25)),
+            ("rgb(300, -20, 0)", rgba(255, 0, 0, 1.0)),
+            ("hsl(120, 100%, 50%)", rgba(0, 255, 0, 1.0)),
+            ("hsla(240, 100%, 50%, 0.5)", rgba(0, 0, 255, 0.5)),
+            ("hsl(0.5turn 100% 50%)", rgba(0, 255, 255, 1.0)),
+            ("hsl(120 100 50)", rgba(0, 255, 0, 1.0)),
+            ("  RebeccaPurple ", rgba(102, 51, 153, 1.0)),
+            ("transparent", rgba(0, 0, 0, 0.0)),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(input.parse::<Color>(), Ok(expected), "{}", input);
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_colors() {
+        let cases = [
+            "",
+            "#",
+            "#12",
+            "#12345",
+            "#1234567",
+            "#ggg",
+            "rgb(255, 50%, 0)",
+            "rgb(255 136)",
+            "rgb(255, 136, 0, 0.5, 1)",
+            "rgb(255 136, 0)",
+            "rgb(255 136 0 / )",
+            "rgb(255, 136, 0",
+            "rgb (255, 136, 0)",
+            "rgb(inf, 0, 0)",
+            "rgb(NaN 0 0)",
+            "hsl(120, 100, 50)",
+            "hsl(120%, 100%, 50%)",
+            "cmyk(0, 0, 0, 0)",
+            "notacolor",
+        ];
+        for input in cases {
+            assert!(input.parse::<Color>().is_err(), "{}", input);
+        }
+    }
+
+    #[test]
+    fn css_output_parses_back() {
+        for color in [rgba(255, 136, 0, 1.0), rgba(1, 2, 3, 0.5)] {
+            assert_eq!(color.to_css().parse::<Color>(), Ok(color));
+        }
+    }
+}
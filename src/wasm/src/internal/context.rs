@@ -1,33 +1,127 @@
-use std::cell::RefCell;
+use std::{cell::RefCell, rc::Rc};
 
 use super::{
-    event::Event,
-    geometry::{Circle, Line, Point, Rectangle, Text},
-    node::Node,
-    style::Style,
+    callback::{self, ContextHandle},
+    clipboard::{ClipboardData, PASTE_OFFSET},
+    color::Color,
+    event::{DeltaMode, Event, Modifiers, MouseButton, PointerInput, PointerType},
+    gesture::{Gesture, GestureRecognizer},
+    handle::{Handle, ResizeState, RotateState},
+    history::History,
+    keymap::{BuiltinAction, Keymap},
+    click::ClickRecognizer,
+    event_queue::EventQueue,
+    import::DropItem,
+    recording::{Input, Recording, Setup},
+    cursor::Cursor,
+    drag::{clamp_offset, handled_offset, DragBounds, DragOptions, DragPosition},
+    listener::{Emitted, EventKind, ListenerId, Listeners},
+    selection::{Marquee, MarqueeMode},
+    snap::{snap, Guide, SnapSettings},
+    geometry::{Circle, Image, Line, Point, Rectangle, Text, TextExtent},
+    node::{Node, NodeId, NodeRef},
+    style::{Style, DEFAULT_FONT_SIZE},
+    text_edit::TextEditor,
+    theme::{HandleShape, SelectionTheme},
+    zorder::{restack, Restack},
+    viewport::{Viewport, DEFAULT_MAX_ZOOM, DEFAULT_MIN_ZOOM},
 };
 use gloo_utils::format::JsValueSerdeExt;
-use std::collections::VecDeque;
+use std::collections::HashMap;
 use wasm_bindgen::prelude::*;
 
-pub(crate) const BOUNDING_BOX_MARGIN: f64 = 5.0;
+/// Pixels scrolled per line for wheels reporting `DeltaMode::Line`.
+const WHEEL_LINE_HEIGHT: f64 = 16.0;
+/// Zoom change per pixel of wheel travel; one notch of a typical mouse
+/// wheel (100px) zooms by about 20%.
+const WHEEL_ZOOM_SPEED: f64 = 0.002;
 
 #[wasm_bindgen]
 pub struct Context {
-    pub(crate) context: web_sys::CanvasRenderingContext2d,
-    pub(crate) background_color: Option<String>,
+    /// Where the scene is drawn; `None` for a headless context, which
+    /// processes input and keeps the scene but draws nothing.
+    surface: Option<web_sys::CanvasRenderingContext2d>,
+    /// Size `shape` reports when there is no surface.
+    headless_size: (f64, f64),
+    pub(crate) background_color: Option<Color>,
     pub(crate) nodes: Vec<Node>,
-    pub(crate) selected_node: Option<usize>,
+    pub(crate) selected_nodes: Vec<NodeId>,
     pub(crate) hovered_node: Option<usize>,
-    pub(crate) bounding_box_color: String,
-    pub(crate) event: VecDeque<Event>,
-    pub(crate) last_event: Option<Event>,
+    pub(crate) selection_theme: SelectionTheme,
+    pub(crate) event: EventQueue,
     pub(crate) drag_start_event: Option<Event>,
+    /// Offset, possibly snapped, the selection has been dragged by so far.
+    drag_offset: Point,
+    pub(crate) clicks: ClickRecognizer,
+    pub(crate) editing: Option<TextEditor>,
+    pub(crate) resizing: Option<ResizeState>,
+    pub(crate) rotating: Option<RotateState>,
+    pub(crate) modifiers: Modifiers,
+    pub(crate) marquee: Option<Marquee>,
+    pub(crate) marquee_mode: MarqueeMode,
+    pub(crate) viewport: Viewport,
+    pub(crate) gestures: GestureRecognizer,
+    pub(crate) min_zoom: f64,
+    pub(crate) max_zoom: f64,
+    /// Callbacks of nodes that take over wheel input while hovered.
+    pub(crate) wheel_handlers: HashMap<NodeId, js_sys::Function>,
+    pub(crate) drag_options: HashMap<NodeId, DragOptions>,
+    /// JS hooks that may move the proposed position of a dragged node.
+    pub(crate) drag_handlers: HashMap<NodeId, js_sys::Function>,
+    /// Offset each dragged node has actually moved by since the drag began,
+    /// which differs from `drag_offset` once its constraints kick in.
+    drag_applied: HashMap<NodeId, Point>,
+    pub(crate) keymap: Keymap,
+    /// Actions registered from JS, run when a key chord bound to their name
+    /// is pressed.
+    pub(crate) actions: HashMap<String, js_sys::Function>,
+    pub(crate) history: History,
+    /// Text of the last copy or cut, pasted when no text is given.
+    pub(crate) clipboard: Option<String>,
+    pub(crate) snap: SnapSettings,
+    pub(crate) snap_guides: Vec<Guide>,
+    pub(crate) listeners: Listeners,
+    /// Cursor last set on the canvas element.
+    cursor: Cursor,
+    /// Interactions waiting to be delivered to the listeners.
+    emitted: Vec<Emitted>,
+    /// Selection as last reported to the `select` listeners.
+    reported_selection: Vec<NodeId>,
+    /// Node under the pointer when the current drag began.
+    grabbed_node: Option<NodeId>,
+    /// Same, once the pointer has actually moved and `drag_start` was sent.
+    dragged_node: Option<NodeId>,
+    /// Scene point of data being dragged over the canvas from outside, if
+    /// any. The node under it is the hovered node.
+    drag_over: Option<Point>,
+    /// Input logged since `start_recording`, if recording.
+    recording: Option<Recording>,
+    /// `<img>` elements for the image nodes, by source, created on first
+    /// draw so they load in the background.
+    images: RefCell<HashMap<String, web_sys::HtmlImageElement>>,
+    /// Whether a drag, handle drag or text edit holds a history transaction
+    /// open, so the whole interaction is undone in one step.
+    is_interaction_recorded: bool,
+    /// Transactions opened from JS and not committed yet.
+    transaction_depth: usize,
+    /// Dropped with the context, telling `ContextHandle`s it is gone.
+    pub(crate) alive: Rc<()>,
+    next_node_id: NodeId,
+    /// Time of the frame being processed, as given to `process_input`.
+    /// Everything time-based reads this rather than the system clock, so
+    /// headless canvases and replays behave like the browser did.
+    pub(crate) now: f64,
     is_dirty: bool,
 }
 
+/// Keys the text editor acts on; with ctrl or cmd they go to the keymap.
+fn is_edit_key(key: &str) -> bool {
+    let named = ["Enter", "Escape", "ArrowLeft", "ArrowRight", "ArrowUp", "ArrowDown", "Home", "End", "Backspace", "Delete"];
+    named.contains(&key) || key.chars().count() == 1
+}
+
 impl Context {
-    pub fn new<S: AsRef<str>>(id: S, background_color: Option<String>) -> Option<Self> {
+    pub fn new<S: AsRef<str>>(id: S, background_color: Option<Color>) -> Option<Self> {
         let document = web_sys::window()?.document()?;
         let canvas = document.get_element_by_id(id.as_ref())?;
         let canvas: web_sys::HtmlCanvasElement =
@@ -47,152 +141,538 @@ impl Context {
             .dyn_into::<web_sys::CanvasRenderingContext2d>()
             .unwrap();
 
-        let nodes = Vec::new();
+        let mut canvas = Self::with_surface(Some(context), background_color);
+        let _ = canvas.clear_screen();
+
+        Some(canvas)
+    }
+
+    /// A context without a canvas, e.g. to replay recorded input under Node
+    /// where there is no DOM. Text is measured with estimated metrics and
+    /// path clips do not affect hit-testing.
+    pub fn headless() -> Self {
+        Self::with_surface(None, None)
+    }
 
-        let mut canvas = Context {
-            context,
+    fn with_surface(surface: Option<web_sys::CanvasRenderingContext2d>, background_color: Option<Color>) -> Self {
+        Context {
+            surface,
+            headless_size: (0.0, 0.0),
             background_color,
-            nodes,
-            selected_node: None,
+            nodes: Vec::new(),
+            selected_nodes: Vec::new(),
             hovered_node: None,
-            bounding_box_color: "#0ea5e9".to_string(),
-            event: VecDeque::new(),
-            last_event: None,
+            selection_theme: SelectionTheme::default(),
+            event: EventQueue::default(),
             drag_start_event: None,
+            drag_offset: Point { x: 0.0, y: 0.0 },
+            clicks: ClickRecognizer::default(),
+            editing: None,
+            resizing: None,
+            rotating: None,
+            modifiers: Modifiers::default(),
+            marquee: None,
+            marquee_mode: MarqueeMode::default(),
+            viewport: Viewport::default(),
+            gestures: GestureRecognizer::default(),
+            min_zoom: DEFAULT_MIN_ZOOM,
+            max_zoom: DEFAULT_MAX_ZOOM,
+            wheel_handlers: HashMap::new(),
+            drag_options: HashMap::new(),
+            drag_handlers: HashMap::new(),
+            drag_applied: HashMap::new(),
+            keymap: Keymap::default(),
+            actions: HashMap::new(),
+            history: History::default(),
+            clipboard: None,
+            snap: SnapSettings::default(),
+            snap_guides: Vec::new(),
+            listeners: Listeners::default(),
+            cursor: Cursor::Default,
+            emitted: Vec::new(),
+            reported_selection: Vec::new(),
+            grabbed_node: None,
+            dragged_node: None,
+            drag_over: None,
+            recording: None,
+            images: RefCell::new(HashMap::new()),
+            is_interaction_recorded: false,
+            transaction_depth: 0,
+            alive: Rc::new(()),
+            next_node_id: 0,
+            now: 0.0,
             is_dirty: true,
-        };
-        let _ = canvas.clear_screen();
+        }
+    }
 
-        Some(canvas)
+    /// The 2D context to draw with. Drawing only happens from `render`,
+    /// which skips it for headless contexts.
+    pub(crate) fn context(&self) -> &web_sys::CanvasRenderingContext2d {
+        self.surface.as_ref().expect("drawing requires a canvas")
     }
 
-    fn push_unique_event(&mut self, event: Event) {
-        if let Some(last_el) = self.event.back() {
-            if last_el == &event {
-                return;
+    pub(crate) fn canvas(&self) -> Option<web_sys::HtmlCanvasElement> {
+        self.surface.as_ref()?.canvas()
+    }
+
+    /// The element that loads `src`, started on the first call. `None`
+    /// without a DOM.
+    pub(crate) fn image_element(&self, src: &str) -> Option<web_sys::HtmlImageElement> {
+        if let Some(image) = self.images.borrow().get(src) {
+            return Some(image.clone());
+        }
+        let image = web_sys::HtmlImageElement::new().ok()?;
+        image.set_src(src);
+        self.images.borrow_mut().insert(src.to_string(), image.clone());
+        Some(image)
+    }
+
+    fn push_event(&mut self, event: Event) {
+        if self.event.push(event) {
+            self.is_dirty = true;
+        }
+    }
+
+    pub(crate) fn on_mouse_down(&mut self, x: f64, y: f64, ts: f64, modifiers: Option<Modifiers>, pointer: PointerInput) {
+        self.log_input(Input::MouseDown { x, y, ts, modifiers, pointer });
+        self.push_mouse_event(Event::from_mouse_down, Point { x, y }, ts, modifiers, pointer);
+    }
+
+    pub(crate) fn on_mouse_move(&mut self, x: f64, y: f64, ts: f64, modifiers: Option<Modifiers>, pointer: PointerInput) {
+        self.log_input(Input::MouseMove { x, y, ts, modifiers, pointer });
+        self.push_mouse_event(Event::from_mouse_move, Point { x, y }, ts, modifiers, pointer);
+    }
+
+    pub(crate) fn on_mouse_up(&mut self, x: f64, y: f64, ts: f64, modifiers: Option<Modifiers>, pointer: PointerInput) {
+        self.log_input(Input::MouseUp { x, y, ts, modifiers, pointer });
+        self.push_mouse_event(Event::from_mouse_up, Point { x, y }, ts, modifiers, pointer);
+    }
+
+    /// Queues a mouse event at the screen point `at` without logging it,
+    /// for events derived from input that was logged already.
+    fn push_mouse_event(
+        &mut self,
+        event: fn(f64, f64, f64) -> Event,
+        at: Point,
+        ts: f64,
+        modifiers: Option<Modifiers>,
+        pointer: PointerInput,
+    ) {
+        let (x, y) = self.viewport.to_scene(at.x, at.y);
+        self.push_event(event(x, y, ts).with_modifiers(modifiers).with_pointer(pointer));
+    }
+
+    pub(crate) fn on_pointer_down(&mut self, id: i32, x: f64, y: f64, ts: f64, modifiers: Option<Modifiers>, pointer: PointerInput) {
+        self.log_input(Input::PointerDown { id, x, y, ts, modifiers, pointer });
+        let gestures = self.gestures.pointer_down(id, Point { x, y }, ts, pointer.pointer_type, self.viewport);
+        self.apply_gestures(gestures, ts, modifiers, pointer);
+    }
+
+    pub(crate) fn on_pointer_move(&mut self, id: i32, x: f64, y: f64, ts: f64, modifiers: Option<Modifiers>, pointer: PointerInput) {
+        self.log_input(Input::PointerMove { id, x, y, ts, modifiers, pointer });
+        let gestures = self.gestures.pointer_move(id, Point { x, y }, pointer.pointer_type);
+        self.apply_gestures(gestures, ts, modifiers, pointer);
+    }
+
+    pub(crate) fn on_pointer_up(&mut self, id: i32, x: f64, y: f64, ts: f64, modifiers: Option<Modifiers>, pointer: PointerInput) {
+        self.log_input(Input::PointerUp { id, x, y, ts, modifiers, pointer });
+        let gestures = self.gestures.pointer_up(id, Point { x, y });
+        self.apply_gestures(gestures, ts, modifiers, pointer);
+    }
+
+    pub(crate) fn on_pointer_cancel(&mut self, id: i32, ts: f64) {
+        self.log_input(Input::PointerCancel { id, ts });
+        let gestures = self.gestures.pointer_cancel(id);
+        self.apply_gestures(gestures, ts, None, PointerInput::default());
+    }
+
+    /// Turns recognized gestures into the mouse events the rest of the
+    /// input handling understands, or applies them to the view directly.
+    /// Only the pointer events are logged, so a replay recognizes the same
+    /// gestures again instead of seeing them twice.
+    fn apply_gestures(&mut self, gestures: Vec<Gesture>, ts: f64, modifiers: Option<Modifiers>, pointer: PointerInput) {
+        for gesture in gestures {
+            match gesture {
+                Gesture::Press(point) => self.push_mouse_event(Event::from_mouse_down, point, ts, modifiers, pointer),
+                Gesture::Move(point) => self.push_mouse_event(Event::from_mouse_move, point, ts, modifiers, pointer),
+                Gesture::Release(point) => self.push_mouse_event(Event::from_mouse_up, point, ts, modifiers, pointer),
+                Gesture::Cancel(point) => {
+                    let (x, y) = self.viewport.to_scene(point.x, point.y);
+                    self.push_event(Event::from_mouse_cancel(x, y, ts));
+                }
+                Gesture::Transform { viewport, centroid } => {
+                    let scale = viewport.scale.clamp(self.min_zoom, self.max_zoom);
+                    self.viewport = viewport.anchored(centroid, centroid, scale, viewport.rotation);
+                    self.is_dirty = true;
+                }
+                Gesture::LongPress(point) => {
+                    let pointer = PointerInput { button: MouseButton::Right, ..pointer };
+                    self.push_mouse_event(Event::from_mouse_down, point, ts, modifiers, pointer);
+                    self.push_mouse_event(Event::from_mouse_up, point, ts, modifiers, pointer);
+                }
             }
         }
-        self.event.push_back(event);
+    }
+
+    /// Scrolls the view by the wheel deltas, or zooms it around the cursor
+    /// at `(x, y)` while ctrl or cmd is held, which is also how trackpad
+    /// pinches arrive. Nodes under the cursor with a wheel handler get the
+    /// first chance to consume the event.
+    pub(crate) fn on_wheel(&mut self, dx: f64, dy: f64, x: f64, y: f64, mode: DeltaMode, modifiers: Option<Modifiers>) {
+        self.log_input(Input::Wheel { dx, dy, x, y, mode, modifiers });
+        let modifiers = modifiers.unwrap_or(self.modifiers);
+        let unit = match mode {
+            DeltaMode::Pixel => 1.0,
+            DeltaMode::Line => WHEEL_LINE_HEIGHT,
+            DeltaMode::Page => self.shape().1,
+        };
+        let (dx, dy) = (dx * unit, dy * unit);
+
+        if self.delegate_wheel(dx, dy, x, y, modifiers) {
+            return;
+        }
+
+        if modifiers.command() {
+            let scale = (self.viewport.scale * (-dy * WHEEL_ZOOM_SPEED).exp()).clamp(self.min_zoom, self.max_zoom);
+            let anchor = Point { x, y };
+            self.viewport = self.viewport.anchored(anchor, anchor, scale, self.viewport.rotation);
+        } else {
+            // Shift turns a vertical wheel into a horizontal one.
+            let (dx, dy) = if modifiers.shift && dx == 0.0 { (dy, 0.0) } else { (dx, dy) };
+            self.viewport.offset_x -= dx;
+            self.viewport.offset_y -= dy;
+        }
+        self.is_dirty = true;
+    }
+
+    /// Returns the view to the unpanned, unzoomed and unrotated one.
+    pub(crate) fn reset_viewport(&mut self) {
+        self.viewport = Viewport::default();
         self.is_dirty = true;
     }
 
-    pub fn on_mouse_down(&mut self, x: f64, y: f64, ts: f64) {
-        self.push_unique_event(Event::from_mouse_down(x, y, ts));
+    /// Changes the zoom limits, zooming around the middle of the canvas if
+    /// the current zoom falls outside them.
+    pub(crate) fn set_zoom_limits(&mut self, min: f64, max: f64) {
+        self.min_zoom = min;
+        self.max_zoom = max;
+        let (width, height) = self.shape();
+        let center = Point { x: width / 2.0, y: height / 2.0 };
+        let scale = self.viewport.scale.clamp(min, max);
+        self.viewport = self.viewport.anchored(center, center, scale, self.viewport.rotation);
+        self.is_dirty = true;
     }
 
-    pub fn on_mouse_move(&mut self, x: f64, y: f64, ts: f64) {
-        self.push_unique_event(Event::from_mouse_move(x, y, ts));
+    /// Offers the wheel event to the wheel handlers of the nodes under the
+    /// cursor, topmost first, until one returns `true`.
+    fn delegate_wheel(&mut self, dx: f64, dy: f64, x: f64, y: f64, modifiers: Modifiers) -> bool {
+        if self.wheel_handlers.is_empty() {
+            return false;
+        }
+
+        let (sx, sy) = self.viewport.to_scene(x, y);
+        let probe = Event::from_mouse_move(sx, sy, 0.0);
+        let handlers: Vec<(NodeId, js_sys::Function)> = (0..self.nodes.len())
+            .rev()
+            .filter(|&index| self.hit_test(index, &probe))
+            .filter_map(|index| {
+                let id = self.nodes[index].id();
+                self.wheel_handlers.get(&id).map(|handler| (id, handler.clone()))
+            })
+            .collect();
+
+        handlers.into_iter().any(|(id, handler)| {
+            let payload = JsValue::from_serde(&serde_json::json!({
+                "node": id,
+                "dx": dx,
+                "dy": dy,
+                "x": sx,
+                "y": sy,
+                "shift": modifiers.shift,
+                "ctrl": modifiers.ctrl,
+                "alt": modifiers.alt,
+                "meta": modifiers.meta,
+            }))
+            .unwrap_or(JsValue::NULL);
+            callback::call_hook(&handler, &payload)
+                .map(|handled| handled.is_truthy())
+                .unwrap_or(false)
+        })
     }
 
-    pub fn on_mouse_up(&mut self, x: f64, y: f64, ts: f64) {
-        self.push_unique_event(Event::from_mouse_up(x, y, ts));
+    /// Queues a key down and returns whether the canvas, as it is now, will
+    /// act on it: type it into the edited text or run its keymap action.
+    pub(crate) fn on_key_down(&mut self, key: String, ts: f64, modifiers: Option<Modifiers>) -> bool {
+        self.log_input(Input::KeyDown { key: key.clone(), ts, modifiers });
+        let event = Event::from_key_down(key, ts).with_modifiers(modifiers);
+        let consumed = self.consumes_key(&event);
+        self.push_event(event);
+        consumed
     }
 
-    pub fn on_key_down(&mut self, key: String, ts: f64) {
-        self.push_unique_event(Event::from_key_down(key, ts));
+    fn consumes_key(&self, event: &Event) -> bool {
+        let key = event.key.as_deref().unwrap_or_default();
+        let modifiers = event.modifiers.unwrap_or_else(|| self.modifiers.after_key(event));
+        if self.editing.is_some() && !modifiers.command() {
+            return is_edit_key(key);
+        }
+        self.keymap
+            .action(key, modifiers)
+            .is_some_and(|action| BuiltinAction::from_name(action).is_some() || self.actions.contains_key(action))
     }
 
-    pub fn on_key_up(&mut self, key: String, ts: f64) {
-        self.push_unique_event(Event::from_key_up(key, ts));
+    pub(crate) fn on_key_up(&mut self, key: String, ts: f64, modifiers: Option<Modifiers>) {
+        self.log_input(Input::KeyUp { key: key.clone(), ts, modifiers });
+        self.push_event(Event::from_key_up(key, ts).with_modifiers(modifiers));
     }
 
-    pub fn on_key_press(&mut self, key: String, ts: f64) {
-        self.push_unique_event(Event::from_key_press(key, ts));
+    pub(crate) fn on_key_press(&mut self, key: String, ts: f64, modifiers: Option<Modifiers>) {
+        self.log_input(Input::KeyPress { key: key.clone(), ts, modifiers });
+        self.push_event(Event::from_key_press(key, ts).with_modifiers(modifiers));
     }
 
     pub fn shape(&self) -> (f64, f64) {
-        let canvas = self.context.canvas();
-        if let Some(canvas) = canvas {
+        if let Some(canvas) = self.canvas() {
             return (canvas.width() as f64, canvas.height() as f64);
         }
 
-        (0.0, 0.0)
+        self.headless_size
+    }
+
+    /// Resizes the canvas element, or the size a headless context reports.
+    pub(crate) fn resize(&mut self, width: f64, height: f64) {
+        match self.canvas() {
+            Some(canvas) => {
+                canvas.set_width(width as u32);
+                canvas.set_height(height as u32);
+            }
+            None => self.headless_size = (width, height),
+        }
+        self.is_dirty = true;
+    }
+
+    /// Measures a single line of text with the font described by `style`.
+    /// Falls back to an estimate when the backend cannot report metrics.
+    pub fn measure_text(&self, text: &str, style: &Style) -> TextExtent {
+        let estimate = || TextExtent::estimate(text, style.font_size.unwrap_or(DEFAULT_FONT_SIZE));
+        let Some(context) = &self.surface else {
+            return estimate();
+        };
+        context.save();
+        context.set_font(&style.font());
+        let metrics = context.measure_text(text);
+        context.restore();
+
+        match metrics {
+            Ok(metrics) => TextExtent::from_metrics(&metrics),
+            Err(_) => estimate(),
+        }
     }
 
     pub fn clear_screen(&mut self) -> Option<()> {
-        let canvas = self.context.canvas()?;
+        let canvas = self.canvas()?;
         if let Some(color) = &self.background_color {
-            self.context.set_fill_style(&JsValue::from(color));
-            self.context
+            self.context().set_fill_style_str(&color.to_css());
+            self.context()
                 .fill_rect(0.0, 0.0, canvas.width() as f64, canvas.height() as f64);
         } else {
-            self.context
+            self.context()
                 .clear_rect(0.0, 0.0, canvas.width() as f64, canvas.height() as f64);
         }
         Some(())
     }
 
+    /// The input half of `render`: handles everything queued as of `now` and
+    /// notifies the listeners, without drawing.
+    pub(crate) fn process_input(&mut self, now: f64) {
+        self.log_input(Input::Frame { now });
+        self.now = now;
+        let gestures = self.gestures.poll(now);
+        self.apply_gestures(gestures, now, None, PointerInput::default());
+
+        while let Some(event) = self.event.pop() {
+            self.handle_event(event);
+        }
+        // Nodes hidden or locked since they were selected drop out.
+        let selection = std::mem::take(&mut self.selected_nodes);
+        self.selected_nodes = selection.into_iter().filter(|&id| self.is_selectable(id)).collect();
+        self.dispatch_emitted();
+    }
+
+    fn log_input(&mut self, input: Input) {
+        let Some(recording) = &mut self.recording else {
+            return;
+        };
+        // Frames with no input in between only matter for the latest time.
+        if let (Input::Frame { .. }, Some(Input::Frame { .. })) = (&input, recording.inputs.last()) {
+            recording.inputs.pop();
+        }
+        recording.inputs.push(input);
+    }
+
+    /// Starts logging input, along with the current scene and the settings
+    /// that affect how input is handled. Restarts a recording in progress.
+    pub(crate) fn start_recording(&mut self) {
+        let setup = Setup {
+            selection: self.selected_nodes.clone(),
+            last_id: self.next_node_id,
+            viewport: self.viewport,
+            snap: self.snap.clone(),
+            clicks: self.clicks.settings.clone(),
+            theme: self.selection_theme.clone(),
+            keymap: self.keymap.clone(),
+            marquee_mode: self.marquee_mode,
+            min_zoom: self.min_zoom,
+            max_zoom: self.max_zoom,
+            size: self.shape(),
+        };
+        self.recording = Some(Recording::new(&self.nodes, setup));
+    }
+
+    /// Stops recording and returns the log as JSON.
+    pub(crate) fn stop_recording(&mut self) -> Option<String> {
+        self.recording.take().map(|recording| recording.to_json())
+    }
+
+    pub(crate) fn is_recording(&self) -> bool {
+        self.recording.is_some()
+    }
+
+    /// Replaces the scene with the one a recording started from, restores
+    /// the settings and canvas size it was made with, and feeds it the
+    /// recorded input, processing it frame by frame as it was. Any
+    /// interaction in progress and the history are dropped first. Input after
+    /// the last frame stays queued for the next `render`. JS hooks, actions,
+    /// listeners and drag options are not part of the recording and have to
+    /// be set up again to get the same result.
+    pub(crate) fn replay(&mut self, json: &str) -> Result<(), String> {
+        let recording = Recording::from_json(json)?;
+        let setup = recording.setup;
+
+        let ids = recording.scene.ids();
+        let nodes = recording.scene.to_nodes(&ids, |_| false);
+        self.measure_texts(&nodes);
+        self.nodes = nodes;
+        self.next_node_id = setup.last_id;
+        self.selected_nodes = setup.selection;
+        self.reported_selection = self.selected_nodes.clone();
+        self.viewport = setup.viewport;
+        self.snap = setup.snap;
+        self.clicks.settings = setup.clicks;
+        self.selection_theme = setup.theme;
+        self.keymap = setup.keymap;
+        self.marquee_mode = setup.marquee_mode;
+        self.min_zoom = setup.min_zoom;
+        self.max_zoom = setup.max_zoom;
+        self.resize(setup.size.0, setup.size.1);
+
+        self.gestures = GestureRecognizer::default();
+        self.event = EventQueue::default();
+        self.modifiers = Modifiers::default();
+        self.cancel_pointer_interaction();
+        self.drag_offset = Point { x: 0.0, y: 0.0 };
+        self.drag_applied.clear();
+        self.grabbed_node = None;
+        self.dragged_node = None;
+        self.drag_over = None;
+        self.emitted.clear();
+        self.editing = None;
+        self.hovered_node = None;
+        self.clear_history();
+        self.is_dirty = true;
+
+        for input in recording.inputs {
+            match input {
+                Input::MouseDown { x, y, ts, modifiers, pointer } => self.on_mouse_down(x, y, ts, modifiers, pointer),
+                Input::MouseMove { x, y, ts, modifiers, pointer } => self.on_mouse_move(x, y, ts, modifiers, pointer),
+                Input::MouseUp { x, y, ts, modifiers, pointer } => self.on_mouse_up(x, y, ts, modifiers, pointer),
+                Input::PointerDown { id, x, y, ts, modifiers, pointer } => {
+                    self.on_pointer_down(id, x, y, ts, modifiers, pointer)
+                }
+                Input::PointerMove { id, x, y, ts, modifiers, pointer } => {
+                    self.on_pointer_move(id, x, y, ts, modifiers, pointer)
+                }
+                Input::PointerUp { id, x, y, ts, modifiers, pointer } => self.on_pointer_up(id, x, y, ts, modifiers, pointer),
+                Input::PointerCancel { id, ts } => self.on_pointer_cancel(id, ts),
+                Input::Wheel { dx, dy, x, y, mode, modifiers } => self.on_wheel(dx, dy, x, y, mode, modifiers),
+                Input::KeyDown { key, ts, modifiers } => {
+                    self.on_key_down(key, ts, modifiers);
+                }
+                Input::KeyUp { key, ts, modifiers } => self.on_key_up(key, ts, modifiers),
+                Input::KeyPress { key, ts, modifiers } => self.on_key_press(key, ts, modifiers),
+                Input::DragOver { x, y } => self.on_drag_over(x, y),
+                Input::DragLeave => self.on_drag_leave(),
+                Input::Drop { x, y, items } => {
+                    let _ = self.on_drop(x, y, items);
+                }
+                Input::Frame { now } => self.process_input(now),
+            }
+        }
+        Ok(())
+    }
+
+    /// The current scene in the clipboard format, e.g. to check that a
+    /// replay ended where the recording did.
+    pub(crate) fn scene_json(&self) -> String {
+        ClipboardData::from_nodes(&self.nodes).to_json()
+    }
+
+    fn measure_texts(&self, nodes: &[Node]) {
+        for node in nodes {
+            if let Node::Text(text, style, ..) = node {
+                *text.shape.borrow_mut() = Some(self.measure_text(&text.text, style));
+            }
+        }
+    }
+
     pub fn render(&mut self) -> Option<()> {
         // if self.nodes.iter().all(|node| !node.is_dirty()) && !self.is_dirty {
         //     return Some(());
         // }
 
-        self.clear_screen()?;
-        let mut has_any_node_selected = false;
-        let mut has_hovered_node = false;
-
         // super::log_utils::log(&format!("event: {:?}", self.event));
 
-        loop {
-            let event = self.event.pop_front();
-            if event.is_none() {
-                break;
-            }
-
-            let event = unsafe { event.unwrap_unchecked() };
+        self.process_input(js_sys::Date::now());
+        self.clear_screen()?;
 
-            for (index, node) in self.nodes.iter().enumerate().rev() {
-                if node.is_hovered(&event) {
-                    if event.is_click(&mut self.last_event) {
-                        self.selected_node = Some(index);
-                        has_any_node_selected = true;
-                    }
-                    self.hovered_node = Some(index);
-                    has_hovered_node = true;
-                    break;
-                }
-            }
+        self.context().save();
+        self.viewport.apply(self.context());
 
-            if !has_hovered_node {
-                self.hovered_node = None;
+        let is_multi_selection = self.selected_nodes.len() > 1;
+        for (index, node) in self.nodes.iter().enumerate() {
+            if !node.get_style().is_visible() {
+                continue;
             }
+            let is_selected = self.is_selected(node.id());
+            let is_hovered = (Some(index) == self.hovered_node && !node.get_style().is_locked())
+                || (is_selected && is_multi_selection);
+            self.render_node(node, is_hovered, is_selected && !is_multi_selection);
+        }
 
-            if !has_any_node_selected {
-                if event.is_click(&mut self.last_event) {
-                    self.selected_node = None;
-                }
+        if is_multi_selection {
+            if let Some(bounds) = self.selection_bounds() {
+                self.draw_bounding_box(bounds, &Style::default(), true);
             }
+        }
 
-            self.last_event = Some(event.clone());
+        if let Some(marquee) = &self.marquee {
+            self.draw_marquee(marquee);
+        }
 
-            if event.mouse_down {
-                self.drag_start_event = Some(event.clone());
-                self.selected_node = self.hovered_node;
-            } else if event.mouse_moved && self.drag_start_event.is_some() {
-                // Do nothing
-            } else {
-                self.drag_start_event = None;
-            }
+        for guide in &self.snap_guides {
+            guide.render(self.context(), self.snap.guide_color, self.viewport.scale);
+        }
 
-            if let Some(drag_start_event) = &self.drag_start_event {
-                self.selected_node = self.selected_node.or(self.hovered_node);
-                if let Some(selected_node) = self.selected_node {
-                    if event.mouse_moved {
-                        if let Some(node) = self.nodes.get_mut(selected_node) {
-                            let dx = event.mouse_x - drag_start_event.mouse_x;
-                            let dy = event.mouse_y - drag_start_event.mouse_y;
-                            node.translate(dx, dy);
-                            self.drag_start_event = Some(event.clone());
-                        }
-                    }
-                }
+        if let Some(editor) = &self.editing {
+            if let Some(Node::Text(text, style, ..)) = self.get_node(editor.node) {
+                editor.render(self, text, style);
             }
         }
 
-        for (index, node) in self.nodes.iter().enumerate() {
-            let is_selected = Some(index) == self.selected_node;
-            self.render_node(node, Some(index) == self.hovered_node, is_selected);
+        if let Some(rotate) = &self.rotating {
+            self.draw_rotation_readout(rotate);
         }
+        self.context().restore();
 
         self.nodes.iter_mut().for_each(|node| {
             node.set_is_dirty(false);
@@ -203,98 +683,1643 @@ impl Context {
         Some(())
     }
 
-    pub(crate) fn render_node(&self, node: &Node, has_bounding_box: bool, is_selected: bool) {
-        match node {
-            Node::Line(line, style, is_dirty) => {
-                if *is_dirty || self.is_dirty {
-                    line.render(&self, style, has_bounding_box, is_selected);
-                }
+    fn handle_event(&mut self, event: Event) {
+        self.modifiers = event.modifiers.unwrap_or_else(|| self.modifiers.after_key(&event));
+
+        // A press may start a drag, handle drag or text edit, recorded as one
+        // step until it ends. Other input outside an interaction either
+        // changes no node or records its own changes, like the keymap does.
+        if event.mouse_down && !self.is_interaction_recorded {
+            self.history.begin(&self.nodes);
+            self.is_interaction_recorded = true;
+        }
+
+        let hovered_before = self.hovered_id();
+        let (x, y) = (event.mouse_x, event.mouse_y);
+        let pointer_type = event.key.is_none().then_some(event.pointer.pointer_type);
+        if event.key.is_some() {
+            self.handle_key_event(&event);
+        } else {
+            self.handle_pointer_event(event);
+        }
+
+        let hovered = self.hovered_id();
+        if hovered != hovered_before {
+            if let Some(id) = hovered_before {
+                self.emit(Emitted::new(EventKind::HoverLeave, Some(id), x, y));
             }
-            Node::Rect(rect, style, is_dirty) => {
-                if *is_dirty || self.is_dirty {
-                    rect.render(&self, style, has_bounding_box, is_selected);
-                }
+            if let Some(id) = hovered {
+                self.emit(Emitted::new(EventKind::HoverEnter, Some(id), x, y));
             }
-            Node::Circle(circle, style, is_dirty) => {
-                if *is_dirty || self.is_dirty {
-                    circle.render(&self, style, has_bounding_box, is_selected);
-                }
+        }
+        if self.drag_start_event.is_none() {
+            if let Some(id) = self.dragged_node.take() {
+                self.emit_drag(EventKind::DragEnd, id, x, y);
             }
-            Node::Text(text, style, is_dirty) => {
-                if *is_dirty || self.is_dirty {
-                    text.render(&self, style, has_bounding_box, is_selected);
-                }
+        }
+        // After a finished drag is cleared, so the cursor does not stay `move`.
+        if let Some(pointer_type) = pointer_type {
+            self.set_cursor(self.cursor_at(x, y, pointer_type));
+        }
+
+        if self.is_interaction_recorded && !self.is_interacting() {
+            self.history.commit(&self.nodes);
+            self.is_interaction_recorded = false;
+            self.forget_removed_nodes();
+        }
+    }
+
+    /// Subscribes `callback` to the event `name`, on the whole canvas or on
+    /// one node.
+    pub(crate) fn subscribe(
+        &mut self,
+        name: &str,
+        node: Option<NodeId>,
+        callback: js_sys::Function,
+    ) -> Result<ListenerId, String> {
+        let kind = EventKind::from_name(name).ok_or_else(|| format!("unknown event `{}`", name))?;
+        Ok(self.listeners.add(kind, node, callback))
+    }
+
+    /// Cursor for the pointer at `(x, y)`: the one matching the running
+    /// interaction, else that of a handle of the selected node, else the
+    /// hovered node's own.
+    fn cursor_at(&self, x: f64, y: f64, pointer_type: PointerType) -> Cursor {
+        let rotation = |node: &Node| node.get_style().rotation.map_or(0.0, |(angle, _)| angle);
+
+        if let Some(resize) = &self.resizing {
+            return self
+                .get_node(resize.node)
+                .map_or(Cursor::Default, |node| resize.handle.cursor(rotation(node)));
+        }
+        if self.rotating.is_some() {
+            return Cursor::Grabbing;
+        }
+        if self.dragged_node.is_some() {
+            return Cursor::Move;
+        }
+        if let Some(node) = self.single_selection() {
+            if let Some(handle) = Handle::hit(node, &self.selection_theme, x, y, pointer_type) {
+                return handle.cursor(rotation(node));
             }
         }
+
+        let Some(id) = self.hovered_id() else {
+            return Cursor::Default;
+        };
+        if self.editing.as_ref().is_some_and(|editor| editor.node == id) {
+            return Cursor::Text;
+        }
+        self.get_node(id)
+            .and_then(|node| node.get_style().cursor)
+            .unwrap_or_default()
     }
 
-    pub(crate) fn draw_bounding_box(
-        &self,
-        rect: Rectangle,
-        color: &str,
-        style: &Style,
-        is_selected: bool,
-    ) {
-        self.context.save();
-        if let Some((x, y)) = style.translate {
-            let _ = self.context.translate(x, y);
-        }
-        if let Some((rotation, control_point)) = style.rotation {
-            let (x, y) = control_point.resolve_coords(rect.left, rect.top, rect.width, rect.height);
-            let _ = self.context.translate(x, y);
-            let _ = self.context.rotate(rotation);
-            let _ = self.context.translate(-x, -y);
-        }
-
-        self.context.begin_path();
-        self.context.set_stroke_style(&JsValue::from(color));
-        self.context
-            .set_line_width(if is_selected { 2.5 } else { 1.0 });
-        self.context.rect(
-            rect.left - BOUNDING_BOX_MARGIN,
-            rect.top - BOUNDING_BOX_MARGIN,
-            rect.width + BOUNDING_BOX_MARGIN * 2.0,
-            rect.height + BOUNDING_BOX_MARGIN * 2.0,
-        );
-        self.context.close_path();
-        self.context.stroke();
-        self.context.restore();
+    fn set_cursor(&mut self, cursor: Cursor) {
+        if cursor == self.cursor {
+            return;
+        }
+        if let Some(canvas) = self.canvas() {
+            let _ = canvas.style().set_property("cursor", cursor.to_css());
+        }
+        self.cursor = cursor;
     }
 
-    pub fn add_node(&mut self, node: Node) {
-        self.nodes.push(node);
+    fn hovered_id(&self) -> Option<NodeId> {
+        self.hovered_node.and_then(|index| self.nodes.get(index)).map(|node| node.id())
     }
 
-    pub fn add_line(&mut self, start: Point, end: Point, style: Style) {
-        let line = Line { start, end };
-        let node = Node::Line(line, style, true);
-        self.add_node(node);
+    fn emit(&mut self, emitted: Emitted) {
+        if !self.listeners.is_empty() {
+            self.emitted.push(emitted);
+        }
     }
 
-    pub fn add_rect(&mut self, top: f64, left: f64, width: f64, height: f64, style: Style) {
-        let rect = Rectangle {
-            top,
-            left,
-            width,
-            height,
+    fn emit_drag(&mut self, kind: EventKind, node: NodeId, x: f64, y: f64) {
+        let mut emitted = Emitted::new(kind, Some(node), x, y);
+        emitted.targets = self.selected_nodes.clone();
+        emitted.dx = self.drag_offset.x;
+        emitted.dy = self.drag_offset.y;
+        self.emit(emitted);
+    }
+
+    /// Selects the hovered node unless it already is, so the menu acts on
+    /// what was right-clicked, and emits `context_menu` for it.
+    fn open_context_menu(&mut self, x: f64, y: f64) {
+        let node = self.hovered_id();
+        if let Some(id) = node {
+            if !self.is_selected(id) && self.is_selectable(id) {
+                self.selected_nodes = vec![id];
+            }
+        }
+        self.emit(Emitted::new(EventKind::ContextMenu, node, x, y));
+    }
+
+    /// Built-in actions that would currently do something, in the order a
+    /// context menu would list them.
+    pub(crate) fn available_actions(&self) -> Vec<&'static str> {
+        let mut actions = Vec::new();
+        if !self.selected_nodes.is_empty() {
+            actions.extend(["cut", "copy"]);
+        }
+        if self.clipboard.is_some() {
+            actions.push("paste");
+        }
+        if !self.selected_nodes.is_empty() {
+            actions.extend(["duplicate", "delete", "bring_to_front", "bring_forward", "send_backward", "send_to_back"]);
+        }
+        if self.history.can_undo() {
+            actions.push("undo");
+        }
+        if self.history.can_redo() {
+            actions.push("redo");
+        }
+        actions
+    }
+
+    /// Queues the listeners of everything emitted since the last call, plus
+    /// `select` when the selection changed in the meantime. They run once the
+    /// canvas method that got here returns.
+    fn dispatch_emitted(&mut self) {
+        if self.selected_nodes != self.reported_selection {
+            let newly_selected: Vec<NodeId> = self
+                .selected_nodes
+                .iter()
+                .copied()
+                .filter(|id| !self.reported_selection.contains(id))
+                .collect();
+            let mut emitted = Emitted::new(EventKind::Select, self.selected_nodes.first().copied(), 0.0, 0.0);
+            emitted.targets = newly_selected;
+            self.emit(emitted);
+            self.reported_selection = self.selected_nodes.clone();
+        }
+
+        for emitted in std::mem::take(&mut self.emitted) {
+            let callbacks = self.listeners.callbacks(&emitted);
+            if callbacks.is_empty() {
+                continue;
+            }
+            let payload = self.event_payload(&emitted);
+            for listener in callbacks {
+                callback::defer(&listener, &payload);
+            }
+        }
+    }
+
+    /// `{ type, node, nodes, x, y, dx, dy }` handed to listeners, with node
+    /// handles for `node` and for every target in `nodes`. `context_menu`
+    /// also gets the canvas position and the available actions.
+    fn event_payload(&self, emitted: &Emitted) -> JsValue {
+        let payload = js_sys::Object::new();
+        let set = |key: &str, value: JsValue| {
+            let _ = js_sys::Reflect::set(&payload, &JsValue::from_str(key), &value);
         };
-        let node = Node::Rect(rect, style, true);
-        self.add_node(node);
+        let node_ref = |id: NodeId| JsValue::from(NodeRef::new(self, id));
+
+        set("type", JsValue::from_str(emitted.kind.name()));
+        set("node", emitted.node.map(node_ref).unwrap_or(JsValue::NULL));
+        let nodes: js_sys::Array = match emitted.kind {
+            EventKind::Select | EventKind::ContextMenu => self.selected_nodes.iter().map(|&id| node_ref(id)).collect(),
+            _ => emitted.targets.iter().map(|&id| node_ref(id)).collect(),
+        };
+        set("nodes", nodes.into());
+        set("x", JsValue::from_f64(emitted.x));
+        set("y", JsValue::from_f64(emitted.y));
+        set("dx", JsValue::from_f64(emitted.dx));
+        set("dy", JsValue::from_f64(emitted.dy));
+        if emitted.kind == EventKind::ContextMenu {
+            let (screen_x, screen_y) = self.viewport.to_screen(emitted.x, emitted.y);
+            set("screen_x", JsValue::from_f64(screen_x));
+            set("screen_y", JsValue::from_f64(screen_y));
+            let actions: js_sys::Array = self.available_actions().into_iter().map(JsValue::from_str).collect();
+            set("actions", actions.into());
+        }
+        payload.into()
     }
 
-    pub fn add_circle(&mut self, center: Point, radius: f64, style: Style) {
-        let circle = Circle { center, radius };
-        let node = Node::Circle(circle, style, true);
-        self.add_node(node);
+    fn is_interacting(&self) -> bool {
+        self.drag_start_event.is_some() || self.resizing.is_some() || self.rotating.is_some() || self.editing.is_some()
     }
 
-    pub fn add_text(&mut self, text: String, position: Point, style: Style) {
-        let text = Text {
-            text,
-            position,
-            shape: RefCell::new(None),
+    /// Runs `f` as one undoable step, or as part of the enclosing one.
+    pub(crate) fn record<R>(&mut self, f: impl FnOnce(&mut Self) -> R) -> R {
+        self.history.begin(&self.nodes);
+        let result = f(self);
+        self.history.commit(&self.nodes);
+        self.forget_removed_nodes();
+        result
+    }
+
+    pub(crate) fn begin_transaction(&mut self) {
+        self.history.begin(&self.nodes);
+        self.transaction_depth += 1;
+    }
+
+    pub(crate) fn commit_transaction(&mut self) {
+        if self.transaction_depth > 0 {
+            self.transaction_depth -= 1;
+            self.history.commit(&self.nodes);
+            self.forget_removed_nodes();
+        }
+    }
+
+    pub(crate) fn clear_history(&mut self) {
+        self.history.clear();
+        self.transaction_depth = 0;
+        self.is_interaction_recorded = false;
+        self.forget_removed_nodes();
+    }
+
+    /// Applies a change made through a `NodeRef`. Inside a JS transaction it
+    /// joins that; otherwise it is an undo step of its own, merged with the
+    /// changes to the same node right before it, and never joins a drag or
+    /// other interaction in progress.
+    pub(crate) fn update_node(&mut self, id: NodeId, change: impl Fn(&mut Node)) {
+        let is_in_transaction = self.transaction_depth > 0;
+        let Some(node) = self.get_node_mut(id) else {
+            return;
         };
-        let node = Node::Text(text, style, true);
-        self.add_node(node);
+        let before = (!is_in_transaction).then(|| node.clone());
+        change(node);
+        node.set_is_dirty(true);
+        self.measure_node(id);
+        let Some(after) = self.get_node(id).cloned() else {
+            return;
+        };
+        if let Some(before) = before {
+            self.history.record_change(before, after, change);
+        }
+    }
+
+    pub(crate) fn undo(&mut self) -> bool {
+        let is_undone = self.history.undo(&mut self.nodes);
+        if is_undone {
+            self.after_history_change();
+        }
+        is_undone
+    }
+
+    pub(crate) fn redo(&mut self) -> bool {
+        let is_redone = self.history.redo(&mut self.nodes);
+        if is_redone {
+            self.after_history_change();
+        }
+        is_redone
+    }
+
+    /// Drops interaction state that may refer to nodes an undo or redo
+    /// removed.
+    fn after_history_change(&mut self) {
+        let nodes = &self.nodes;
+        self.selected_nodes.retain(|&id| nodes.iter().any(|node| node.id() == id));
+        self.hovered_node = None;
+        self.marquee = None;
+        self.is_dirty = true;
+    }
+
+    fn handle_pointer_event(&mut self, event: Event) {
+        if event.mouse_cancelled {
+            self.cancel_pointer_interaction();
+            return;
+        }
+
+        // Only the primary button edits, selects and drags; the others are
+        // left free for things like panning and context menus.
+        if event.pointer.button != MouseButton::Left {
+            self.hovered_node = (0..self.nodes.len()).rev().find(|&index| self.hit_test(index, &event));
+            if event.mouse_down && event.pointer.button == MouseButton::Right {
+                self.open_context_menu(event.mouse_x, event.mouse_y);
+            }
+            return;
+        }
+
+        if self.editing.is_some() && self.handle_edit_mouse_event(&event) {
+            return;
+        }
+
+        if self.handle_transform_event(&event) {
+            return;
+        }
+
+        let point = Point { x: event.mouse_x, y: event.mouse_y };
+        let scale = self.viewport.scale;
+        let clicks = if event.mouse_down {
+            self.clicks.press(point, event.timestamp, event.pointer.pointer_type);
+            None
+        } else if event.mouse_up {
+            self.clicks.release(point, event.timestamp, scale)
+        } else {
+            self.clicks.moved(point, scale);
+            None
+        };
+        self.hovered_node = (0..self.nodes.len()).rev().find(|&index| self.hit_test(index, &event));
+        let hovered_id = self.hovered_node.map(|index| self.nodes[index].id());
+        // A locked node stops the pointer but acts like empty canvas for
+        // selecting, so a marquee can start on top of it.
+        let selectable_id = hovered_id.filter(|&id| self.is_selectable(id));
+        let is_additive = self.modifiers.shift || self.modifiers.command();
+
+        if let Some(count) = clicks {
+            if let (Some(id), false) = (selectable_id, is_additive) {
+                self.selected_nodes = vec![id];
+            }
+            self.emit(Emitted::new(EventKind::Click, hovered_id, event.mouse_x, event.mouse_y));
+            if count == 2 {
+                self.emit(Emitted::new(EventKind::DblClick, hovered_id, event.mouse_x, event.mouse_y));
+                self.start_editing(&event);
+            }
+        }
+
+        if event.mouse_down {
+            self.drag_start_event = None;
+            self.drag_offset = Point { x: 0.0, y: 0.0 };
+            self.drag_applied.clear();
+            self.grabbed_node = selectable_id;
+            match selectable_id {
+                Some(id) if is_additive => {
+                    self.toggle_selection(id);
+                    if self.is_selected(id) && self.is_draggable(id) {
+                        self.drag_start_event = Some(event.clone());
+                    }
+                }
+                Some(id) => {
+                    if !self.is_selected(id) {
+                        self.selected_nodes = vec![id];
+                    }
+                    if self.is_draggable(id) {
+                        self.drag_start_event = Some(event.clone());
+                    }
+                }
+                None => {
+                    if !is_additive {
+                        self.selected_nodes.clear();
+                    }
+                    self.marquee = Some(Marquee::new(event.mouse_x, event.mouse_y, self.selected_nodes.clone()));
+                }
+            }
+        } else if event.mouse_moved && self.drag_start_event.is_some() {
+            // Do nothing
+        } else {
+            self.drag_start_event = None;
+        }
+
+        if let Some(marquee) = &mut self.marquee {
+            marquee.end = (event.mouse_x, event.mouse_y).into();
+            let marquee = marquee.clone();
+            let mut selection = marquee.base.clone();
+            for node in &self.nodes {
+                if !self.is_selectable(node.id()) {
+                    continue;
+                }
+                if marquee.selects(node.get_bounds(), self.marquee_mode) && !selection.contains(&node.id()) {
+                    selection.push(node.id());
+                }
+            }
+            self.selected_nodes = selection;
+            if event.mouse_up {
+                self.marquee = None;
+            }
+        }
+
+        if let Some(drag_start_event) = &self.drag_start_event {
+            if event.mouse_moved && self.clicks.is_dragging() {
+                let (start_x, start_y) = (drag_start_event.mouse_x, drag_start_event.mouse_y);
+                let raw = Point {
+                    x: event.mouse_x - drag_start_event.mouse_x,
+                    y: event.mouse_y - drag_start_event.mouse_y,
+                };
+                let offset = self.snap_drag(raw);
+                let dragged: Vec<NodeId> =
+                    self.selected_nodes.iter().copied().filter(|&id| self.is_draggable(id)).collect();
+                for id in dragged {
+                    let applied = self.drag_applied.get(&id).copied().unwrap_or(Point { x: 0.0, y: 0.0 });
+                    let target = self.constrain_drag(id, offset, applied);
+                    if let Some(node) = self.get_node_mut(id) {
+                        node.translate(target.x - applied.x, target.y - applied.y);
+                    }
+                    self.drag_applied.insert(id, target);
+                }
+                self.drag_offset = offset;
+
+                if let Some(grabbed) = self.grabbed_node {
+                    if self.dragged_node.is_none() {
+                        self.dragged_node = Some(grabbed);
+                        self.emit_drag(EventKind::DragStart, grabbed, start_x, start_y);
+                    }
+                    self.emit_drag(EventKind::DragMove, grabbed, event.mouse_x, event.mouse_y);
+                }
+            }
+        } else {
+            self.snap_guides.clear();
+        }
+    }
+
+    /// Whether the node can be selected: it is visible and not locked.
+    /// Hidden nodes cannot be hit anyway, but may still be in a selection
+    /// made before they were hidden.
+    pub(crate) fn is_selectable(&self, id: NodeId) -> bool {
+        self.get_node(id)
+            .is_some_and(|node| node.get_style().is_visible() && !node.get_style().is_locked())
+    }
+
+    fn is_draggable(&self, id: NodeId) -> bool {
+        self.drag_options.get(&id).is_none_or(|options| options.draggable)
+    }
+
+    /// Offset node `id` should have moved by for the drag `offset`, given
+    /// that it has moved by `applied` so far: locked to its axis, clamped to
+    /// its bounds and finally passed through its drag handler, which
+    /// receives `{ node, x, y, dx, dy }` with the proposed top left corner
+    /// of the node's bounds and may return a different `{ x, y }`.
+    fn constrain_drag(&self, id: NodeId, offset: Point, applied: Point) -> Point {
+        let Some(node) = self.get_node(id) else {
+            return offset;
+        };
+        let options = self.drag_options.get(&id).cloned().unwrap_or_default();
+        let mut offset = options.lock_axis(offset, self.modifiers.shift);
+
+        let bounds = node.get_bounds();
+        let start = Rectangle { left: bounds.left - applied.x, top: bounds.top - applied.y, ..bounds };
+        let region = options.bounds.and_then(|region| match region {
+            DragBounds::Rect { left, top, width, height } => Some(Rectangle { left, top, width, height }),
+            DragBounds::Node { node } => self.get_node(node).map(Node::get_bounds),
+        });
+        if let Some(region) = region {
+            offset = clamp_offset(start, offset, region);
+        }
+
+        if let Some(handler) = self.drag_handlers.get(&id) {
+            let payload = JsValue::from_serde(&serde_json::json!({
+                "node": id,
+                "x": start.left + offset.x,
+                "y": start.top + offset.y,
+                "dx": offset.x,
+                "dy": offset.y,
+            }))
+            .unwrap_or(JsValue::NULL);
+            let position = callback::call_hook(handler, &payload)
+                .ok()
+                .and_then(|position| position.into_serde::<DragPosition>().ok());
+            offset = handled_offset(start, offset, position);
+        }
+        offset
+    }
+
+    /// Snaps the drag offset `raw` so the selection lines up with the grid
+    /// or other nodes, updating the guides to draw. Holding ctrl/cmd drags
+    /// freely.
+    fn snap_drag(&mut self, raw: Point) -> Point {
+        self.snap_guides.clear();
+        if !self.snap.enabled || self.modifiers.command() {
+            return raw;
+        }
+        let Some(bounds) = self.selection_bounds() else {
+            return raw;
+        };
+
+        let moving = Rectangle {
+            left: bounds.left - self.drag_offset.x + raw.x,
+            top: bounds.top - self.drag_offset.y + raw.y,
+            ..bounds
+        };
+        let others: Vec<Rectangle> = self
+            .nodes
+            .iter()
+            .filter(|node| !self.is_selected(node.id()) && node.get_style().is_visible())
+            .map(|node| node.get_bounds())
+            .collect();
+        let (correction, guides) = snap(moving, &others, &self.snap, self.viewport.scale);
+        self.snap_guides = guides;
+        Point { x: raw.x + correction.x, y: raw.y + correction.y }
+    }
+
+    /// Stops any drag, marquee or handle interaction without finishing it
+    /// like a mouse up would.
+    fn cancel_pointer_interaction(&mut self) {
+        self.drag_start_event = None;
+        self.snap_guides.clear();
+        self.clicks.cancel();
+        self.marquee = None;
+        self.resizing = None;
+        self.rotating = None;
+        if let Some(editor) = &mut self.editing {
+            editor.is_selecting = false;
+        }
+    }
+
+    pub(crate) fn is_selected(&self, id: NodeId) -> bool {
+        self.selected_nodes.contains(&id)
+    }
+
+    fn toggle_selection(&mut self, id: NodeId) {
+        match self.selected_nodes.iter().position(|&selected| selected == id) {
+            Some(position) => {
+                self.selected_nodes.remove(position);
+            }
+            None => self.selected_nodes.push(id),
+        }
+    }
+
+    /// The selected node, when exactly one is selected.
+    pub(crate) fn single_selection(&self) -> Option<&Node> {
+        match self.selected_nodes.as_slice() {
+            [id] => self.get_node(*id),
+            _ => None,
+        }
+    }
+
+    /// Canvas bounds enclosing every selected node.
+    pub(crate) fn selection_bounds(&self) -> Option<Rectangle> {
+        self.nodes
+            .iter()
+            .filter(|node| self.is_selected(node.id()))
+            .map(|node| node.get_bounds())
+            .reduce(|bounds, other| bounds.union(&other))
+    }
+
+    fn handle_key_event(&mut self, event: &Event) {
+        let key = event.key.as_deref().unwrap_or_default();
+        if !event.key_down {
+            return;
+        }
+
+        // Command chords are shortcuts even while editing text.
+        let id = match &self.editing {
+            Some(editor) if !self.modifiers.command() => editor.node,
+            _ => {
+                if let Some(action) = self.keymap.action(key, self.modifiers).map(str::to_string) {
+                    self.run_action(&action);
+                }
+                return;
+            }
+        };
+
+        let node = self.nodes.iter_mut().find(|node| node.id() == id);
+        let (editor, text, is_dirty) = match (self.editing.as_mut(), node) {
+            (Some(editor), Some(Node::Text(text, _, is_dirty, _))) => (editor, &mut text.text, is_dirty),
+            _ => {
+                self.editing = None;
+                return;
+            }
+        };
+
+        let extend = self.modifiers.shift;
+        match key {
+            "Enter" => {
+                self.editing = None;
+            }
+            "Escape" => {
+                *text = editor.original.clone();
+                self.editing = None;
+            }
+            "ArrowLeft" => editor.move_left(extend),
+            "ArrowRight" => editor.move_right(text, extend),
+            "ArrowUp" | "Home" => editor.move_to(0, extend),
+            "ArrowDown" | "End" => editor.move_to(text.chars().count(), extend),
+            "Backspace" => editor.backspace(text),
+            "Delete" => editor.delete(text),
+            _ if key.chars().count() == 1 => editor.insert(text, key),
+            _ => {}
+        }
+        if let Some(editor) = &mut self.editing {
+            editor.reset_blink(self.now);
+        }
+        *is_dirty = true;
+        self.measure_node(id);
+    }
+
+    /// Measures the node again if it is text, e.g. after its text or font
+    /// changed, so hit-testing and handles see the new extent right away.
+    fn measure_node(&self, id: NodeId) {
+        if let Some(node) = self.get_node(id) {
+            self.measure_texts(std::slice::from_ref(node));
+        }
+    }
+
+    /// Runs the built-in or JS-registered action called `name`. Returns
+    /// `false` when there is no such action.
+    pub(crate) fn run_action(&mut self, name: &str) -> bool {
+        match BuiltinAction::from_name(name) {
+            Some(BuiltinAction::Undo) => return self.undo(),
+            Some(BuiltinAction::Redo) => return self.redo(),
+            Some(BuiltinAction::Copy) => {
+                self.copy_selection();
+            }
+            Some(BuiltinAction::Cut) => {
+                self.cut_selection();
+            }
+            Some(BuiltinAction::Paste) => {
+                let _ = self.paste(None, None);
+            }
+            Some(BuiltinAction::Duplicate) => {
+                self.duplicate();
+            }
+            Some(action) => self.record(|ctx| ctx.run_builtin_action(action)),
+            None => {
+                let Some(action) = self.actions.get(name).cloned() else {
+                    return false;
+                };
+                let selection = JsValue::from_serde(&self.selected_nodes).unwrap_or(JsValue::NULL);
+                // Runs once the canvas is free again, so the action can use
+                // it, and not at all when the canvas is freed before that.
+                let handle = ContextHandle::new(self);
+                callback::defer_with(move || {
+                    if handle.with(Context::begin_transaction).is_none() {
+                        return;
+                    }
+                    let _ = action.call1(&JsValue::NULL, &selection);
+                    handle.with(|ctx| {
+                        ctx.commit_transaction();
+                        ctx.is_dirty = true;
+                    });
+                });
+            }
+        }
+        self.is_dirty = true;
+        true
+    }
+
+    fn run_builtin_action(&mut self, action: BuiltinAction) {
+        match action {
+            // Nudges obey the drag options and handlers like a drag by the
+            // same distance would.
+            BuiltinAction::Nudge(dx, dy) => {
+                let nudged: Vec<NodeId> = self.selected_nodes.iter().copied().filter(|&id| self.is_draggable(id)).collect();
+                for id in nudged {
+                    let offset = self.constrain_drag(id, Point { x: dx, y: dy }, Point { x: 0.0, y: 0.0 });
+                    if let Some(node) = self.get_node_mut(id) {
+                        node.translate(offset.x, offset.y);
+                    }
+                }
+            }
+            BuiltinAction::Delete => {
+                for id in self.selected_nodes.clone() {
+                    self.remove_node(id);
+                }
+            }
+            BuiltinAction::Deselect => self.selected_nodes.clear(),
+            BuiltinAction::Restack(how) => {
+                let selection = self.selected_nodes.clone();
+                self.restack(&selection, how);
+            }
+            _ => {}
+        }
+    }
+
+    /// Moves the nodes with `ids` in the draw order as one undoable step,
+    /// keeping the hovered node tracked by index pointing at the same node.
+    pub(crate) fn restack(&mut self, ids: &[NodeId], how: Restack) -> bool {
+        let hovered = self.hovered_id();
+        let is_moved = self.record(|ctx| restack(&mut ctx.nodes, ids, how));
+        if is_moved {
+            self.hovered_node = hovered.and_then(|id| self.get_node_index(id));
+            self.is_dirty = true;
+        }
+        is_moved
+    }
+
+    fn selection_data(&self) -> Option<ClipboardData> {
+        let data = ClipboardData::from_nodes(self.nodes.iter().filter(|node| self.is_selected(node.id())));
+        (!data.is_empty()).then_some(data)
+    }
+
+    /// Copies the selected nodes, returning them as clipboard text.
+    pub(crate) fn copy_selection(&mut self) -> Option<String> {
+        let json = self.selection_data()?.to_json();
+        self.clipboard = Some(json.clone());
+        Some(json)
+    }
+
+    pub(crate) fn cut_selection(&mut self) -> Option<String> {
+        let json = self.copy_selection()?;
+        self.record(|ctx| {
+            for id in ctx.selected_nodes.clone() {
+                ctx.remove_node(id);
+            }
+        });
+        Some(json)
+    }
+
+    /// Pastes clipboard `text`, or the last copy when `None`, and selects the
+    /// pasted nodes. With `at` their top-left corner lands on that scene
+    /// point; otherwise they are offset a little from where they were copied.
+    pub(crate) fn paste(&mut self, text: Option<&str>, at: Option<Point>) -> Result<Vec<NodeId>, String> {
+        let text = match text {
+            Some(text) => text.to_string(),
+            None => self.clipboard.clone().ok_or("nothing to paste")?,
+        };
+        let data = ClipboardData::from_json(&text)?;
+        Ok(self.insert_copies(&data, at))
+    }
+
+    /// Copies the selected nodes in place, offset slightly, without touching
+    /// the clipboard.
+    pub(crate) fn duplicate(&mut self) -> Vec<NodeId> {
+        match self.selection_data() {
+            Some(data) => self.insert_copies(&data, None),
+            None => Vec::new(),
+        }
+    }
+
+    fn insert_copies(&mut self, data: &ClipboardData, at: Option<Point>) -> Vec<NodeId> {
+        let ids: Vec<NodeId> = (0..data.len()).map(|_| self.next_node_id()).collect();
+        let mut nodes = data.to_nodes(&ids, |id| self.get_node(id).is_some());
+        self.measure_texts(&nodes);
+
+        let bounds = nodes.iter().map(|node| node.get_bounds()).reduce(|bounds, other| bounds.union(&other));
+        let (dx, dy) = match (at, bounds) {
+            (Some(at), Some(bounds)) => (at.x - bounds.left, at.y - bounds.top),
+            _ => (PASTE_OFFSET, PASTE_OFFSET),
+        };
+        nodes.iter_mut().for_each(|node| node.translate(dx, dy));
+
+        self.record(|ctx| ctx.nodes.extend(nodes));
+        self.selected_nodes = ids.clone();
+        self.is_dirty = true;
+        ids
+    }
+
+    /// Tracks data dragged over the canvas from outside at `(x, y)`:
+    /// highlights the node under it and emits `drag_enter`, `drag_over` and
+    /// `drag_leave`, for the canvas (with no node) and for each node it
+    /// passes over.
+    pub(crate) fn on_drag_over(&mut self, x: f64, y: f64) {
+        self.log_input(Input::DragOver { x, y });
+        let (x, y) = self.viewport.to_scene(x, y);
+        let before = self.drag_over.and(self.hovered_id());
+        if self.drag_over.is_none() {
+            self.emit(Emitted::new(EventKind::DragEnter, None, x, y));
+        }
+        self.drag_over = Some(Point { x, y });
+
+        let event = Event::from_mouse_move(x, y, 0.0);
+        self.hovered_node = (0..self.nodes.len()).rev().find(|&index| self.hit_test(index, &event));
+        let target = self.hovered_id();
+        if target != before {
+            if let Some(id) = before {
+                self.emit(Emitted::new(EventKind::DragLeave, Some(id), x, y));
+            }
+            if let Some(id) = target {
+                self.emit(Emitted::new(EventKind::DragEnter, Some(id), x, y));
+            }
+        }
+        self.emit(Emitted::new(EventKind::DragOver, target, x, y));
+        self.is_dirty = true;
+    }
+
+    /// The dragged data left the canvas or the drag was cancelled.
+    pub(crate) fn on_drag_leave(&mut self) {
+        self.log_input(Input::DragLeave);
+        let Some(Point { x, y }) = self.drag_over.take() else {
+            return;
+        };
+        if let Some(id) = self.hovered_id() {
+            self.emit(Emitted::new(EventKind::DragLeave, Some(id), x, y));
+        }
+        self.emit(Emitted::new(EventKind::DragLeave, None, x, y));
+        self.hovered_node = None;
+        self.is_dirty = true;
+    }
+
+    /// Adds the dropped `items` with the top-left of each at the canvas point
+    /// `(x, y)`, later ones offset a little so they do not cover each other,
+    /// as one undoable step. Selects and returns the new nodes and emits
+    /// `drop` for the node dropped on. If any item cannot be imported nothing
+    /// changes, not even the ids handed out.
+    pub(crate) fn on_drop(&mut self, x: f64, y: f64, items: Vec<DropItem>) -> Result<Vec<NodeId>, String> {
+        let last_id = self.next_node_id;
+        let converted: Result<Vec<Vec<Node>>, String> =
+            items.iter().map(|item| item.to_nodes(&mut || self.next_node_id())).collect();
+        let converted = match converted {
+            Ok(converted) => converted,
+            Err(err) => {
+                self.next_node_id = last_id;
+                return Err(err);
+            }
+        };
+
+        self.log_input(Input::Drop { x, y, items });
+        let target = self.drag_over.take().and(self.hovered_id());
+        let (x, y) = self.viewport.to_scene(x, y);
+
+        let mut nodes = Vec::new();
+        for (index, mut item_nodes) in converted.into_iter().enumerate() {
+            self.measure_texts(&item_nodes);
+            let bounds = item_nodes.iter().map(|node| node.get_bounds()).reduce(|bounds, other| bounds.union(&other));
+            if let Some(bounds) = bounds {
+                let offset = index as f64 * PASTE_OFFSET;
+                let (dx, dy) = (x + offset - bounds.left, y + offset - bounds.top);
+                item_nodes.iter_mut().for_each(|node| node.translate(dx, dy));
+            }
+            nodes.extend(item_nodes);
+        }
+
+        let ids: Vec<NodeId> = nodes.iter().map(|node| node.id()).collect();
+        if !ids.is_empty() {
+            self.record(|ctx| ctx.nodes.extend(nodes));
+            self.selected_nodes = ids.clone();
+        }
+        self.emit(Emitted::new(EventKind::Drop, target, x, y));
+        self.is_dirty = true;
+        Ok(ids)
+    }
+
+    /// Routes mouse input to the active text editor. Returns `true` when the
+    /// event was consumed; pressing outside the edited node commits the edit
+    /// and lets the event through.
+    fn handle_edit_mouse_event(&mut self, event: &Event) -> bool {
+        let (node, is_selecting) = match &self.editing {
+            Some(editor) => (editor.node, editor.is_selecting),
+            None => return false,
+        };
+
+        let index_at = match self.get_node(node) {
+            Some(Node::Text(text, style, ..)) if event.mouse_down && !text.is_hovered(event.mouse_x, event.mouse_y, style) => {
+                self.editing = None;
+                return false;
+            }
+            Some(Node::Text(text, style, ..)) => match &self.editing {
+                Some(editor) => editor.index_at(self, text, style, event.mouse_x, event.mouse_y),
+                None => return false,
+            },
+            _ => {
+                self.editing = None;
+                return false;
+            }
+        };
+
+        let extend = self.modifiers.shift;
+        let Some(editor) = self.editing.as_mut() else {
+            return false;
+        };
+
+        if event.mouse_down {
+            editor.move_to(index_at, extend);
+            editor.reset_blink(self.now);
+            editor.is_selecting = true;
+            return true;
+        }
+
+        if is_selecting {
+            editor.move_to(index_at, true);
+            editor.reset_blink(self.now);
+            editor.is_selecting = !event.mouse_up;
+            return true;
+        }
+
+        false
+    }
+
+    /// Starts, updates or finishes dragging a handle of the selected node.
+    /// Returns `true` when the event was consumed.
+    fn handle_transform_event(&mut self, event: &Event) -> bool {
+        if let Some(resize) = &self.resizing {
+            let id = resize.node;
+            let resized = resize.resize(event.mouse_x, event.mouse_y, self.modifiers.shift, self.modifiers.alt);
+            if let Some(node) = self.get_node_mut(id) {
+                *node = resized;
+            }
+            if !event.mouse_moved {
+                self.resizing = None;
+            }
+            return true;
+        }
+
+        if let Some(rotate) = &mut self.rotating {
+            let id = rotate.node;
+            let rotated = rotate.rotate(event.mouse_x, event.mouse_y, self.modifiers.shift);
+            if let Some(node) = self.get_node_mut(id) {
+                *node = rotated;
+            }
+            if !event.mouse_moved {
+                self.rotating = None;
+            }
+            return true;
+        }
+
+        if !event.mouse_down {
+            return false;
+        }
+
+        let Some(node) = self.single_selection() else {
+            return false;
+        };
+
+        let (x, y) = (event.mouse_x, event.mouse_y);
+        match Handle::hit(node, &self.selection_theme, x, y, event.pointer.pointer_type) {
+            Some(Handle::Rotate) => self.rotating = Some(RotateState::new(node, x, y)),
+            Some(handle) => self.resizing = Some(ResizeState::new(node, handle, x, y)),
+            None => return false,
+        }
+        self.drag_start_event = None;
+        true
+    }
+
+    fn start_editing(&mut self, event: &Event) {
+        let Some(index) = self.hovered_node else {
+            return;
+        };
+        if !self.is_selectable(self.nodes[index].id()) {
+            return;
+        }
+
+        if let Some(Node::Text(text, style, _, id)) = self.nodes.get(index) {
+            let mut editor = TextEditor::new(*id, &text.text, self.now);
+            let caret = editor.index_at(self, text, style, event.mouse_x, event.mouse_y);
+            editor.move_to(caret, false);
+            self.editing = Some(editor);
+            self.drag_start_event = None;
+        }
+    }
+
+    pub(crate) fn render_node(&self, node: &Node, has_bounding_box: bool, is_selected: bool) {
+        self.context().save();
+        if let Some(clip) = &node.get_style().clip {
+            clip.apply(self, node);
+        }
+        match node {
+            Node::Line(line, style, is_dirty, _) => {
+                if *is_dirty || self.is_dirty {
+                    line.render(self, style);
+                }
+            }
+            Node::Rect(rect, style, is_dirty, _) => {
+                if *is_dirty || self.is_dirty {
+                    rect.render(self, style);
+                }
+            }
+            Node::Circle(circle, style, is_dirty, _) => {
+                if *is_dirty || self.is_dirty {
+                    circle.render(self, style);
+                }
+            }
+            Node::Text(text, style, is_dirty, _) => {
+                if *is_dirty || self.is_dirty {
+                    text.render(self, style);
+                }
+            }
+            Node::Image(image, style, is_dirty, _) => {
+                if *is_dirty || self.is_dirty {
+                    image.render(self, style);
+                }
+            }
+        }
+        self.context().restore();
+
+        if has_bounding_box || is_selected {
+            self.draw_bounding_box(node.get_rect(), node.get_style(), is_selected);
+        }
+
+        if is_selected {
+            self.draw_handles(node);
+        }
+    }
+
+    pub(crate) fn draw_bounding_box(&self, rect: Rectangle, style: &Style, is_selected: bool) {
+        let theme = &self.selection_theme;
+        let chrome = if is_selected { &theme.selected } else { &theme.hover };
+        let outline = theme.outline(rect);
+
+        self.context().save();
+        style.apply_transform(self.context(), rect);
+
+        self.context().begin_path();
+        self.context().set_stroke_style_str(&chrome.color.to_css());
+        self.context().set_line_width(chrome.line_width);
+        let dash: js_sys::Array = chrome.dash.iter().map(|&segment| JsValue::from(segment)).collect();
+        let _ = self.context().set_line_dash(&dash);
+        self.context().rect(outline.left, outline.top, outline.width, outline.height);
+        self.context().close_path();
+        self.context().stroke();
+        self.context().restore();
+    }
+
+    /// Draws the resize and rotation handles of the selected node.
+    pub(crate) fn draw_handles(&self, node: &Node) {
+        let theme = &self.selection_theme;
+        self.context().save();
+        node.get_style().apply_transform(self.context(), node.get_rect());
+        for (handle, point) in Handle::positions(node, theme) {
+            if handle == Handle::Rotate {
+                let outline = theme.outline(node.get_rect());
+                self.context().begin_path();
+                self.context().set_stroke_style_str(&theme.selected.color.to_css());
+                self.context().set_line_width(theme.hover.line_width);
+                self.context().move_to(point.x, outline.top);
+                self.context().line_to(point.x, point.y);
+                self.context().stroke();
+            }
+            self.draw_handle(point.x, point.y);
+        }
+        self.context().restore();
+    }
+
+    pub(crate) fn draw_marquee(&self, marquee: &Marquee) {
+        let rect = marquee.get_rect();
+        let color = self.selection_theme.selected.color;
+
+        self.context().save();
+        self.context().set_fill_style_str(&color.with_alpha(0.1).to_css());
+        self.context().fill_rect(rect.left, rect.top, rect.width, rect.height);
+        self.context().set_stroke_style_str(&color.to_css());
+        self.context().set_line_width(self.selection_theme.hover.line_width);
+        self.context().stroke_rect(rect.left, rect.top, rect.width, rect.height);
+        self.context().restore();
+    }
+
+    /// Draws the angle next to the pointer while the rotation handle is
+    /// being dragged.
+    pub(crate) fn draw_rotation_readout(&self, rotate: &RotateState) {
+        let label = format!("{}\u{b0}", rotate.degrees());
+        let (x, y) = (rotate.pointer.x + 16.0, rotate.pointer.y + 16.0);
+        let theme = &self.selection_theme;
+
+        self.context().save();
+        self.context().set_font("12px sans-serif");
+        self.context().set_text_baseline("top");
+        let width = self.context().measure_text(&label).map(|m| m.width()).unwrap_or(24.0);
+        self.context().set_fill_style_str(&theme.selected.color.to_css());
+        self.context().fill_rect(x - 4.0, y - 3.0, width + 8.0, 18.0);
+        self.context().set_fill_style_str(&theme.handle.fill_color.to_css());
+        let _ = self.context().fill_text(&label, x, y);
+        self.context().restore();
+    }
+
+    /// Draws a single selection handle centred on `(x, y)` in the current
+    /// transform.
+    pub(crate) fn draw_handle(&self, x: f64, y: f64) {
+        let handle = &self.selection_theme.handle;
+        let half = handle.size / 2.0;
+
+        self.context().begin_path();
+        match handle.shape {
+            HandleShape::Square => self.context().rect(x - half, y - half, handle.size, handle.size),
+            HandleShape::Circle => {
+                let _ = self.context().arc(x, y, half, 0.0, 2.0 * std::f64::consts::PI);
+            }
+            HandleShape::None => return,
+        }
+        let _ = self.context().set_line_dash(&js_sys::Array::new());
+        self.context().set_fill_style_str(&handle.fill_color.to_css());
+        self.context().set_stroke_style_str(&handle.stroke_color.to_css());
+        self.context().set_line_width(handle.stroke_width);
+        self.context().fill();
+        self.context().stroke();
+    }
+
+    pub fn add_node(&mut self, node: Node) {
+        self.record(|ctx| ctx.nodes.push(node));
+    }
+
+    fn next_node_id(&mut self) -> NodeId {
+        self.next_node_id += 1;
+        self.next_node_id
+    }
+
+    pub(crate) fn get_node_index(&self, id: NodeId) -> Option<usize> {
+        self.nodes.iter().position(|node| node.id() == id)
+    }
+
+    pub(crate) fn get_node(&self, id: NodeId) -> Option<&Node> {
+        self.nodes.iter().find(|node| node.id() == id)
+    }
+
+    pub(crate) fn get_node_mut(&mut self, id: NodeId) -> Option<&mut Node> {
+        self.nodes.iter_mut().find(|node| node.id() == id)
+    }
+
+    /// Removes a node, dropping it from the selection and keeping the hovered
+    /// index pointing at the same node as before.
+    pub(crate) fn remove_node(&mut self, id: NodeId) -> Option<Node> {
+        let index = self.get_node_index(id)?;
+        let shift = |current: Option<usize>| match current {
+            Some(current) if current == index => None,
+            Some(current) if current > index => Some(current - 1),
+            current => current,
+        };
+        self.selected_nodes.retain(|&selected| selected != id);
+        self.hovered_node = shift(self.hovered_node);
+        if self.editing.as_ref().is_some_and(|editor| editor.node == id) {
+            self.editing = None;
+        }
+        self.is_dirty = true;
+        Some(self.nodes.remove(index))
+    }
+
+    /// Drops the drag options and handlers of removed nodes that undo and
+    /// redo can no longer bring back. The others keep theirs, so an undone
+    /// delete restores the node as it was.
+    fn forget_removed_nodes(&mut self) {
+        if self.history.is_recording() {
+            return;
+        }
+        let gone: Vec<NodeId> = self
+            .drag_options
+            .keys()
+            .chain(self.drag_handlers.keys())
+            .chain(self.wheel_handlers.keys())
+            .copied()
+            .filter(|&id| self.get_node(id).is_none() && !self.history.can_restore(id))
+            .collect();
+        for id in gone {
+            self.drag_options.remove(&id);
+            self.drag_handlers.remove(&id);
+            self.wheel_handlers.remove(&id);
+        }
+    }
+
+    /// Whether the event's pointer is over the node at `index`, taking its
+    /// clip into account so clipped-away parts are not interactive.
+    pub(crate) fn hit_test(&self, index: usize, event: &Event) -> bool {
+        let Some(node) = self.nodes.get(index) else {
+            return false;
+        };
+
+        if !node.get_style().is_hittable() || !node.is_hovered(event) {
+            return false;
+        }
+
+        match &node.get_style().clip {
+            Some(clip) => clip.contains(self, node, event.mouse_x, event.mouse_y),
+            None => true,
+        }
+    }
+
+    pub fn add_line(&mut self, start: Point, end: Point, style: Style) {
+        let line = Line { start, end };
+        let node = Node::Line(line, style, true, self.next_node_id());
+        self.add_node(node);
+    }
+
+    pub fn add_rect(&mut self, top: f64, left: f64, width: f64, height: f64, style: Style) {
+        let rect = Rectangle {
+            top,
+            left,
+            width,
+            height,
+        };
+        let node = Node::Rect(rect, style, true, self.next_node_id());
+        self.add_node(node);
+    }
+
+    pub fn add_circle(&mut self, center: Point, radius: f64, style: Style) {
+        let circle = Circle { center, radius };
+        let node = Node::Circle(circle, style, true, self.next_node_id());
+        self.add_node(node);
+    }
+
+    pub fn add_text(&mut self, text: String, position: Point, style: Style) {
+        let extent = self.measure_text(&text, &style);
+        let text = Text {
+            text,
+            position,
+            shape: RefCell::new(Some(extent)),
+        };
+        let node = Node::Text(text, style, true, self.next_node_id());
+        self.add_node(node);
+    }
+
+    pub fn add_image(&mut self, src: String, rect: Rectangle, style: Style) {
+        let node = Node::Image(Image { src, rect }, style, true, self.next_node_id());
+        self.add_node(node);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::internal::{clip::Clip, style::ControlPoint};
+
+    fn mouse() -> PointerInput {
+        PointerInput { pointer_type: PointerType::Mouse, ..Default::default() }
+    }
+
+    #[test]
+    fn hovering_pointer_moves_update_the_hovered_node() {
+        let mut ctx = Context::headless();
+        ctx.add_rect(0.0, 0.0, 50.0, 50.0, Style::default());
+
+        ctx.on_pointer_move(1, 10.0, 10.0, 0.0, None, mouse());
+        ctx.process_input(0.0);
+        assert_eq!(ctx.hovered_id(), Some(1));
+
+        ctx.on_pointer_move(1, 100.0, 100.0, 10.0, None, mouse());
+        ctx.process_input(10.0);
+        assert_eq!(ctx.hovered_id(), None);
+    }
+
+    #[test]
+    fn replaying_a_recording_ends_in_the_recorded_scene() {
+        let touch = PointerInput { pointer_type: PointerType::Touch, ..Default::default() };
+        let mut ctx = Context::headless();
+        ctx.add_rect(0.0, 0.0, 50.0, 50.0, Style::default());
+        let style = Style { font_size: Some(40.0), ..Default::default() };
+        ctx.add_text("hello".to_string(), Point { x: 100.0, y: 100.0 }, style);
+        ctx.start_recording();
+
+        // Drag the rect with the mouse.
+        ctx.on_pointer_down(1, 10.0, 10.0, 0.0, None, mouse());
+        ctx.on_pointer_move(1, 40.0, 30.0, 20.0, None, mouse());
+        ctx.process_input(20.0);
+        ctx.on_pointer_up(1, 40.0, 30.0, 40.0, None, mouse());
+        ctx.process_input(40.0);
+        assert_eq!((ctx.nodes[0].get_bounds().left, ctx.nodes[0].get_bounds().top), (30.0, 20.0));
+
+        // Double-tap the text to edit it, then type.
+        let bounds = ctx.nodes[1].get_bounds();
+        let (x, y) = (bounds.left + bounds.width / 2.0, bounds.top + bounds.height / 2.0);
+        for ts in [1000.0, 1200.0] {
+            ctx.on_pointer_down(2, x, y, ts, None, touch);
+            ctx.on_pointer_up(2, x, y, ts + 50.0, None, touch);
+            ctx.process_input(ts + 50.0);
+        }
+        assert!(ctx.editing.is_some());
+        for key in ["End", "!", "Enter"] {
+            ctx.on_key_down(key.to_string(), 1500.0, None);
+        }
+        ctx.process_input(1500.0);
+
+        let scene = ctx.scene_json();
+        let recording = ctx.stop_recording().unwrap();
+        assert!(scene.contains("hello!"));
+        // Pointer input is logged once, not again as the mouse events it
+        // turns into.
+        assert!(!recording.contains("mouse_down"));
+
+        let mut replayed = Context::headless();
+        replayed.replay(&recording).unwrap();
+        assert_eq!(replayed.scene_json(), scene);
+        assert_eq!(replayed.nodes, ctx.nodes);
+    }
+
+    #[test]
+    fn replays_restore_the_settings_the_recording_started_with() {
+        let mut ctx = Context::headless();
+        ctx.resize(800.0, 600.0);
+        ctx.add_rect(100.0, 100.0, 100.0, 100.0, Style::default());
+        ctx.selected_nodes = vec![1];
+        ctx.selection_theme.handle.size = 30.0;
+        ctx.set_zoom_limits(0.5, 1.5);
+        ctx.keymap.bind("Mod+B", "bring_to_front").unwrap();
+        ctx.marquee_mode = MarqueeMode::Contain;
+
+        ctx.start_recording();
+        // Wide handles let this press, outside the corner, grab it.
+        ctx.on_pointer_down(1, 90.0, 90.0, 0.0, None, mouse());
+        ctx.on_pointer_move(1, 60.0, 60.0, 10.0, None, mouse());
+        ctx.on_pointer_up(1, 60.0, 60.0, 20.0, None, mouse());
+        ctx.process_input(20.0);
+        ctx.on_wheel(0.0, -1000.0, 400.0, 300.0, DeltaMode::Pixel, Some(Modifiers { ctrl: true, ..Default::default() }));
+        ctx.on_wheel(0.0, 1.0, 0.0, 0.0, DeltaMode::Page, None);
+        let recording = ctx.stop_recording().unwrap();
+        assert_eq!(ctx.nodes[0].get_bounds().left, 70.0);
+
+        let mut replayed = Context::headless();
+        replayed.replay(&recording).unwrap();
+        assert_eq!(replayed.scene_json(), ctx.scene_json());
+        assert_eq!(replayed.viewport, ctx.viewport);
+        assert_eq!(replayed.shape(), (800.0, 600.0));
+        assert_eq!(replayed.selection_theme, ctx.selection_theme);
+        assert_eq!((replayed.min_zoom, replayed.max_zoom), (0.5, 1.5));
+        assert_eq!(replayed.keymap, ctx.keymap);
+        assert_eq!(replayed.marquee_mode, MarqueeMode::Contain);
+    }
+
+    #[test]
+    fn command_chords_are_not_typed_into_edited_text() {
+        let mut ctx = Context::headless();
+        ctx.add_text("hi".to_string(), Point { x: 0.0, y: 0.0 }, Style::default());
+        ctx.add_rect(100.0, 100.0, 10.0, 10.0, Style::default());
+        ctx.selected_nodes = vec![2];
+        ctx.editing = Some(TextEditor::new(1, "hi", 0.0));
+
+        let command = Modifiers { ctrl: true, ..Default::default() };
+        ctx.on_key_down("c".to_string(), 0.0, Some(command));
+        ctx.on_key_down("x".to_string(), 0.0, Some(Modifiers::default()));
+        ctx.process_input(0.0);
+
+        assert!(matches!(&ctx.nodes[0], Node::Text(text, ..) if text.text == "hix"));
+        // `Mod+C` went to the keymap and copied the selection.
+        assert!(ctx.clipboard.is_some());
+    }
+
+    #[test]
+    fn edited_text_is_measured_again_right_away() {
+        let mut ctx = Context::headless();
+        ctx.add_text("hi".to_string(), Point { x: 0.0, y: 0.0 }, Style::default());
+        let width = ctx.nodes[0].get_bounds().width;
+        ctx.editing = Some(TextEditor::new(1, "hi", 0.0));
+        for key in ["!", "!", "!"] {
+            ctx.on_key_down(key.to_string(), 0.0, Some(Modifiers::default()));
+        }
+        ctx.process_input(0.0);
+        assert!(ctx.nodes[0].get_bounds().width > width);
+
+        let width = ctx.nodes[0].get_bounds().width;
+        ctx.update_node(1, |node| node.get_style_mut().font_size = Some(40.0));
+        assert!(ctx.nodes[0].get_bounds().width > width);
+    }
+
+    #[test]
+    fn drags_do_not_snap_to_hidden_nodes() {
+        let drag_right = |hidden: bool| {
+            let mut ctx = Context::headless();
+            ctx.add_rect(0.0, 0.0, 50.0, 50.0, Style::default());
+            ctx.add_rect(0.0, 103.0, 50.0, 50.0, Style { visible: Some(!hidden), ..Default::default() });
+            ctx.on_pointer_down(1, 10.0, 10.0, 0.0, None, mouse());
+            ctx.on_pointer_move(1, 60.0, 10.0, 20.0, None, mouse());
+            ctx.on_pointer_up(1, 60.0, 10.0, 40.0, None, mouse());
+            ctx.process_input(40.0);
+            ctx.nodes[0].get_bounds().left
+        };
+        assert_eq!(drag_right(false), 53.0);
+        assert_eq!(drag_right(true), 50.0);
+    }
+
+    #[test]
+    fn nudges_follow_the_drag_options() {
+        let mut ctx = Context::headless();
+        ctx.add_rect(0.0, 0.0, 10.0, 10.0, Style::default());
+        ctx.add_rect(0.0, 50.0, 10.0, 10.0, Style::default());
+        ctx.add_rect(0.0, 100.0, 10.0, 10.0, Style::default());
+        let options = |json: serde_json::Value| serde_json::from_value::<DragOptions>(json).unwrap();
+        ctx.drag_options.insert(1, options(serde_json::json!({ "draggable": false })));
+        ctx.drag_options.insert(2, options(serde_json::json!({ "axis": "y" })));
+        let bounds = serde_json::json!({ "type": "rect", "left": 95, "top": 0, "width": 20, "height": 100 });
+        ctx.drag_options.insert(3, options(serde_json::json!({ "bounds": bounds })));
+        ctx.selected_nodes = vec![1, 2, 3];
+
+        ctx.run_action("nudge_right_large");
+        ctx.run_action("nudge_down");
+        let positions: Vec<(f64, f64)> =
+            ctx.nodes.iter().map(|node| (node.get_bounds().left, node.get_bounds().top)).collect();
+        assert_eq!(positions, vec![(0.0, 0.0), (50.0, 1.0), (105.0, 1.0)]);
+    }
+
+    #[test]
+    fn undoing_a_delete_restores_the_drag_options() {
+        let mut ctx = Context::headless();
+        ctx.add_rect(0.0, 0.0, 50.0, 50.0, Style::default());
+        ctx.drag_options.insert(1, serde_json::from_value(serde_json::json!({ "axis": "x" })).unwrap());
+        ctx.selected_nodes = vec![1];
+        ctx.run_action("delete");
+        assert!(ctx.drag_options.contains_key(&1));
+        assert!(ctx.undo());
+
+        ctx.on_pointer_down(1, 10.0, 10.0, 0.0, None, mouse());
+        ctx.on_pointer_move(1, 40.0, 40.0, 10.0, None, mouse());
+        ctx.on_pointer_up(1, 40.0, 40.0, 20.0, None, mouse());
+        ctx.process_input(20.0);
+        let bounds = ctx.nodes[0].get_bounds();
+        assert_eq!((bounds.left, bounds.top), (30.0, 0.0));
+
+        ctx.selected_nodes = vec![1];
+        ctx.run_action("delete");
+        ctx.clear_history();
+        assert!(ctx.drag_options.is_empty());
+    }
+
+    #[test]
+    fn duplicates_keep_clips_to_nodes_left_behind() {
+        let mut ctx = Context::headless();
+        ctx.add_rect(0.0, 0.0, 50.0, 50.0, Style::default());
+        ctx.add_rect(0.0, 0.0, 80.0, 80.0, Style { clip: Some(Clip::Node { node: 1 }), ..Default::default() });
+        ctx.selected_nodes = vec![2];
+        let copies = ctx.duplicate();
+        assert_eq!(ctx.get_node(copies[0]).and_then(|node| node.get_style().clip.clone()), Some(Clip::Node { node: 1 }));
+    }
+
+    #[test]
+    fn only_presses_open_a_history_transaction() {
+        let mut ctx = Context::headless();
+        ctx.add_rect(0.0, 0.0, 50.0, 50.0, Style::default());
+        ctx.history.clear();
+
+        ctx.on_pointer_move(1, 10.0, 10.0, 0.0, None, mouse());
+        ctx.on_pointer_move(1, 20.0, 10.0, 10.0, None, mouse());
+        ctx.process_input(10.0);
+        assert!(!ctx.is_interaction_recorded && !ctx.history.is_recording());
+
+        ctx.on_pointer_down(1, 20.0, 10.0, 20.0, None, mouse());
+        ctx.on_pointer_move(1, 40.0, 10.0, 30.0, None, mouse());
+        ctx.process_input(30.0);
+        assert!(ctx.history.is_recording());
+        ctx.on_pointer_up(1, 40.0, 10.0, 40.0, None, mouse());
+        ctx.process_input(40.0);
+        assert!(!ctx.history.is_recording());
+
+        assert!(ctx.undo());
+        assert_eq!(ctx.nodes[0].get_bounds().left, 0.0);
+        assert!(!ctx.history.can_undo());
+    }
+
+    #[test]
+    fn node_ref_changes_are_undo_steps() {
+        let mut ctx = Context::headless();
+        ctx.add_rect(0.0, 0.0, 50.0, 50.0, Style::default());
+        ctx.add_rect(100.0, 0.0, 50.0, 50.0, Style::default());
+        ctx.history.clear();
+
+        let (mut first, mut second) = (NodeRef::new(&ctx, 1), NodeRef::new(&ctx, 2));
+        first.translate(1.0, 0.0);
+        first.translate(2.0, 0.0);
+        second.set_visible(false);
+        assert!(ctx.undo());
+        assert!(ctx.nodes[1].get_style().is_visible());
+        assert!(ctx.undo());
+        assert_eq!(ctx.nodes[0].get_style().translate, None);
+        assert!(!ctx.history.can_undo());
+
+        ctx.begin_transaction();
+        first.translate(10.0, 0.0);
+        second.rotation(45.0, None, None, None);
+        ctx.commit_transaction();
+        assert!(ctx.undo());
+        assert!(!ctx.history.can_undo());
+    }
+
+    #[test]
+    fn node_ref_changes_during_a_drag_are_undone_apart_from_it() {
+        let mut ctx = Context::headless();
+        ctx.add_rect(0.0, 0.0, 50.0, 50.0, Style::default());
+        ctx.add_rect(100.0, 0.0, 50.0, 50.0, Style::default());
+        ctx.history.clear();
+
+        ctx.on_pointer_down(1, 10.0, 10.0, 0.0, None, mouse());
+        ctx.on_pointer_move(1, 30.0, 10.0, 10.0, None, mouse());
+        ctx.process_input(10.0);
+        NodeRef::new(&ctx, 2).set_visible(false);
+        ctx.on_pointer_up(1, 30.0, 10.0, 20.0, None, mouse());
+        ctx.process_input(20.0);
+
+        assert!(ctx.undo());
+        assert_eq!(ctx.nodes[0].get_bounds().left, 0.0);
+        assert!(!ctx.nodes[1].get_style().is_visible());
+        assert!(ctx.undo());
+        assert!(ctx.nodes[1].get_style().is_visible());
+    }
+
+    #[test]
+    fn resetting_the_viewport_redraws() {
+        let mut ctx = Context::headless();
+        ctx.on_wheel(0.0, 50.0, 0.0, 0.0, DeltaMode::Pixel, None);
+        ctx.is_dirty = false;
+        ctx.reset_viewport();
+        assert_eq!(ctx.viewport, Viewport::default());
+        assert!(ctx.is_dirty);
+    }
+
+    #[test]
+    fn a_drop_of_several_items_is_one_undo_step() {
+        let mut ctx = Context::headless();
+        ctx.add_rect(0.0, 0.0, 50.0, 50.0, Style::default());
+        ctx.history.clear();
+        let items = vec![
+            DropItem::Image { src: "a.png".to_string(), width: Some(20.0), height: None, style: None },
+            DropItem::Text { text: "one\ntwo".to_string(), style: None },
+        ];
+
+        assert_eq!(ctx.on_drop(100.0, 100.0, items), Ok(vec![2, 3, 4]));
+        assert_eq!(ctx.selected_nodes, vec![2, 3, 4]);
+        assert_eq!(ctx.nodes[1].get_bounds().left, 100.0);
+        assert!(ctx.undo());
+        assert_eq!(ctx.nodes.len(), 1);
+        assert!(!ctx.undo());
+    }
+
+    fn drag(ctx: &mut Context, from: (f64, f64), to: (f64, f64), ts: f64, modifiers: Option<Modifiers>) {
+        ctx.on_pointer_down(1, from.0, from.1, ts, modifiers, mouse());
+        ctx.process_input(ts);
+        ctx.on_pointer_move(1, to.0, to.1, ts + 10.0, modifiers, mouse());
+        ctx.process_input(ts + 10.0);
+        ctx.on_pointer_up(1, to.0, to.1, ts + 20.0, modifiers, mouse());
+        ctx.process_input(ts + 20.0);
+    }
+
+    #[test]
+    fn marquees_select_touched_or_contained_nodes() {
+        let mut ctx = Context::headless();
+        ctx.add_rect(0.0, 0.0, 50.0, 50.0, Style::default());
+        ctx.add_rect(0.0, 100.0, 50.0, 50.0, Style::default());
+
+        drag(&mut ctx, (-10.0, -10.0), (120.0, 60.0), 0.0, None);
+        assert_eq!(ctx.selected_nodes, vec![1, 2]);
+
+        ctx.marquee_mode = MarqueeMode::Contain;
+        drag(&mut ctx, (-10.0, -10.0), (120.0, 60.0), 1000.0, None);
+        assert_eq!(ctx.selected_nodes, vec![1]);
+        assert!(ctx.marquee.is_none());
+    }
+
+    #[test]
+    fn shift_clicks_toggle_nodes_in_and_out_of_the_selection() {
+        let (plain, shift) = (Some(Modifiers::default()), Some(Modifiers { shift: true, ..Default::default() }));
+        let mut ctx = Context::headless();
+        ctx.add_rect(0.0, 0.0, 50.0, 50.0, Style::default());
+        ctx.add_rect(0.0, 100.0, 50.0, 50.0, Style::default());
+
+        let mut click = |x: f64, ts: f64, modifiers: Option<Modifiers>| {
+            ctx.on_pointer_down(1, x, 10.0, ts, modifiers, mouse());
+            ctx.on_pointer_up(1, x, 10.0, ts + 10.0, modifiers, mouse());
+            ctx.process_input(ts + 10.0);
+            ctx.selected_nodes.clone()
+        };
+        assert_eq!(click(10.0, 0.0, plain), vec![1]);
+        assert_eq!(click(110.0, 1000.0, shift), vec![1, 2]);
+        assert_eq!(click(10.0, 2000.0, shift), vec![2]);
+        assert_eq!(click(10.0, 3000.0, plain), vec![1]);
+    }
+
+    #[test]
+    fn dragging_one_selected_node_moves_the_whole_selection() {
+        let mut ctx = Context::headless();
+        ctx.add_rect(0.0, 0.0, 50.0, 50.0, Style::default());
+        ctx.add_rect(0.0, 100.0, 50.0, 50.0, Style::default());
+        ctx.add_rect(100.0, 0.0, 50.0, 50.0, Style::default());
+        ctx.selected_nodes = vec![1, 2];
+        ctx.snap.enabled = false;
+
+        drag(&mut ctx, (10.0, 10.0), (30.0, 40.0), 0.0, None);
+        let positions: Vec<(f64, f64)> = ctx.nodes.iter().map(|node| (node.get_bounds().left, node.get_bounds().top)).collect();
+        assert_eq!(positions, vec![(20.0, 30.0), (120.0, 30.0), (0.0, 100.0)]);
+        assert_eq!(ctx.selected_nodes, vec![1, 2]);
+
+        assert!(ctx.undo());
+        assert_eq!((ctx.nodes[1].get_bounds().left, ctx.nodes[1].get_bounds().top), (100.0, 0.0));
+    }
+
+    #[test]
+    fn the_cursor_follows_hovered_nodes_handles_and_drags() {
+        let mut ctx = Context::headless();
+        ctx.add_rect(0.0, 0.0, 100.0, 50.0, Style { cursor: Some(Cursor::Pointer), ..Default::default() });
+        ctx.add_rect(200.0, 0.0, 50.0, 50.0, Style::default());
+        let move_to = |ctx: &mut Context, x: f64, y: f64, ts: f64| {
+            ctx.on_pointer_move(1, x, y, ts, None, mouse());
+            ctx.process_input(ts);
+            ctx.cursor
+        };
+
+        assert_eq!(move_to(&mut ctx, 10.0, 10.0, 0.0), Cursor::Pointer);
+        assert_eq!(move_to(&mut ctx, 10.0, 210.0, 10.0), Cursor::Default);
+        assert_eq!(move_to(&mut ctx, 150.0, 150.0, 20.0), Cursor::Default);
+
+        // The right handle sits on the middle of the outline's right edge.
+        ctx.selected_nodes = vec![1];
+        assert_eq!(move_to(&mut ctx, 105.0, 25.0, 30.0), Cursor::EwResize);
+        assert_eq!(move_to(&mut ctx, -5.0, -5.0, 40.0), Cursor::NwseResize);
+        ctx.get_node_mut(1).unwrap().get_style_mut().rotation = Some((std::f64::consts::FRAC_PI_2, ControlPoint::Center));
+        // Turned a quarter around the centre, it sits below the node.
+        assert_eq!(ctx.cursor_at(50.0, 80.0, PointerType::Mouse), Cursor::NsResize);
+        assert_eq!(ctx.cursor_at(105.0, 25.0, PointerType::Mouse), Cursor::Grab);
+        ctx.get_node_mut(1).unwrap().get_style_mut().rotation = None;
+
+        ctx.on_pointer_down(1, 50.0, 25.0, 50.0, None, mouse());
+        ctx.process_input(50.0);
+        assert_eq!(move_to(&mut ctx, 70.0, 25.0, 60.0), Cursor::Move);
+        ctx.on_pointer_up(1, 70.0, 25.0, 70.0, None, mouse());
+        ctx.process_input(70.0);
+        assert_eq!(ctx.cursor, Cursor::Pointer);
+    }
+
+    #[test]
+    fn available_actions_follow_the_selection_clipboard_and_history() {
+        let mut ctx = Context::headless();
+        assert!(ctx.available_actions().is_empty());
+        ctx.add_rect(0.0, 0.0, 50.0, 50.0, Style::default());
+        ctx.history.clear();
+        assert!(ctx.available_actions().is_empty());
+
+        ctx.selected_nodes = vec![1];
+        let selected =
+            ["cut", "copy", "duplicate", "delete", "bring_to_front", "bring_forward", "send_backward", "send_to_back"];
+        assert_eq!(ctx.available_actions(), selected);
+        assert!(selected.iter().all(|name| BuiltinAction::from_name(name).is_some()));
+
+        assert!(ctx.run_action("copy"));
+        assert!(ctx.available_actions().contains(&"paste"));
+        assert!(ctx.run_action("delete"));
+        assert_eq!(ctx.available_actions(), ["paste", "undo"]);
+        assert!(ctx.run_action("undo"));
+        assert_eq!(ctx.available_actions(), ["paste", "redo"]);
+    }
+
+    #[test]
+    fn key_downs_report_whether_the_canvas_acts_on_them() {
+        let (plain, command) = (Some(Modifiers::default()), Some(Modifiers { ctrl: true, ..Default::default() }));
+        let mut ctx = Context::headless();
+        ctx.add_text("hi".to_string(), Point { x: 0.0, y: 0.0 }, Style::default());
+
+        assert!(ctx.on_key_down("z".to_string(), 0.0, command));
+        assert!(ctx.on_key_down("Delete".to_string(), 0.0, plain));
+        assert!(!ctx.on_key_down("x".to_string(), 0.0, plain));
+        assert!(!ctx.on_key_down("F5".to_string(), 0.0, plain));
+        ctx.keymap.bind("Mod+K", "missing").unwrap();
+        assert!(!ctx.on_key_down("k".to_string(), 0.0, command));
+
+        ctx.editing = Some(TextEditor::new(1, "hi", 0.0));
+        assert!(ctx.on_key_down("x".to_string(), 0.0, plain));
+        assert!(ctx.on_key_down("Backspace".to_string(), 0.0, plain));
+        assert!(!ctx.on_key_down("F5".to_string(), 0.0, plain));
+        assert!(ctx.on_key_down("z".to_string(), 0.0, command));
     }
 }
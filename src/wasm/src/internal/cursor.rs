@@ -0,0 +1,72 @@
+/// CSS cursors a node can ask for while the pointer is over it. The canvas
+/// picks the resize and rotate cursors for selection handles itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Cursor {
+    #[default]
+    Default,
+    Pointer,
+    Move,
+    Grab,
+    Grabbing,
+    Text,
+    Crosshair,
+    NotAllowed,
+    /// Vertical resize.
+    NsResize,
+    /// Horizontal resize.
+    EwResize,
+    /// Resize along the top-right to bottom-left diagonal.
+    NeswResize,
+    /// Resize along the top-left to bottom-right diagonal.
+    NwseResize,
+}
+
+impl Cursor {
+    pub(crate) fn to_css(self) -> &'static str {
+        match self {
+            Cursor::Default => "default",
+            Cursor::Pointer => "pointer",
+            Cursor::Move => "move",
+            Cursor::Grab => "grab",
+            Cursor::Grabbing => "grabbing",
+            Cursor::Text => "text",
+            Cursor::Crosshair => "crosshair",
+            Cursor::NotAllowed => "not-allowed",
+            Cursor::NsResize => "ns-resize",
+            Cursor::EwResize => "ew-resize",
+            Cursor::NeswResize => "nesw-resize",
+            Cursor::NwseResize => "nwse-resize",
+        }
+    }
+
+    /// Resize cursor closest to dragging along the direction `(dx, dy)`,
+    /// given in canvas space with y pointing down.
+    pub(crate) fn resize(dx: f64, dy: f64) -> Self {
+        // Directions repeat every half turn; split it into four 45° sectors
+        // centred on the horizontal, the two diagonals and the vertical.
+        let angle = dy.atan2(dx).rem_euclid(std::f64::consts::PI);
+        let sector = (angle / std::f64::consts::FRAC_PI_4).round() as u32 % 4;
+        match sector {
+            0 => Cursor::EwResize,
+            1 => Cursor::NwseResize,
+            2 => Cursor::NsResize,
+            _ => Cursor::NeswResize,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resize_cursors_pick_the_closest_direction() {
+        assert_eq!(Cursor::resize(1.0, 0.0), Cursor::EwResize);
+        assert_eq!(Cursor::resize(-1.0, 0.1), Cursor::EwResize);
+        assert_eq!(Cursor::resize(0.0, -1.0), Cursor::NsResize);
+        assert_eq!(Cursor::resize(1.0, 1.0), Cursor::NwseResize);
+        assert_eq!(Cursor::resize(-1.0, -1.0), Cursor::NwseResize);
+        assert_eq!(Cursor::resize(1.0, -1.0), Cursor::NeswResize);
+    }
+}
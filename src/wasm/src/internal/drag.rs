@@ -0,0 +1,135 @@
+use gloo_utils::format::JsValueSerdeExt;
+use wasm_bindgen::JsValue;
+
+use super::{
+    geometry::{Point, Rectangle},
+    node::NodeId,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum Axis {
+    X,
+    Y,
+}
+
+/// Region a dragged node has to stay inside.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub(crate) enum DragBounds {
+    /// A fixed rectangle in scene coordinates.
+    Rect { left: f64, top: f64, width: f64, height: f64 },
+    /// The current bounds of another node, e.g. the track of a slider.
+    Node { node: NodeId },
+}
+
+/// How a node reacts to being dragged, set per node from JS.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub(crate) struct DragOptions {
+    pub(crate) draggable: bool,
+    /// Only move along this axis. Without it, holding shift locks the drag
+    /// to whichever axis it has moved further along.
+    pub(crate) axis: Option<Axis>,
+    pub(crate) bounds: Option<DragBounds>,
+}
+
+impl Default for DragOptions {
+    fn default() -> Self {
+        Self { draggable: true, axis: None, bounds: None }
+    }
+}
+
+impl DragOptions {
+    /// Parses drag options coming from JS; missing fields keep their
+    /// defaults and `undefined` or `null` resets them all.
+    pub(crate) fn from_json(json: &JsValue) -> Result<Self, JsValue> {
+        if json.is_undefined() || json.is_null() {
+            return Ok(Self::default());
+        }
+        json.into_serde()
+            .map_err(|err| JsValue::from_str(&format!("invalid drag options: {}", err)))
+    }
+
+    /// Restricts the drag `offset` of a node to its axis. `shift` locks it
+    /// to the dominant axis when the node has none of its own.
+    pub(crate) fn lock_axis(&self, offset: Point, shift: bool) -> Point {
+        let axis = self.axis.or_else(|| {
+            shift.then(|| if offset.x.abs() >= offset.y.abs() { Axis::X } else { Axis::Y })
+        });
+        match axis {
+            Some(Axis::X) => Point { x: offset.x, y: 0.0 },
+            Some(Axis::Y) => Point { x: 0.0, y: offset.y },
+            None => offset,
+        }
+    }
+}
+
+/// Shifts `offset` so that `start` moved by it stays inside `region`. When
+/// the node is larger than the region, its top left corner is kept inside.
+pub(crate) fn clamp_offset(start: Rectangle, offset: Point, region: Rectangle) -> Point {
+    let clamp = |position: f64, size: f64, min: f64, extent: f64| {
+        position.min(min + extent - size).max(min)
+    };
+    let left = clamp(start.left + offset.x, start.width, region.left, region.width);
+    let top = clamp(start.top + offset.y, start.height, region.top, region.height);
+    Point { x: left - start.left, y: top - start.top }
+}
+
+/// Position returned by a JS drag handler.
+#[derive(Debug, Clone, Copy, serde::Deserialize)]
+pub(crate) struct DragPosition {
+    pub(crate) x: f64,
+    pub(crate) y: f64,
+}
+
+/// Offset after a drag handler answered the `proposed` one: a position puts
+/// the top left corner of `start` there, no position keeps the proposal.
+pub(crate) fn handled_offset(start: Rectangle, proposed: Point, position: Option<DragPosition>) -> Point {
+    match position {
+        Some(position) => Point { x: position.x - start.left, y: position.y - start.top },
+        None => proposed,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const START: Rectangle = Rectangle { left: 10.0, top: 10.0, width: 20.0, height: 20.0 };
+
+    #[test]
+    fn offsets_are_clamped_to_the_region() {
+        let region = Rectangle { left: 0.0, top: 0.0, width: 100.0, height: 50.0 };
+        let inside = Point { x: 30.0, y: 5.0 };
+        assert_eq!(clamp_offset(START, inside, region), inside);
+        assert_eq!(clamp_offset(START, Point { x: 200.0, y: -40.0 }, region), Point { x: 70.0, y: -10.0 });
+
+        // A node larger than the region keeps its top left corner inside.
+        let small = Rectangle { left: 0.0, top: 0.0, width: 5.0, height: 5.0 };
+        assert_eq!(clamp_offset(START, Point { x: 50.0, y: 50.0 }, small), Point { x: -10.0, y: -10.0 });
+    }
+
+    #[test]
+    fn axes_lock_to_the_option_or_the_dominant_direction() {
+        let offset = Point { x: 3.0, y: 8.0 };
+        let free = DragOptions::default();
+        assert_eq!(free.lock_axis(offset, false), offset);
+        assert_eq!(free.lock_axis(offset, true), Point { x: 0.0, y: 8.0 });
+
+        let horizontal = DragOptions { axis: Some(Axis::X), ..Default::default() };
+        assert_eq!(horizontal.lock_axis(offset, true), Point { x: 3.0, y: 0.0 });
+    }
+
+    #[test]
+    fn handlers_can_rewrite_or_reject_a_move() {
+        let proposed = Point { x: 40.0, y: 0.0 };
+        let answer = |json: serde_json::Value| handled_offset(START, proposed, serde_json::from_value(json).ok());
+
+        assert_eq!(answer(serde_json::json!({ "x": 25.0, "y": 10.0 })), Point { x: 15.0, y: 0.0 });
+        // Answering with where the drag began keeps the node in place.
+        assert_eq!(answer(serde_json::json!({ "x": 10.0, "y": 10.0 })), Point { x: 0.0, y: 0.0 });
+        assert_eq!(answer(serde_json::json!(null)), proposed);
+        assert_eq!(answer(serde_json::json!({ "x": 25.0 })), proposed);
+    }
+}
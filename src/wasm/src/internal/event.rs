@@ -1,4 +1,101 @@
-#[derive(Debug, Clone, Default, PartialOrd)]
+use wasm_bindgen::prelude::*;
+
+/// Bits of the `modifiers` argument taken by the input methods, combined
+/// with `|`.
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Modifier {
+    Shift = 1,
+    Ctrl = 2,
+    Alt = 4,
+    Meta = 8,
+}
+
+/// Mouse button that changed state, numbered like `MouseEvent.button`.
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MouseButton {
+    #[default]
+    Left = 0,
+    Middle = 1,
+    Right = 2,
+    Back = 3,
+    Forward = 4,
+}
+
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PointerType {
+    #[default]
+    Mouse,
+    Pen,
+    Touch,
+}
+
+/// Unit of the deltas passed to `on_wheel`, numbered like
+/// `WheelEvent.deltaMode`.
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeltaMode {
+    #[default]
+    Pixel = 0,
+    Line = 1,
+    Page = 2,
+}
+
+/// Modifier keys held while an event happened.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, serde::Serialize, serde::Deserialize)]
+pub(crate) struct Modifiers {
+    pub(crate) shift: bool,
+    pub(crate) ctrl: bool,
+    pub(crate) alt: bool,
+    pub(crate) meta: bool,
+}
+
+impl Modifiers {
+    pub(crate) fn from_bits(bits: u32) -> Self {
+        let has = |modifier: Modifier| bits & modifier as u32 != 0;
+        Self {
+            shift: has(Modifier::Shift),
+            ctrl: has(Modifier::Ctrl),
+            alt: has(Modifier::Alt),
+            meta: has(Modifier::Meta),
+        }
+    }
+
+    /// Ctrl, or Cmd on macOS.
+    pub(crate) fn command(&self) -> bool {
+        self.ctrl || self.meta
+    }
+
+    /// Modifiers after the key event `event`, for hosts that do not report
+    /// the modifier state themselves.
+    pub(crate) fn after_key(mut self, event: &Event) -> Self {
+        if !(event.key_down || event.key_up) {
+            return self;
+        }
+        match event.key.as_deref() {
+            Some("Shift") => self.shift = event.key_down,
+            Some("Control") => self.ctrl = event.key_down,
+            Some("Alt") => self.alt = event.key_down,
+            Some("Meta") => self.meta = event.key_down,
+            _ => {}
+        }
+        self
+    }
+}
+
+/// How a pointer event was produced, beyond its position.
+#[derive(Debug, Clone, Copy, Default, PartialEq, PartialOrd, serde::Serialize, serde::Deserialize)]
+pub(crate) struct PointerInput {
+    pub(crate) button: MouseButton,
+    pub(crate) pointer_type: PointerType,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, PartialOrd)]
 pub(crate) struct Event {
     pub(crate) mouse_x: f64,
     pub(crate) mouse_y: f64,
@@ -9,31 +106,22 @@ pub(crate) struct Event {
     pub(crate) mouse_up: bool,
     pub(crate) mouse_down: bool,
     pub(crate) mouse_moved: bool,
-    pub(crate) timestamp: u128,
+    /// The pointer's interaction was interrupted, e.g. by a multi-touch
+    /// gesture; drags stop where they are and no click is reported.
+    pub(crate) mouse_cancelled: bool,
+    pub(crate) timestamp: f64,
+    /// `None` when the host did not report modifiers with the event.
+    pub(crate) modifiers: Option<Modifiers>,
+    pub(crate) pointer: PointerInput,
 }
 
-impl PartialEq for Event {
-    fn eq(&self, other: &Self) -> bool {
-        (self.timestamp == other.timestamp)
-            && (self.key_down == other.key_down)
-            && (self.key_up == other.key_up)
-            && (self.key_pressed == other.key_pressed)
-            && (self.mouse_down == other.mouse_down)
-            && (self.mouse_up == other.mouse_up)
-            && (self.mouse_moved == other.mouse_moved)
-            && (self.key == other.key)
-    }
-}
-
-impl Eq for Event {}
-
 impl Event {
     pub(crate) fn from_mouse_up(x: f64, y: f64, ts: f64) -> Self {
         Self {
             mouse_x: x,
             mouse_y: y,
             mouse_up: true,
-            timestamp: ts as u128,
+            timestamp: ts,
             ..Default::default()
         }
     }
@@ -43,7 +131,7 @@ impl Event {
             mouse_x: x,
             mouse_y: y,
             mouse_down: true,
-            timestamp: ts as u128,
+            timestamp: ts,
             ..Default::default()
         }
     }
@@ -53,7 +141,17 @@ impl Event {
             mouse_x: x,
             mouse_y: y,
             mouse_moved: true,
-            timestamp: ts as u128,
+            timestamp: ts,
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn from_mouse_cancel(x: f64, y: f64, ts: f64) -> Self {
+        Self {
+            mouse_x: x,
+            mouse_y: y,
+            mouse_cancelled: true,
+            timestamp: ts,
             ..Default::default()
         }
     }
@@ -62,7 +160,7 @@ impl Event {
         Self {
             key: Some(key),
             key_down: true,
-            timestamp: ts as u128,
+            timestamp: ts,
             ..Default::default()
         }
     }
@@ -71,7 +169,7 @@ impl Event {
         Self {
             key: Some(key),
             key_up: true,
-            timestamp: ts as u128,
+            timestamp: ts,
             ..Default::default()
         }
     }
@@ -80,24 +178,59 @@ impl Event {
         Self {
             key: Some(key),
             key_pressed: true,
-            timestamp: ts as u128,
+            timestamp: ts,
             ..Default::default()
         }
     }
 
-    pub(crate) fn is_click(&self, last_event_opt: &mut Option<Event>) -> bool {
-        if let Some(last_event) = last_event_opt {
-            let diff = self.timestamp - last_event.timestamp;
-            if diff > 500 || !last_event.mouse_down {
-                last_event_opt.take();
-                return false;
-            }
-
-            if self.mouse_up {
-                last_event_opt.take();
-                return true;
-            }
-        }
-        false
+    pub(crate) fn with_modifiers(mut self, modifiers: Option<Modifiers>) -> Self {
+        self.modifiers = modifiers;
+        self
+    }
+
+    pub(crate) fn with_pointer(mut self, pointer: PointerInput) -> Self {
+        self.pointer = pointer;
+        self
+    }
+
+    /// Whether `next` is a move that can replace this one without losing
+    /// anything but the intermediate position.
+    pub(crate) fn can_coalesce(&self, next: &Event) -> bool {
+        self.mouse_moved
+            && next.mouse_moved
+            && self.modifiers == next.modifiers
+            && self.pointer == next.pointer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn modifier_bits_combine() {
+        let bits = Modifier::Shift as u32 | Modifier::Meta as u32;
+        let modifiers = Modifiers::from_bits(bits);
+        assert_eq!(modifiers, Modifiers { shift: true, meta: true, ..Default::default() });
+        assert!(modifiers.command());
+        assert_eq!(Modifiers::from_bits(0), Modifiers::default());
+        assert!(Modifiers::from_bits(Modifier::Ctrl as u32).command());
+        assert!(!Modifiers::from_bits(Modifier::Alt as u32).command());
+    }
+
+    #[test]
+    fn modifier_keys_are_tracked_from_key_events() {
+        let held = Modifiers::default().after_key(&Event::from_key_down("Shift".to_string(), 0.0));
+        assert!(held.shift);
+        let held = held.after_key(&Event::from_key_down("Control".to_string(), 1.0));
+        assert_eq!(held, Modifiers { shift: true, ctrl: true, ..Default::default() });
+
+        // Other keys, presses and pointer events leave them alone.
+        assert_eq!(held.after_key(&Event::from_key_down("a".to_string(), 2.0)), held);
+        assert_eq!(held.after_key(&Event::from_key_press("Shift".to_string(), 2.0)), held);
+        assert_eq!(held.after_key(&Event::from_mouse_down(0.0, 0.0, 2.0)), held);
+
+        let released = held.after_key(&Event::from_key_up("Shift".to_string(), 3.0));
+        assert_eq!(released, Modifiers { ctrl: true, ..Default::default() });
     }
 }
@@ -0,0 +1,176 @@
+use std::collections::VecDeque;
+
+use super::event::Event;
+
+/// Most events kept between two renders, e.g. while the tab is in the
+/// background and no frames are requested.
+pub(crate) const MAX_QUEUED_EVENTS: usize = 256;
+
+/// Input waiting for the next `render`. Consecutive moves collapse into the
+/// latest one, exact repeats (a host reporting the same input twice) are
+/// dropped, and presses, releases and keys keep their order.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct EventQueue {
+    events: VecDeque<Event>,
+}
+
+/// Whether `release` ends the press or key down `press`.
+fn is_release_of(press: &Event, release: &Event) -> bool {
+    if press.mouse_down {
+        (release.mouse_up || release.mouse_cancelled) && release.pointer == press.pointer
+    } else {
+        press.key_down && release.key_up && release.key == press.key
+    }
+}
+
+impl EventQueue {
+    /// Queues `event`, returning whether it changed the queue. A full queue
+    /// refuses moves but keeps every press, so no release loses its press.
+    pub(crate) fn push(&mut self, event: Event) -> bool {
+        if let Some(last) = self.events.back_mut() {
+            if *last == event {
+                return false;
+            }
+            if last.can_coalesce(&event) {
+                *last = event;
+                return true;
+            }
+        }
+
+        if self.events.len() >= MAX_QUEUED_EVENTS && !self.make_room() && event.mouse_moved {
+            return false;
+        }
+        self.events.push_back(event);
+        true
+    }
+
+    pub(crate) fn pop(&mut self) -> Option<Event> {
+        self.events.pop_front()
+    }
+
+    /// Drops the oldest move, whose position later moves supersede anyway,
+    /// or else the oldest press together with its release and the key
+    /// presses in between. Returns `false` when neither is queued.
+    fn make_room(&mut self) -> bool {
+        if let Some(index) = self.events.iter().position(|event| event.mouse_moved) {
+            self.events.remove(index);
+            return true;
+        }
+
+        let pair = self.events.iter().enumerate().find_map(|(index, press)| {
+            let release = self.events.iter().skip(index + 1).position(|release| is_release_of(press, release))?;
+            Some((index, index + 1 + release))
+        });
+        let Some((press, release)) = pair else {
+            return false;
+        };
+        let key = self.events[press].key.clone();
+        let mut index = release;
+        while index >= press {
+            let event = &self.events[index];
+            if index == press || index == release || (key.is_some() && event.key_pressed && event.key == key) {
+                self.events.remove(index);
+            }
+            if index == 0 {
+                break;
+            }
+            index -= 1;
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::internal::event::{Modifiers, MouseButton, PointerInput, PointerType};
+
+    fn drain(queue: &mut EventQueue) -> Vec<Event> {
+        std::iter::from_fn(|| queue.pop()).collect()
+    }
+
+    #[test]
+    fn consecutive_moves_collapse_into_the_latest() {
+        let mut queue = EventQueue::default();
+        assert!(queue.push(Event::from_mouse_move(1.0, 1.0, 0.0)));
+        assert!(queue.push(Event::from_mouse_move(2.0, 2.0, 1.0)));
+        assert!(queue.push(Event::from_mouse_down(2.0, 2.0, 2.0)));
+        assert!(queue.push(Event::from_mouse_move(3.0, 3.0, 3.0)));
+        assert!(queue.push(Event::from_mouse_move(4.0, 4.0, 4.0)));
+
+        let events = drain(&mut queue);
+        assert_eq!(events.len(), 3);
+        assert_eq!((events[0].mouse_x, events[0].timestamp), (2.0, 1.0));
+        assert!(events[1].mouse_down);
+        assert_eq!((events[2].mouse_x, events[2].timestamp), (4.0, 4.0));
+    }
+
+    #[test]
+    fn moves_with_other_modifiers_or_pointers_are_kept_apart() {
+        let mut queue = EventQueue::default();
+        let shift = Modifiers { shift: true, ..Default::default() };
+        let touch = PointerInput { button: MouseButton::Left, pointer_type: PointerType::Touch };
+        queue.push(Event::from_mouse_move(1.0, 1.0, 0.0));
+        queue.push(Event::from_mouse_move(2.0, 2.0, 1.0).with_modifiers(Some(shift)));
+        queue.push(Event::from_mouse_move(3.0, 3.0, 2.0).with_pointer(touch));
+        assert_eq!(drain(&mut queue).len(), 3);
+    }
+
+    #[test]
+    fn exact_repeats_are_dropped() {
+        let mut queue = EventQueue::default();
+        assert!(queue.push(Event::from_mouse_down(1.0, 1.0, 0.0)));
+        assert!(!queue.push(Event::from_mouse_down(1.0, 1.0, 0.0)));
+        assert!(queue.push(Event::from_key_down("a".to_string(), 1.0)));
+        assert!(!queue.push(Event::from_key_down("a".to_string(), 1.0)));
+        assert_eq!(drain(&mut queue).len(), 2);
+    }
+
+    #[test]
+    fn a_full_queue_drops_moves_before_presses() {
+        let mut queue = EventQueue::default();
+        queue.push(Event::from_mouse_move(0.0, 0.0, 0.0));
+        for index in 1..MAX_QUEUED_EVENTS {
+            queue.push(Event::from_key_down(format!("{}", index), index as f64));
+        }
+        queue.push(Event::from_key_down("last".to_string(), 1000.0));
+
+        let events = drain(&mut queue);
+        assert_eq!(events.len(), MAX_QUEUED_EVENTS);
+        assert!(events.iter().all(|event| !event.mouse_moved));
+    }
+
+    #[test]
+    fn a_queue_full_of_presses_drops_whole_pairs() {
+        let mut queue = EventQueue::default();
+        for index in 0..MAX_QUEUED_EVENTS / 3 {
+            let (key, ts) = (format!("{}", index), index as f64);
+            queue.push(Event::from_key_down(key.clone(), ts));
+            queue.push(Event::from_key_press(key.clone(), ts));
+            queue.push(Event::from_key_up(key, ts));
+        }
+        queue.push(Event::from_mouse_down(0.0, 0.0, 1000.0));
+        queue.push(Event::from_mouse_up(0.0, 0.0, 1001.0));
+
+        let events = drain(&mut queue);
+        assert!(events.len() <= MAX_QUEUED_EVENTS);
+        assert_eq!(events[0].key.as_deref(), Some("1"));
+        assert!(events[0].key_down);
+        assert!(events.iter().rev().take(2).all(|event| event.key.is_none()));
+    }
+
+    #[test]
+    fn a_queue_full_of_unreleased_presses_keeps_them_and_refuses_moves() {
+        let mut queue = EventQueue::default();
+        for index in 0..MAX_QUEUED_EVENTS {
+            queue.push(Event::from_key_down(format!("{}", index), index as f64));
+        }
+        assert!(!queue.push(Event::from_mouse_move(1.0, 1.0, 1000.0)));
+        assert!(queue.push(Event::from_mouse_down(1.0, 1.0, 1001.0)));
+
+        let events = drain(&mut queue);
+        assert_eq!(events.len(), MAX_QUEUED_EVENTS + 1);
+        assert_eq!(events[0].key.as_deref(), Some("0"));
+        assert!(events[MAX_QUEUED_EVENTS].mouse_down);
+    }
+}
@@ -1,6 +1,12 @@
 use std::cell::RefCell;
 
-use super::{context::{Context, BOUNDING_BOX_MARGIN}, style::Style};
+use super::{context::Context, style::Style};
+
+/// Extra room around a text's glyph bounds that still counts as hovering it.
+const TEXT_HIT_MARGIN: f64 = 5.0;
+
+/// Fill shown in place of an image that has not loaded (yet).
+const IMAGE_PLACEHOLDER_COLOR: &str = "#e0e0e0";
 
 #[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
 pub struct Point {
@@ -28,10 +34,75 @@ impl Point {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
-pub struct Size {
+/// Measured extent of a single line of text, relative to its alphabetic
+/// baseline and origin. Mirrors the fields of the DOM `TextMetrics`.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Default, serde::Serialize)]
+pub struct TextExtent {
+    /// Advance width of the text.
     pub width: f64,
-    pub height: f64,
+    /// Distance from the origin to the left-most glyph edge (positive to the left).
+    pub actual_left: f64,
+    /// Distance from the origin to the right-most glyph edge.
+    pub actual_right: f64,
+    /// Distance from the baseline to the top of the highest glyph.
+    pub actual_ascent: f64,
+    /// Distance from the baseline to the bottom of the lowest glyph.
+    pub actual_descent: f64,
+    /// Ascent of the font itself, independent of the glyphs drawn.
+    pub font_ascent: f64,
+    /// Descent of the font itself, independent of the glyphs drawn.
+    pub font_descent: f64,
+}
+
+impl TextExtent {
+    pub(crate) fn from_metrics(metrics: &web_sys::TextMetrics) -> Self {
+        Self {
+            width: metrics.width(),
+            actual_left: metrics.actual_bounding_box_left(),
+            actual_right: metrics.actual_bounding_box_right(),
+            actual_ascent: metrics.actual_bounding_box_ascent(),
+            actual_descent: metrics.actual_bounding_box_descent(),
+            font_ascent: metrics.font_bounding_box_ascent(),
+            font_descent: metrics.font_bounding_box_descent(),
+        }
+    }
+
+    /// Approximation for backends that cannot measure glyphs. The ratios are
+    /// those of a typical sans-serif face.
+    pub(crate) fn estimate(text: &str, font_size: f64) -> Self {
+        let width = text.chars().count() as f64 * font_size * 0.55;
+        let has_descender = text.chars().any(|c| "gjpqy,;()[]{}|".contains(c));
+        Self {
+            width,
+            actual_left: 0.0,
+            actual_right: width,
+            actual_ascent: font_size * 0.72,
+            actual_descent: if has_descender { font_size * 0.21 } else { 0.0 },
+            font_ascent: font_size * 0.8,
+            font_descent: font_size * 0.2,
+        }
+    }
+
+    pub fn height(&self) -> f64 {
+        self.actual_ascent + self.actual_descent
+    }
+
+    /// Extent of the same text drawn at `scale` times the font size.
+    pub(crate) fn scaled(&self, scale: f64) -> Self {
+        Self {
+            width: self.width * scale,
+            actual_left: self.actual_left * scale,
+            actual_right: self.actual_right * scale,
+            actual_ascent: self.actual_ascent * scale,
+            actual_descent: self.actual_descent * scale,
+            font_ascent: self.font_ascent * scale,
+            font_descent: self.font_descent * scale,
+        }
+    }
+
+    pub fn font_height(&self) -> f64 {
+        self.font_ascent + self.font_descent
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
@@ -58,7 +129,15 @@ pub struct Circle {
 pub struct Text {
     pub text: String,
     pub position: Point,
-    pub shape: RefCell<Option<Size>>,
+    pub shape: RefCell<Option<TextExtent>>,
+}
+
+/// Bitmap drawn into `rect`. `src` is anything an `<img>` accepts, usually
+/// a URL or a data URL.
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+pub struct Image {
+    pub src: String,
+    pub rect: Rectangle,
 }
 
 impl Line {
@@ -82,36 +161,34 @@ impl Line {
             / ((sy - ey) * sy + (ex - sx) * ex); // distance from line cross product (projected point sine)
         let distance = ((x - px) * (x - px) + (y - py) * (y - py)).sqrt();
         if distance <= (style.stroke_width.unwrap_or_default() / 2.0)
-            && a >= 0.0
-            && a <= 1.0
-            && s >= 0.0
-            && s <= 1.0
+            && (0.0..=1.0).contains(&a)
+            && (0.0..=1.0).contains(&s)
         {
             return true;
         }
         false
     }
 
-    pub(crate) fn render(&self, ctx: &Context, style: &Style, has_bounding_box: bool, is_selected: bool) {
-        ctx.context.save();
-        ctx.context.begin_path();
-        let rect = Rectangle {
+    pub(crate) fn get_rect(&self) -> Rectangle {
+        Rectangle {
             top: self.start.y,
             left: self.start.x,
             width: self.end.x - self.start.x,
             height: self.end.y - self.start.y,
-        };
-        style.apply_style(&ctx.context, rect);
-        ctx.context.move_to(self.start.x, self.start.y);
-        ctx.context.line_to(self.end.x, self.end.y);
-        if style.stroke_width.is_some() {
-            ctx.context.stroke();
         }
-        ctx.context.restore();
+    }
 
-        if has_bounding_box || is_selected {
-            ctx.draw_bounding_box(rect, &ctx.bounding_box_color, &style, is_selected);
+    pub(crate) fn render(&self, ctx: &Context, style: &Style) {
+        ctx.context().save();
+        ctx.context().begin_path();
+        let rect = self.get_rect();
+        style.apply_style(ctx.context(), rect);
+        ctx.context().move_to(self.start.x, self.start.y);
+        ctx.context().line_to(self.end.x, self.end.y);
+        if style.stroke_width.is_some() {
+            ctx.context().stroke();
         }
+        ctx.context().restore();
     }
 
     pub(crate) fn translate(&mut self, x: f64, y: f64) {
@@ -123,8 +200,33 @@ impl Line {
 }
 
 impl Rectangle {
-    fn get_rect(&self) -> Rectangle {
-        self.clone()
+    pub(crate) fn get_rect(&self) -> Rectangle {
+        *self
+    }
+
+    pub(crate) fn union(&self, other: &Rectangle) -> Rectangle {
+        let left = self.left.min(other.left);
+        let top = self.top.min(other.top);
+        Rectangle {
+            left,
+            top,
+            width: (self.left + self.width).max(other.left + other.width) - left,
+            height: (self.top + self.height).max(other.top + other.height) - top,
+        }
+    }
+
+    pub(crate) fn intersects(&self, other: &Rectangle) -> bool {
+        self.left <= other.left + other.width
+            && other.left <= self.left + self.width
+            && self.top <= other.top + other.height
+            && other.top <= self.top + self.height
+    }
+
+    pub(crate) fn contains_rect(&self, other: &Rectangle) -> bool {
+        other.left >= self.left
+            && other.top >= self.top
+            && other.left + other.width <= self.left + self.width
+            && other.top + other.height <= self.top + self.height
     }
 
     pub fn is_hovered(&self, x: f64, y: f64, style: &Style) -> bool {
@@ -201,25 +303,21 @@ impl Rectangle {
         false
     }
 
-    pub(crate) fn render(&self, ctx: &Context, style: &Style, has_bounding_box: bool, is_selected: bool) {
-        ctx.context.save();
-        ctx.context.begin_path();
+    pub(crate) fn render(&self, ctx: &Context, style: &Style) {
+        ctx.context().save();
+        ctx.context().begin_path();
         let rect = self.get_rect();
 
-        style.apply_style(&ctx.context, rect);
-        ctx.context
+        style.apply_style(ctx.context(), rect);
+        ctx.context()
             .rect(self.left, self.top, self.width, self.height);
         if style.stroke_width.is_some() {
-            ctx.context.stroke();
+            ctx.context().stroke();
         }
         if style.fill_color.is_some() {
-            ctx.context.fill();
-        }
-        ctx.context.restore();
-
-        if has_bounding_box || is_selected {
-            ctx.draw_bounding_box(rect, &ctx.bounding_box_color, &style, is_selected);
+            ctx.context().fill();
         }
+        ctx.context().restore();
     }
 
     pub(crate) fn translate(&mut self, x: f64, y: f64) {
@@ -230,6 +328,15 @@ impl Rectangle {
 }
 
 impl Circle {
+    pub(crate) fn get_rect(&self) -> Rectangle {
+        Rectangle {
+            top: self.center.y - self.radius,
+            left: self.center.x - self.radius,
+            width: self.radius * 2.0,
+            height: self.radius * 2.0,
+        }
+    }
+
     pub fn is_hovered(&self, x: f64, y: f64, style: &Style) -> bool {
         let (tx, ty) = style.translate.unwrap_or_default();
 
@@ -242,20 +349,13 @@ impl Circle {
         false
     }
 
-    pub(crate) fn render(&self, ctx: &Context, style: &Style, has_bounding_box: bool, is_selected: bool) {
-        ctx.context.save();
-        ctx.context.begin_path();
+    pub(crate) fn render(&self, ctx: &Context, style: &Style) {
+        ctx.context().save();
+        ctx.context().begin_path();
 
-        let rect = Rectangle {
-            top: self.center.y - self.radius,
-            left: self.center.x - self.radius,
-            width: self.radius * 2.0,
-            height: self.radius * 2.0,
-        };
-
-        style.apply_style(&ctx.context, rect);
+        style.apply_style(ctx.context(), self.get_rect());
 
-        ctx.context
+        ctx.context()
             .arc(
                 self.center.x,
                 self.center.y,
@@ -265,16 +365,12 @@ impl Circle {
             )
             .unwrap();
         if style.stroke_width.is_some() {
-            ctx.context.stroke();
+            ctx.context().stroke();
         }
         if style.fill_color.is_some() {
-            ctx.context.fill();
-        }
-        ctx.context.restore();
-
-        if has_bounding_box || is_selected {
-            ctx.draw_bounding_box(rect, &ctx.bounding_box_color, &style, is_selected);
+            ctx.context().fill();
         }
+        ctx.context().restore();
     }
 
     pub(crate) fn translate(&mut self, x: f64, y: f64) {
@@ -285,57 +381,51 @@ impl Circle {
 
 impl Text {
 
-    fn get_rect(&self) -> Rectangle {
-        let (width, height) = self
-            .shape
-            .borrow()
-            .and_then(|size| Some((size.width, size.height)))
-            .unwrap_or((0.0, 0.0));
-        let top = self.position.y;
-        let left = self.position.x;
+    /// Glyph bounds of the text. `position` is the top-left corner of the
+    /// font's line box, so the baseline sits at `position.y + font_ascent`.
+    pub(crate) fn get_rect(&self) -> Rectangle {
+        let extent = self.shape.borrow().unwrap_or_default();
+        if extent.width == 0.0 {
+            return Rectangle {
+                top: self.position.y,
+                left: self.position.x,
+                width: 0.0,
+                height: extent.font_height(),
+            };
+        }
 
+        let baseline = self.position.y + extent.font_ascent;
         Rectangle {
-            top,
-            left,
-            width,
-            height,
+            top: baseline - extent.actual_ascent,
+            left: self.position.x - extent.actual_left,
+            width: extent.actual_left + extent.actual_right,
+            height: extent.height(),
         }
     }
 
     fn get_wire_frame_rect(&self) -> Rectangle {
         let rect = self.get_rect();
         Rectangle {
-            left: rect.left - BOUNDING_BOX_MARGIN,
-            top: rect.top - BOUNDING_BOX_MARGIN,
-            width: rect.width + BOUNDING_BOX_MARGIN * 2.0,
-            height: rect.height + BOUNDING_BOX_MARGIN * 2.0,
+            left: rect.left - TEXT_HIT_MARGIN,
+            top: rect.top - TEXT_HIT_MARGIN,
+            width: rect.width + TEXT_HIT_MARGIN * 2.0,
+            height: rect.height + TEXT_HIT_MARGIN * 2.0,
         }
     }
 
     pub fn is_hovered(&self, x: f64, y: f64, style: &Style) -> bool {
-        return self.get_wire_frame_rect().is_hovered(x, y, style);
+        self.get_wire_frame_rect().is_hovered(x, y, style)
     }
 
-    pub(crate) fn render(&self, ctx: &Context, style: &Style, has_bounding_box: bool, is_selected: bool) {
-        let mut rect = Rectangle {
-            top: self.position.y,
-            left: self.position.x,
-            width: 0.0,
-            height: 0.0,
-        };
+    pub(crate) fn measure(&self, ctx: &Context, style: &Style) -> TextExtent {
+        let extent = ctx.measure_text(&self.text, style);
+        *self.shape.borrow_mut() = Some(extent);
+        extent
+    }
 
-        {
-            ctx.context.save();
-            style.apply_style(&ctx.context, rect);
-
-            let measured_text = ctx.context.measure_text(&self.text).unwrap();
-            let width = measured_text.width();
-            let height = style.font_size.unwrap_or_default();
-            rect.width = width;
-            rect.height = height;
-            *self.shape.borrow_mut() = Some(Size { width, height });
-            ctx.context.restore();
-        }
+    pub(crate) fn render(&self, ctx: &Context, style: &Style) {
+        let extent = self.measure(ctx, style);
+        let rect = self.get_rect();
 
         // grid
         // {
@@ -369,26 +459,22 @@ impl Text {
 
         // }
 
+        ctx.context().save();
+        style.apply_style(ctx.context(), rect);
+        ctx.context().set_font(&style.font());
+        ctx.context().set_text_baseline("alphabetic");
 
-        ctx.context.save();
-        style.apply_style(&ctx.context, rect);
-
-        ctx.context.set_text_baseline("bottom");
-
+        let baseline = self.position.y + extent.font_ascent;
         if style.fill_color.is_some() {
-            ctx.context
-                .fill_text(&self.text, self.position.x, self.position.y + rect.height)
+            ctx.context()
+                .fill_text(&self.text, self.position.x, baseline)
                 .unwrap();
         } else if style.stroke_width.is_some() {
-            ctx.context
-                .stroke_text(&self.text, self.position.x, self.position.y + rect.height)
+            ctx.context()
+                .stroke_text(&self.text, self.position.x, baseline)
                 .unwrap();
         }
-        ctx.context.restore();
-
-        if has_bounding_box || is_selected {
-            ctx.draw_bounding_box(rect, &ctx.bounding_box_color, &style, is_selected);
-        }
+        ctx.context().restore();
 
     }
 
@@ -397,3 +483,91 @@ impl Text {
         self.position.y += y;
     }
 }
+
+impl Image {
+    pub(crate) fn get_rect(&self) -> Rectangle {
+        self.rect
+    }
+
+    pub fn is_hovered(&self, x: f64, y: f64, style: &Style) -> bool {
+        self.rect.is_hovered(x, y, style)
+    }
+
+    /// Draws the bitmap once it has loaded and a placeholder frame until
+    /// then. The stroke, if any, is drawn around the bitmap.
+    pub(crate) fn render(&self, ctx: &Context, style: &Style) {
+        let Rectangle { left, top, width, height } = self.rect;
+        ctx.context().save();
+        ctx.context().begin_path();
+        style.apply_style(ctx.context(), self.rect);
+        ctx.context().rect(left, top, width, height);
+
+        match ctx.image_element(&self.src) {
+            Some(image) if image.complete() && image.natural_width() > 0 => {
+                let _ = ctx
+                    .context()
+                    .draw_image_with_html_image_element_and_dw_and_dh(&image, left, top, width, height);
+            }
+            _ => {
+                ctx.context().set_fill_style_str(IMAGE_PLACEHOLDER_COLOR);
+                ctx.context().fill();
+            }
+        }
+        if style.stroke_width.is_some() {
+            ctx.context().stroke();
+        }
+        ctx.context().restore();
+    }
+
+    pub(crate) fn translate(&mut self, x: f64, y: f64) {
+        self.rect.translate(x, y);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(value: &str, font_size: f64) -> Text {
+        let extent = TextExtent::estimate(value, font_size);
+        Text { text: value.to_string(), position: Point { x: 10.0, y: 20.0 }, shape: RefCell::new(Some(extent)) }
+    }
+
+    #[test]
+    fn text_bounds_wrap_the_glyphs_below_the_line_box_top() {
+        let extent = TextExtent::estimate("hello", 20.0);
+        let rect = text("hello", 20.0).get_rect();
+        assert_eq!(rect.left, 10.0);
+        assert_eq!(rect.width, extent.width);
+        // The baseline is one font ascent below the position.
+        assert_eq!(rect.top, 20.0 + extent.font_ascent - extent.actual_ascent);
+        assert_eq!(rect.height, extent.actual_ascent);
+
+        // Descenders reach below the baseline.
+        let descending = text("jump", 20.0).get_rect();
+        assert!(descending.top + descending.height > rect.top + rect.height);
+    }
+
+    #[test]
+    fn empty_text_keeps_the_height_of_a_line() {
+        let rect = text("", 20.0).get_rect();
+        assert_eq!((rect.left, rect.top, rect.width), (10.0, 20.0, 0.0));
+        assert_eq!(rect.height, TextExtent::estimate("", 20.0).font_height());
+    }
+
+    #[test]
+    fn scaled_extents_match_a_larger_font() {
+        let scaled = TextExtent::estimate("glyph", 10.0).scaled(3.0);
+        let larger = TextExtent::estimate("glyph", 30.0);
+        assert!((scaled.width - larger.width).abs() < 1e-9);
+        assert!((scaled.height() - larger.height()).abs() < 1e-9);
+        assert!((scaled.font_height() - larger.font_height()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn headless_canvases_estimate_text() {
+        let ctx = Context::headless();
+        let style = Style { font_size: Some(16.0), ..Default::default() };
+        assert_eq!(ctx.measure_text("abc", &style), TextExtent::estimate("abc", 16.0));
+    }
+}
@@ -0,0 +1,273 @@
+use super::{event::PointerType, geometry::Point, viewport::Viewport};
+
+/// How long a touch has to stay put to become a long-press, in ms.
+const LONG_PRESS_DELAY: f64 = 500.0;
+/// Distance a touch may travel and still count as a long-press.
+const TAP_SLOP: f64 = 10.0;
+
+/// Outcome of feeding a pointer event to the `GestureRecognizer`. Positions
+/// are in screen coordinates.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Gesture {
+    /// The primary pointer went down, moved or was released; these drive
+    /// the regular selection and drag handling. `Move` also reports a mouse
+    /// or pen hovering with no button pressed. Taps and double-taps are
+    /// told apart later, like mouse clicks.
+    Press(Point),
+    Move(Point),
+    Release(Point),
+    /// Whatever the primary pointer was doing is abandoned, because another
+    /// pointer joined in or the host cancelled it.
+    Cancel(Point),
+    /// Two pointers are panning, pinching or rotating the view around
+    /// their midpoint `centroid`.
+    Transform { viewport: Viewport, centroid: Point },
+    /// A touch stayed in place long enough to act like a right click.
+    LongPress(Point),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct TrackedPointer {
+    id: i32,
+    pointer_type: PointerType,
+    position: Point,
+    start: Point,
+    start_time: f64,
+}
+
+impl TrackedPointer {
+    fn has_moved(&self) -> bool {
+        distance(self.start, self.position) > TAP_SLOP
+    }
+}
+
+/// Snapshot taken when a second pointer goes down; the view follows how the
+/// two pointers move relative to it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct TwoPointerGesture {
+    centroid: Point,
+    distance: f64,
+    angle: f64,
+    viewport: Viewport,
+}
+
+fn distance(a: Point, b: Point) -> f64 {
+    ((a.x - b.x).powi(2) + (a.y - b.y).powi(2)).sqrt()
+}
+
+fn centroid(a: Point, b: Point) -> Point {
+    Point { x: (a.x + b.x) / 2.0, y: (a.y + b.y) / 2.0 }
+}
+
+fn angle(a: Point, b: Point) -> f64 {
+    (b.y - a.y).atan2(b.x - a.x)
+}
+
+/// Tracks every active pointer by id and turns raw pointer events into
+/// single-pointer interactions and multi-pointer gestures.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct GestureRecognizer {
+    pointers: Vec<TrackedPointer>,
+    two_pointer: Option<TwoPointerGesture>,
+    /// Set once the primary pointer has been taken over by a gesture; its
+    /// remaining events are swallowed until every pointer is lifted.
+    is_primary_cancelled: bool,
+}
+
+impl GestureRecognizer {
+    pub(crate) fn pointer_down(
+        &mut self,
+        id: i32,
+        position: Point,
+        ts: f64,
+        pointer_type: PointerType,
+        viewport: Viewport,
+    ) -> Vec<Gesture> {
+        self.pointers.retain(|pointer| pointer.id != id);
+        self.pointers.push(TrackedPointer { id, pointer_type, position, start: position, start_time: ts });
+
+        match self.pointers.as_slice() {
+            [_] => {
+                self.is_primary_cancelled = false;
+                vec![Gesture::Press(position)]
+            }
+            [first, second] => {
+                self.two_pointer = Some(TwoPointerGesture {
+                    centroid: centroid(first.position, second.position),
+                    distance: distance(first.position, second.position),
+                    angle: angle(first.position, second.position),
+                    viewport,
+                });
+                self.cancel_primary()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    pub(crate) fn pointer_move(&mut self, id: i32, position: Point, pointer_type: PointerType) -> Vec<Gesture> {
+        let Some(index) = self.pointers.iter().position(|pointer| pointer.id == id) else {
+            // A hovering mouse or pen is never pressed and so never tracked;
+            // its moves still drive hover, unless another pointer is busy.
+            if pointer_type != PointerType::Touch && self.pointers.is_empty() {
+                return vec![Gesture::Move(position)];
+            }
+            return Vec::new();
+        };
+        self.pointers[index].position = position;
+
+        if let (Some(gesture), [first, second, ..]) = (self.two_pointer, self.pointers.as_slice()) {
+            if index > 1 {
+                return Vec::new();
+            }
+            let (a, b) = (first.position, second.position);
+            let scale = gesture.viewport.scale * distance(a, b) / gesture.distance.max(1.0);
+            let rotation = gesture.viewport.rotation + angle(a, b) - gesture.angle;
+            let target = centroid(a, b);
+            let viewport = gesture.viewport.anchored(gesture.centroid, target, scale, rotation);
+            return vec![Gesture::Transform { viewport, centroid: target }];
+        }
+
+        if index == 0 && !self.is_primary_cancelled {
+            return vec![Gesture::Move(position)];
+        }
+        Vec::new()
+    }
+
+    pub(crate) fn pointer_up(&mut self, id: i32, position: Point) -> Vec<Gesture> {
+        if self.remove(id).is_none() {
+            return Vec::new();
+        }
+        if self.is_primary_cancelled || !self.pointers.is_empty() {
+            self.reset_when_lifted();
+            return Vec::new();
+        }
+        vec![Gesture::Release(position)]
+    }
+
+    pub(crate) fn pointer_cancel(&mut self, id: i32) -> Vec<Gesture> {
+        let Some(pointer) = self.remove(id) else {
+            return Vec::new();
+        };
+        let gestures = if self.is_primary_cancelled {
+            Vec::new()
+        } else {
+            vec![Gesture::Cancel(pointer.position)]
+        };
+        self.is_primary_cancelled = true;
+        self.reset_when_lifted();
+        gestures
+    }
+
+    /// Checks for a long-press; called every frame since a touch that stays
+    /// put produces no events.
+    pub(crate) fn poll(&mut self, now: f64) -> Vec<Gesture> {
+        match self.pointers.as_slice() {
+            [pointer] if !self.is_primary_cancelled
+                && pointer.pointer_type == PointerType::Touch
+                && !pointer.has_moved()
+                && now - pointer.start_time >= LONG_PRESS_DELAY =>
+            {
+                let position = pointer.position;
+                let mut gestures = self.cancel_primary();
+                gestures.push(Gesture::LongPress(position));
+                gestures
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    fn cancel_primary(&mut self) -> Vec<Gesture> {
+        if self.is_primary_cancelled {
+            return Vec::new();
+        }
+        self.is_primary_cancelled = true;
+        match self.pointers.first() {
+            Some(primary) => vec![Gesture::Cancel(primary.position)],
+            None => Vec::new(),
+        }
+    }
+
+    fn remove(&mut self, id: i32) -> Option<TrackedPointer> {
+        let index = self.pointers.iter().position(|pointer| pointer.id == id)?;
+        if index < 2 {
+            self.two_pointer = None;
+        }
+        Some(self.pointers.remove(index))
+    }
+
+    fn reset_when_lifted(&mut self) {
+        if self.pointers.is_empty() {
+            self.is_primary_cancelled = false;
+            self.two_pointer = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(x: f64, y: f64) -> Point {
+        Point { x, y }
+    }
+
+    #[test]
+    fn hovering_mouse_and_pen_report_moves() {
+        let mut gestures = GestureRecognizer::default();
+        assert_eq!(gestures.pointer_move(1, point(5.0, 6.0), PointerType::Mouse), vec![Gesture::Move(point(5.0, 6.0))]);
+        assert_eq!(gestures.pointer_move(2, point(7.0, 8.0), PointerType::Pen), vec![Gesture::Move(point(7.0, 8.0))]);
+    }
+
+    #[test]
+    fn untracked_touches_and_other_pointers_during_a_press_are_ignored() {
+        let mut gestures = GestureRecognizer::default();
+        assert!(gestures.pointer_move(1, point(5.0, 6.0), PointerType::Touch).is_empty());
+
+        gestures.pointer_down(1, point(0.0, 0.0), 0.0, PointerType::Touch, Viewport::default());
+        assert!(gestures.pointer_move(2, point(5.0, 6.0), PointerType::Mouse).is_empty());
+    }
+
+    #[test]
+    fn primary_pointer_presses_moves_and_releases() {
+        let mut gestures = GestureRecognizer::default();
+        let down = gestures.pointer_down(1, point(0.0, 0.0), 0.0, PointerType::Mouse, Viewport::default());
+        assert_eq!(down, vec![Gesture::Press(point(0.0, 0.0))]);
+        let moved = gestures.pointer_move(1, point(30.0, 0.0), PointerType::Mouse);
+        assert_eq!(moved, vec![Gesture::Move(point(30.0, 0.0))]);
+        let up = gestures.pointer_up(1, point(30.0, 0.0));
+        assert!(matches!(up.as_slice(), [Gesture::Release(_)]));
+    }
+
+    #[test]
+    fn second_pointer_cancels_the_primary_and_transforms_the_view() {
+        let mut gestures = GestureRecognizer::default();
+        let viewport = Viewport::default();
+        gestures.pointer_down(1, point(0.0, 0.0), 0.0, PointerType::Touch, viewport);
+        let down = gestures.pointer_down(2, point(100.0, 0.0), 0.0, PointerType::Touch, viewport);
+        assert_eq!(down, vec![Gesture::Cancel(point(0.0, 0.0))]);
+
+        let moved = gestures.pointer_move(2, point(200.0, 0.0), PointerType::Touch);
+        match moved.as_slice() {
+            [Gesture::Transform { viewport, centroid }] => {
+                assert_eq!(*centroid, point(100.0, 0.0));
+                assert!((viewport.scale - 2.0).abs() < 1e-9);
+            }
+            other => panic!("expected a transform, got {:?}", other),
+        }
+
+        assert!(gestures.pointer_up(2, point(200.0, 0.0)).is_empty());
+        assert!(gestures.pointer_up(1, point(0.0, 0.0)).is_empty());
+    }
+
+    #[test]
+    fn touch_held_in_place_becomes_a_long_press() {
+        let mut gestures = GestureRecognizer::default();
+        gestures.pointer_down(1, point(10.0, 10.0), 0.0, PointerType::Touch, Viewport::default());
+        assert!(gestures.poll(LONG_PRESS_DELAY - 1.0).is_empty());
+        assert_eq!(
+            gestures.poll(LONG_PRESS_DELAY),
+            vec![Gesture::Cancel(point(10.0, 10.0)), Gesture::LongPress(point(10.0, 10.0))]
+        );
+        assert!(gestures.poll(LONG_PRESS_DELAY * 2.0).is_empty());
+    }
+}
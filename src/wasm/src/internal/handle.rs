@@ -0,0 +1,476 @@
+use super::{
+    cursor::Cursor,
+    event::PointerType,
+    geometry::{Line, Point, Rectangle},
+    node::{Node, NodeId},
+    style::{ControlPoint, Style, DEFAULT_FONT_SIZE},
+    theme::SelectionTheme,
+};
+
+/// Extra distance around a handle that still grabs it.
+const HANDLE_HIT_SLOP: f64 = 3.0;
+/// Same, for the less precise touch input.
+const TOUCH_HANDLE_HIT_SLOP: f64 = 12.0;
+/// Smallest width or height a resize can shrink a node to.
+const MIN_SIZE: f64 = 1.0;
+/// Distance of the rotation handle above the selection outline.
+pub(crate) const ROTATION_HANDLE_OFFSET: f64 = 24.0;
+/// Angle rotations snap to while shift is held.
+const ROTATION_SNAP: f64 = std::f64::consts::PI / 12.0;
+
+/// Grips drawn on the selected node. Box handles are named after the edge or
+/// corner they sit on; lines only get a handle on each endpoint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Handle {
+    TopLeft,
+    Top,
+    TopRight,
+    Right,
+    BottomRight,
+    Bottom,
+    BottomLeft,
+    Left,
+    LineStart,
+    LineEnd,
+    Rotate,
+}
+
+impl Handle {
+    const BOX: [Handle; 8] = [
+        Handle::TopLeft,
+        Handle::Top,
+        Handle::TopRight,
+        Handle::Right,
+        Handle::BottomRight,
+        Handle::Bottom,
+        Handle::BottomLeft,
+        Handle::Left,
+    ];
+
+    /// Which horizontal and vertical edge the handle drags: -1 for left/top,
+    /// 1 for right/bottom and 0 when that axis is left alone.
+    fn direction(self) -> (f64, f64) {
+        match self {
+            Handle::TopLeft => (-1.0, -1.0),
+            Handle::Top => (0.0, -1.0),
+            Handle::TopRight => (1.0, -1.0),
+            Handle::Right => (1.0, 0.0),
+            Handle::BottomRight => (1.0, 1.0),
+            Handle::Bottom => (0.0, 1.0),
+            Handle::BottomLeft => (-1.0, 1.0),
+            Handle::Left => (-1.0, 0.0),
+            Handle::LineStart | Handle::LineEnd | Handle::Rotate => (0.0, 0.0),
+        }
+    }
+
+    /// Cursor shown over the handle of a node rotated by `rotation`, so the
+    /// resize arrows follow the edge they drag.
+    pub(crate) fn cursor(self, rotation: f64) -> Cursor {
+        match self {
+            Handle::Rotate => Cursor::Grab,
+            Handle::LineStart | Handle::LineEnd => Cursor::Crosshair,
+            _ => {
+                let (hx, hy) = self.direction();
+                let (sin, cos) = rotation.sin_cos();
+                Cursor::resize(hx * cos - hy * sin, hx * sin + hy * cos)
+            }
+        }
+    }
+
+    /// Handles of `node` with their position in the node's untransformed space.
+    pub(crate) fn positions(node: &Node, theme: &SelectionTheme) -> Vec<(Handle, Point)> {
+        if let Node::Line(line, ..) = node {
+            return vec![(Handle::LineStart, line.start), (Handle::LineEnd, line.end)];
+        }
+
+        let outline = theme.outline(node.get_rect());
+        let (cx, cy) = (outline.left + outline.width / 2.0, outline.top + outline.height / 2.0);
+        let mut handles: Vec<(Handle, Point)> = Self::BOX
+            .iter()
+            .map(|&handle| {
+                let (hx, hy) = handle.direction();
+                let point = Point {
+                    x: cx + hx * outline.width / 2.0,
+                    y: cy + hy * outline.height / 2.0,
+                };
+                (handle, point)
+            })
+            .collect();
+        handles.push((Handle::Rotate, Self::rotation_anchor(outline)));
+        handles
+    }
+
+    /// Position of the rotation handle for a selection outline.
+    pub(crate) fn rotation_anchor(outline: Rectangle) -> Point {
+        Point {
+            x: outline.left + outline.width / 2.0,
+            y: outline.top - ROTATION_HANDLE_OFFSET,
+        }
+    }
+
+    /// Handle of `node` under the canvas point `(x, y)`, if any.
+    pub(crate) fn hit(node: &Node, theme: &SelectionTheme, x: f64, y: f64, pointer_type: PointerType) -> Option<Handle> {
+        let (lx, ly) = node.get_style().to_local(x, y, node.get_rect());
+        let slop = match pointer_type {
+            PointerType::Touch => TOUCH_HANDLE_HIT_SLOP,
+            PointerType::Mouse | PointerType::Pen => HANDLE_HIT_SLOP,
+        };
+        let reach = theme.handle.size / 2.0 + slop;
+        Self::positions(node, theme)
+            .into_iter()
+            .find(|(_, point)| (point.x - lx).abs() <= reach && (point.y - ly).abs() <= reach)
+            .map(|(handle, _)| handle)
+    }
+}
+
+/// An in-progress drag of one of the selected node's handles. Every update
+/// is computed from the snapshot taken when the drag started, so rounding
+/// never accumulates.
+#[derive(Debug, Clone)]
+pub(crate) struct ResizeState {
+    pub(crate) node: NodeId,
+    pub(crate) handle: Handle,
+    original: Node,
+    start: Point,
+}
+
+fn rotate(point: Point, angle: f64) -> Point {
+    let (sin, cos) = angle.sin_cos();
+    Point {
+        x: point.x * cos - point.y * sin,
+        y: point.x * sin + point.y * cos,
+    }
+}
+
+/// Offset to add to the resized geometry so the points a resize did not move
+/// stay at the same spot on the canvas, even though the rotation pivot is
+/// resolved against bounds that changed from `before` to `after`.
+fn pivot_correction(style: &Style, before: Rectangle, after: Rectangle) -> Point {
+    let Some((angle, cp)) = style.rotation else {
+        return Point { x: 0.0, y: 0.0 };
+    };
+    let (px, py) = cp.resolve_coords(before.left, before.top, before.width, before.height);
+    let (qx, qy) = cp.resolve_coords(after.left, after.top, after.width, after.height);
+    let d = Point { x: px - qx, y: py - qy };
+    let rotated = rotate(d, angle);
+    Point { x: d.x - rotated.x, y: d.y - rotated.y }
+}
+
+impl ResizeState {
+    pub(crate) fn new(node: &Node, handle: Handle, x: f64, y: f64) -> Self {
+        Self {
+            node: node.id(),
+            handle,
+            original: node.clone(),
+            start: Point { x, y },
+        }
+    }
+
+    /// Node resized for the pointer at `(x, y)`. `keep_aspect` preserves the
+    /// original proportions (or, for lines, snaps to 45°) and `from_center`
+    /// resizes symmetrically around the centre.
+    pub(crate) fn resize(&self, x: f64, y: f64, keep_aspect: bool, from_center: bool) -> Node {
+        let style = self.original.get_style();
+        let angle = style.rotation.map(|(angle, _)| angle).unwrap_or_default();
+        let delta = rotate(Point { x: x - self.start.x, y: y - self.start.y }, -angle);
+
+        let mut node = self.original.clone();
+        if let Node::Line(line, ..) = &mut node {
+            self.resize_line(line, delta, keep_aspect, from_center);
+            let correction = pivot_correction(style, self.original.get_rect(), line.get_rect());
+            line.translate(correction.x, correction.y);
+            node.set_is_dirty(true);
+            return node;
+        }
+
+        // Circles and text cannot be stretched on one axis only.
+        let keep_aspect = keep_aspect || !matches!(node, Node::Rect(..) | Node::Image(..));
+        let before = self.original.get_rect();
+        let mut after = self.resize_box(before, delta, keep_aspect, from_center);
+        let correction = pivot_correction(style, before, after);
+        after.left += correction.x;
+        after.top += correction.y;
+
+        match &mut node {
+            Node::Rect(rect, ..) => *rect = after,
+            Node::Image(image, ..) => image.rect = after,
+            Node::Circle(circle, ..) => {
+                circle.radius = after.width / 2.0;
+                circle.center = Point {
+                    x: after.left + after.width / 2.0,
+                    y: after.top + after.height / 2.0,
+                };
+            }
+            Node::Text(text, style, ..) => {
+                let scale = after.height / before.height.max(MIN_SIZE);
+                let font_size = style.font_size.unwrap_or(DEFAULT_FONT_SIZE);
+                style.font_size = Some(font_size * scale);
+                text.position = Point {
+                    x: after.left + (text.position.x - before.left) * scale,
+                    y: after.top + (text.position.y - before.top) * scale,
+                };
+                let extent = text.shape.borrow().map(|extent| extent.scaled(scale));
+                *text.shape.borrow_mut() = extent;
+            }
+            Node::Line(..) => {}
+        }
+        node.set_is_dirty(true);
+        node
+    }
+
+    fn resize_box(&self, rect: Rectangle, delta: Point, keep_aspect: bool, from_center: bool) -> Rectangle {
+        let (hx, hy) = self.handle.direction();
+        let factor = if from_center { 2.0 } else { 1.0 };
+        let mut width = (rect.width + hx * delta.x * factor).max(MIN_SIZE);
+        let mut height = (rect.height + hy * delta.y * factor).max(MIN_SIZE);
+
+        if keep_aspect && rect.width > 0.0 && rect.height > 0.0 {
+            let scale = match (hx != 0.0, hy != 0.0) {
+                (true, true) => (width / rect.width).max(height / rect.height),
+                (true, false) => width / rect.width,
+                _ => height / rect.height,
+            };
+            width = (rect.width * scale).max(MIN_SIZE);
+            height = (rect.height * scale).max(MIN_SIZE);
+        }
+
+        // Edges that are not dragged stay put, unless resizing around the
+        // centre or the other axis grew to keep the aspect ratio.
+        let place = |start: f64, size: f64, new_size: f64, direction: f64| {
+            if from_center || direction == 0.0 {
+                start + (size - new_size) / 2.0
+            } else if direction < 0.0 {
+                start + size - new_size
+            } else {
+                start
+            }
+        };
+
+        Rectangle {
+            left: place(rect.left, rect.width, width, hx),
+            top: place(rect.top, rect.height, height, hy),
+            width,
+            height,
+        }
+    }
+
+    fn resize_line(&self, line: &mut Line, delta: Point, keep_aspect: bool, from_center: bool) {
+        let (moving, fixed) = match self.handle {
+            Handle::LineStart => (&mut line.start, line.end),
+            _ => (&mut line.end, line.start),
+        };
+        let original = *moving;
+        let mut target = Point { x: original.x + delta.x, y: original.y + delta.y };
+
+        let anchor = if from_center {
+            Point { x: (original.x + fixed.x) / 2.0, y: (original.y + fixed.y) / 2.0 }
+        } else {
+            fixed
+        };
+
+        if keep_aspect {
+            let (dx, dy) = (target.x - anchor.x, target.y - anchor.y);
+            let step = std::f64::consts::FRAC_PI_4;
+            let angle = (dy.atan2(dx) / step).round() * step;
+            let length = (dx * dx + dy * dy).sqrt();
+            target = Point { x: anchor.x + length * angle.cos(), y: anchor.y + length * angle.sin() };
+        }
+
+        *moving = target;
+        if from_center {
+            let mirrored = Point { x: 2.0 * anchor.x - target.x, y: 2.0 * anchor.y - target.y };
+            match self.handle {
+                Handle::LineStart => line.end = mirrored,
+                _ => line.start = mirrored,
+            }
+        }
+    }
+}
+
+/// An in-progress drag of the rotation handle. The node turns around its
+/// rotation control point, defaulting to its centre.
+#[derive(Debug, Clone)]
+pub(crate) struct RotateState {
+    pub(crate) node: NodeId,
+    pub(crate) pointer: Point,
+    pub(crate) angle: f64,
+    original: Node,
+    pivot: Point,
+    start_angle: f64,
+}
+
+impl RotateState {
+    pub(crate) fn new(node: &Node, x: f64, y: f64) -> Self {
+        let rect = node.get_rect();
+        let style = node.get_style();
+        let (angle, cp) = style.rotation.unwrap_or((0.0, ControlPoint::Center));
+        let (px, py) = cp.resolve_coords(rect.left, rect.top, rect.width, rect.height);
+        let (tx, ty) = style.translate.unwrap_or_default();
+        let pivot = Point { x: px + tx, y: py + ty };
+
+        Self {
+            node: node.id(),
+            pointer: Point { x, y },
+            angle,
+            original: node.clone(),
+            pivot,
+            start_angle: (y - pivot.y).atan2(x - pivot.x),
+        }
+    }
+
+    /// Node rotated for the pointer at `(x, y)`; `snap` rounds the resulting
+    /// angle to 15° steps.
+    pub(crate) fn rotate(&mut self, x: f64, y: f64, snap: bool) -> Node {
+        let mut node = self.original.clone();
+        let style = node.get_style_mut();
+        let (original, cp) = style.rotation.unwrap_or((0.0, ControlPoint::Center));
+
+        let mut angle = original + (y - self.pivot.y).atan2(x - self.pivot.x) - self.start_angle;
+        if snap {
+            angle = (angle / ROTATION_SNAP).round() * ROTATION_SNAP;
+        }
+
+        style.rotation = Some((angle, cp));
+        node.set_is_dirty(true);
+        self.pointer = Point { x, y };
+        self.angle = angle;
+        node
+    }
+
+    /// Current angle in whole degrees within `0..360`, for the live readout.
+    pub(crate) fn degrees(&self) -> f64 {
+        self.angle.to_degrees().rem_euclid(360.0).round() % 360.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use super::*;
+    use crate::internal::geometry::{Text, TextExtent};
+
+    fn rect(left: f64, top: f64, width: f64, height: f64, rotation: Option<(f64, ControlPoint)>) -> Node {
+        let style = Style { rotation, ..Default::default() };
+        Node::Rect(Rectangle { left, top, width, height }, style, true, 1)
+    }
+
+    /// Where the point `(x, y)` of the node's own geometry is drawn.
+    fn on_canvas(node: &Node, x: f64, y: f64) -> Point {
+        let Some((angle, cp)) = node.get_style().rotation else {
+            return Point { x, y };
+        };
+        let rect = node.get_rect();
+        let (px, py) = cp.resolve_coords(rect.left, rect.top, rect.width, rect.height);
+        let turned = rotate(Point { x: x - px, y: y - py }, angle);
+        Point { x: px + turned.x, y: py + turned.y }
+    }
+
+    fn corners(node: &Node) -> (Point, Point) {
+        let rect = node.get_rect();
+        (on_canvas(node, rect.left, rect.top), on_canvas(node, rect.left + rect.width, rect.top + rect.height))
+    }
+
+    fn assert_near(a: Point, b: Point) {
+        assert!((a.x - b.x).abs() < 1e-9 && (a.y - b.y).abs() < 1e-9, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn resizing_a_rotated_rect_keeps_the_opposite_corner_in_place() {
+        for cp in [ControlPoint::Center, ControlPoint::BottomRight, ControlPoint::Custom(30.0, 80.0)] {
+            let node = rect(10.0, 20.0, 100.0, 50.0, Some((0.5, cp)));
+            let (top_left, bottom_right) = corners(&node);
+            let state = ResizeState::new(&node, Handle::BottomRight, bottom_right.x, bottom_right.y);
+
+            let pointer = Point { x: bottom_right.x + 20.0, y: bottom_right.y + 35.0 };
+            let resized = state.resize(pointer.x, pointer.y, false, false);
+            let (new_top_left, new_bottom_right) = corners(&resized);
+            assert_near(new_top_left, top_left);
+            assert_near(new_bottom_right, pointer);
+        }
+    }
+
+    #[test]
+    fn keeping_the_aspect_follows_the_larger_change() {
+        let node = rect(0.0, 0.0, 100.0, 50.0, None);
+        let corner = ResizeState::new(&node, Handle::BottomRight, 100.0, 50.0);
+        assert_eq!(corner.resize(150.0, 50.0, true, false).get_rect(), Rectangle { left: 0.0, top: 0.0, width: 150.0, height: 75.0 });
+
+        // Dragging an edge grows the other axis around its middle.
+        let edge = ResizeState::new(&node, Handle::Right, 100.0, 25.0);
+        assert_eq!(edge.resize(200.0, 25.0, true, false).get_rect(), Rectangle { left: 0.0, top: -25.0, width: 200.0, height: 100.0 });
+    }
+
+    #[test]
+    fn resizing_from_the_center_moves_both_sides() {
+        let node = rect(0.0, 0.0, 100.0, 50.0, None);
+        let state = ResizeState::new(&node, Handle::Right, 100.0, 25.0);
+        assert_eq!(state.resize(110.0, 40.0, false, true).get_rect(), Rectangle { left: -10.0, top: 0.0, width: 120.0, height: 50.0 });
+    }
+
+    #[test]
+    fn sizes_stop_at_the_minimum() {
+        let node = rect(0.0, 0.0, 100.0, 50.0, None);
+        let state = ResizeState::new(&node, Handle::TopLeft, 0.0, 0.0);
+        let rect = state.resize(500.0, 500.0, false, false).get_rect();
+        assert_eq!((rect.width, rect.height), (MIN_SIZE, MIN_SIZE));
+        assert_eq!((rect.left + rect.width, rect.top + rect.height), (100.0, 50.0));
+    }
+
+    #[test]
+    fn lines_keeping_the_aspect_snap_to_45_degrees() {
+        let start = Point { x: 0.0, y: 0.0 };
+        let node = Node::Line(Line { start, end: Point { x: 100.0, y: 0.0 } }, Style::default(), true, 1);
+        let state = ResizeState::new(&node, Handle::LineEnd, 100.0, 0.0);
+
+        let Node::Line(line, ..) = state.resize(100.0, 90.0, true, false) else { unreachable!() };
+        assert_eq!(line.start, start);
+        assert!((line.end.x - line.end.y).abs() < 1e-9);
+        assert!((line.end.x.hypot(line.end.y) - 100.0_f64.hypot(90.0)).abs() < 1e-9);
+
+        let Node::Line(line, ..) = state.resize(100.0, 90.0, false, false) else { unreachable!() };
+        assert_eq!(line.end, Point { x: 100.0, y: 90.0 });
+    }
+
+    #[test]
+    fn resizing_text_scales_its_font() {
+        let extent = TextExtent::estimate("hello", 20.0);
+        let text = Text { text: "hello".to_string(), position: Point { x: 0.0, y: 0.0 }, shape: RefCell::new(Some(extent)) };
+        let style = Style { font_size: Some(20.0), ..Default::default() };
+        let node = Node::Text(text, style, true, 1);
+        let height = node.get_rect().height;
+        let state = ResizeState::new(&node, Handle::Bottom, 0.0, height);
+
+        let resized = state.resize(0.0, height * 2.0, false, false);
+        assert_eq!(resized.get_style().font_size, Some(40.0));
+        assert!((resized.get_rect().height - height * 2.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn rotations_snap_to_15_degree_steps() {
+        let node = rect(0.0, 0.0, 100.0, 100.0, None);
+        let mut state = RotateState::new(&node, 100.0, 50.0);
+        let (sin, cos) = 40.0_f64.to_radians().sin_cos();
+        let (x, y) = (50.0 + 50.0 * cos, 50.0 + 50.0 * sin);
+
+        state.rotate(x, y, false);
+        assert_eq!(state.degrees(), 40.0);
+        let rotated = state.rotate(x, y, true);
+        assert_eq!(state.degrees(), 45.0);
+        assert_eq!(rotated.get_style().rotation, Some((std::f64::consts::FRAC_PI_4, ControlPoint::Center)));
+    }
+
+    #[test]
+    fn rotations_turn_around_the_control_point() {
+        let node = rect(0.0, 0.0, 100.0, 100.0, Some((0.0, ControlPoint::TopLeft)));
+        let mut state = RotateState::new(&node, 100.0, 0.0);
+
+        // Around the centre this would be a half turn.
+        let rotated = state.rotate(0.0, 100.0, false);
+        assert_eq!(state.degrees(), 90.0);
+        assert_eq!(rotated.get_style().rotation.map(|(_, cp)| cp), Some(ControlPoint::TopLeft));
+
+        state.rotate(100.0, -100.0, false);
+        assert_eq!(state.degrees(), 315.0);
+    }
+}
@@ -0,0 +1,458 @@
+use std::collections::VecDeque;
+
+use super::node::{Node, NodeId};
+
+/// Number of undo steps kept unless configured otherwise.
+pub(crate) const DEFAULT_HISTORY_LIMIT: usize = 100;
+
+/// One reversible change to `Context::nodes`.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Command {
+    Modify { before: Box<Node>, after: Box<Node> },
+    /// `node` was inserted and ended up at `index`.
+    Insert { index: usize, node: Node },
+    /// `node` was removed from `index`.
+    Remove { index: usize, node: Node },
+    /// The draw order changed; each list holds every node id in order.
+    Reorder { before: Vec<NodeId>, after: Vec<NodeId> },
+}
+
+impl Command {
+    fn inverse(&self) -> Self {
+        match self {
+            Command::Modify { before, after } => Command::Modify { before: after.clone(), after: before.clone() },
+            Command::Insert { index, node } => Command::Remove { index: *index, node: node.clone() },
+            Command::Remove { index, node } => Command::Insert { index: *index, node: node.clone() },
+            Command::Reorder { before, after } => Command::Reorder { before: after.clone(), after: before.clone() },
+        }
+    }
+}
+
+/// Whether two snapshots of a node differ in anything but the dirty flag.
+fn is_same_content(a: &Node, b: &Node) -> bool {
+    let mut a = a.clone();
+    a.set_is_dirty(b.is_dirty());
+    &a == b
+}
+
+/// Commands that turn `before` into `after`, matching nodes by id.
+fn diff(before: &[Node], after: &[Node]) -> Vec<Command> {
+    let find = |nodes: &[Node], id: NodeId| nodes.iter().position(|node| node.id() == id);
+    let mut commands = Vec::new();
+
+    for (index, node) in before.iter().enumerate() {
+        match find(after, node.id()) {
+            None => commands.push(Command::Remove { index, node: node.clone() }),
+            Some(other) if !is_same_content(node, &after[other]) => commands.push(Command::Modify {
+                before: Box::new(node.clone()),
+                after: Box::new(after[other].clone()),
+            }),
+            Some(_) => {}
+        }
+    }
+    for (index, node) in after.iter().enumerate() {
+        if find(before, node.id()).is_none() {
+            commands.push(Command::Insert { index, node: node.clone() });
+        }
+    }
+
+    // Nodes present on both sides but in a different order.
+    let kept = |from: &[Node], other: &[Node]| -> Vec<NodeId> {
+        from.iter().map(Node::id).filter(|&id| find(other, id).is_some()).collect()
+    };
+    if kept(before, after) != kept(after, before) {
+        commands.push(Command::Reorder {
+            before: before.iter().map(Node::id).collect(),
+            after: after.iter().map(Node::id).collect(),
+        });
+    }
+    commands
+}
+
+/// Applies `commands` to `nodes`: removals first, then modifications, then
+/// insertions from the lowest index up, so every index lands where it was
+/// recorded, and finally any change of draw order.
+fn apply(commands: &[Command], nodes: &mut Vec<Node>) {
+    for command in commands {
+        if let Command::Remove { node, .. } = command {
+            nodes.retain(|existing| existing.id() != node.id());
+        }
+    }
+    for command in commands {
+        if let Command::Modify { after, .. } = command {
+            if let Some(existing) = nodes.iter_mut().find(|existing| existing.id() == after.id()) {
+                *existing = (**after).clone();
+                existing.set_is_dirty(true);
+            }
+        }
+    }
+    let mut inserts: Vec<(usize, &Node)> = commands
+        .iter()
+        .filter_map(|command| match command {
+            Command::Insert { index, node } => Some((*index, node)),
+            _ => None,
+        })
+        .collect();
+    inserts.sort_by_key(|(index, _)| *index);
+    for (index, node) in inserts {
+        let mut node = node.clone();
+        node.set_is_dirty(true);
+        nodes.insert(index.min(nodes.len()), node);
+    }
+    for command in commands {
+        if let Command::Reorder { after, .. } = command {
+            nodes.sort_by_key(|node| after.iter().position(|&id| id == node.id()).unwrap_or(usize::MAX));
+        }
+    }
+}
+
+/// Undo and redo stacks of `Context::nodes`. Changes are recorded by
+/// snapshotting the nodes when the outermost transaction begins and diffing
+/// them when it commits, so every mutation in between becomes one entry.
+#[derive(Debug, Clone)]
+pub(crate) struct History {
+    undo: VecDeque<Vec<Command>>,
+    redo: Vec<Vec<Command>>,
+    limit: usize,
+    snapshot: Option<Vec<Node>>,
+    depth: usize,
+    /// Node whose single-node change is the latest entry, so the next change
+    /// of the same node can extend it.
+    merge_target: Option<NodeId>,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self {
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+            limit: DEFAULT_HISTORY_LIMIT,
+            snapshot: None,
+            depth: 0,
+            merge_target: None,
+        }
+    }
+}
+
+impl History {
+    /// Opens a transaction; transactions nest, and only the outermost one
+    /// takes a snapshot.
+    pub(crate) fn begin(&mut self, nodes: &[Node]) {
+        if self.depth == 0 {
+            self.snapshot = Some(nodes.to_vec());
+        }
+        self.depth += 1;
+    }
+
+    /// Closes a transaction. When it is the outermost one, whatever changed
+    /// since it began is pushed as a single undo entry.
+    pub(crate) fn commit(&mut self, nodes: &[Node]) {
+        if self.depth == 0 {
+            return;
+        }
+        self.depth -= 1;
+        if self.depth > 0 {
+            return;
+        }
+
+        let Some(snapshot) = self.snapshot.take() else {
+            return;
+        };
+        let commands = diff(&snapshot, nodes);
+        if commands.is_empty() {
+            return;
+        }
+        self.push(commands);
+    }
+
+    /// Records a change of one node made outside any transaction as its own
+    /// entry, or as part of the latest entry when that changed the same node
+    /// in the same way, so animating a node does not flood the stack. A
+    /// transaction still open for an interaction gets `change` applied to its
+    /// snapshot, so its entry does not also contain the change.
+    pub(crate) fn record_change(&mut self, before: Node, after: Node, change: impl Fn(&mut Node)) {
+        if is_same_content(&before, &after) {
+            return;
+        }
+        let id = after.id();
+        if let Some(node) = self.snapshot.iter_mut().flatten().find(|node| node.id() == id) {
+            change(node);
+        }
+
+        if self.merge_target == Some(id) {
+            if let Some([Command::Modify { after: latest, .. }]) = self.undo.back_mut().map(Vec::as_mut_slice) {
+                **latest = after;
+                return;
+            }
+        }
+        self.push(vec![Command::Modify { before: Box::new(before), after: Box::new(after) }]);
+        self.merge_target = Some(id);
+    }
+
+    fn push(&mut self, commands: Vec<Command>) {
+        self.undo.push_back(commands);
+        self.redo.clear();
+        self.merge_target = None;
+        self.trim();
+    }
+
+    pub(crate) fn is_recording(&self) -> bool {
+        self.depth > 0
+    }
+
+    pub(crate) fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub(crate) fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// Reverts the latest entry. Returns `false` when there is nothing to
+    /// undo or a transaction is still open.
+    pub(crate) fn undo(&mut self, nodes: &mut Vec<Node>) -> bool {
+        if self.is_recording() {
+            return false;
+        }
+        let Some(commands) = self.undo.pop_back() else {
+            return false;
+        };
+        let inverse: Vec<Command> = commands.iter().map(Command::inverse).collect();
+        apply(&inverse, nodes);
+        self.merge_target = None;
+        self.redo.push(commands);
+        true
+    }
+
+    /// Re-applies the latest undone entry.
+    pub(crate) fn redo(&mut self, nodes: &mut Vec<Node>) -> bool {
+        if self.is_recording() {
+            return false;
+        }
+        let Some(commands) = self.redo.pop() else {
+            return false;
+        };
+        apply(&commands, nodes);
+        self.undo.push_back(commands);
+        self.merge_target = None;
+        true
+    }
+
+    /// Whether undo or redo could bring back the node `id`.
+    pub(crate) fn can_restore(&self, id: NodeId) -> bool {
+        self.undo.iter().chain(&self.redo).flatten().any(|command| match command {
+            Command::Insert { node, .. } | Command::Remove { node, .. } => node.id() == id,
+            _ => false,
+        })
+    }
+
+    pub(crate) fn set_limit(&mut self, limit: usize) {
+        self.limit = limit;
+        self.trim();
+    }
+
+    /// Forgets every entry and closes any open transaction.
+    pub(crate) fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+        self.snapshot = None;
+        self.depth = 0;
+        self.merge_target = None;
+    }
+
+    fn trim(&mut self) {
+        while self.undo.len() > self.limit {
+            self.undo.pop_front();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::internal::{geometry::Rectangle, style::Style};
+
+    fn rect(id: NodeId, left: f64) -> Node {
+        Node::Rect(Rectangle { left, top: 0.0, width: 10.0, height: 10.0 }, Style::default(), false, id)
+    }
+
+    fn ids(nodes: &[Node]) -> Vec<NodeId> {
+        nodes.iter().map(Node::id).collect()
+    }
+
+    /// Records `change` on `nodes` as one transaction, then checks that undo
+    /// restores the nodes and redo repeats the change.
+    fn assert_round_trip(nodes: Vec<Node>, change: impl FnOnce(&mut Vec<Node>)) -> Vec<Command> {
+        let mut history = History::default();
+        let mut current = nodes.clone();
+        history.begin(&current);
+        change(&mut current);
+        history.commit(&current);
+        let changed = current.clone();
+        let commands = history.undo.back().cloned().unwrap_or_default();
+
+        assert!(history.undo(&mut current));
+        assert_eq!(ids(&current), ids(&nodes));
+        assert!(current.iter().zip(&nodes).all(|(a, b)| is_same_content(a, b)));
+        assert!(history.redo(&mut current));
+        assert_eq!(ids(&current), ids(&changed));
+        assert!(current.iter().zip(&changed).all(|(a, b)| is_same_content(a, b)));
+        commands
+    }
+
+    #[test]
+    fn modifications_are_undone_and_redone() {
+        let commands = assert_round_trip(vec![rect(1, 0.0), rect(2, 0.0)], |nodes| nodes[1].translate(5.0, 0.0));
+        assert!(matches!(commands.as_slice(), [Command::Modify { .. }]));
+    }
+
+    #[test]
+    fn insertions_land_at_their_index() {
+        let commands = assert_round_trip(vec![rect(1, 0.0), rect(2, 0.0)], |nodes| {
+            nodes.insert(1, rect(3, 0.0));
+            nodes.push(rect(4, 0.0));
+        });
+        assert!(matches!(commands.as_slice(), [Command::Insert { index: 1, .. }, Command::Insert { index: 3, .. }]));
+    }
+
+    #[test]
+    fn removals_are_restored_in_place() {
+        let commands = assert_round_trip(vec![rect(1, 0.0), rect(2, 0.0), rect(3, 0.0)], |nodes| {
+            nodes.remove(1);
+        });
+        assert!(matches!(commands.as_slice(), [Command::Remove { index: 1, .. }]));
+    }
+
+    #[test]
+    fn draw_order_changes_are_recorded() {
+        let commands = assert_round_trip(vec![rect(1, 0.0), rect(2, 0.0), rect(3, 0.0)], |nodes| {
+            let node = nodes.remove(0);
+            nodes.push(node);
+        });
+        assert!(matches!(commands.as_slice(), [Command::Reorder { .. }]));
+    }
+
+    #[test]
+    fn mixed_changes_round_trip() {
+        assert_round_trip(vec![rect(1, 0.0), rect(2, 0.0), rect(3, 0.0)], |nodes| {
+            nodes.remove(0);
+            nodes[0].translate(1.0, 1.0);
+            nodes.insert(0, rect(4, 0.0));
+            nodes.swap(1, 2);
+        });
+    }
+
+    #[test]
+    fn dirty_flags_alone_are_not_changes() {
+        let mut history = History::default();
+        let mut nodes = vec![rect(1, 0.0)];
+        history.begin(&nodes);
+        nodes[0].set_is_dirty(true);
+        history.commit(&nodes);
+        assert!(!history.can_undo());
+    }
+
+    #[test]
+    fn nested_transactions_make_one_step() {
+        let mut history = History::default();
+        let mut nodes = vec![rect(1, 0.0)];
+        history.begin(&nodes);
+        nodes[0].translate(1.0, 0.0);
+        history.begin(&nodes);
+        nodes.push(rect(2, 0.0));
+        history.commit(&nodes);
+        assert!(!history.can_undo());
+        assert!(!history.undo(&mut nodes), "undo waits for the outer transaction");
+        history.commit(&nodes);
+
+        assert!(history.undo(&mut nodes));
+        assert_eq!(ids(&nodes), vec![1]);
+        assert_eq!(nodes[0].get_bounds().left, 0.0);
+        assert!(!history.can_undo());
+    }
+
+    #[test]
+    fn new_changes_clear_redo() {
+        let mut history = History::default();
+        let mut nodes = vec![rect(1, 0.0)];
+        history.begin(&nodes);
+        nodes.push(rect(2, 0.0));
+        history.commit(&nodes);
+        history.undo(&mut nodes);
+        assert!(history.can_redo());
+
+        history.begin(&nodes);
+        nodes.push(rect(3, 0.0));
+        history.commit(&nodes);
+        assert!(!history.can_redo());
+    }
+
+    #[test]
+    fn changes_of_the_same_node_in_a_row_merge() {
+        let mut history = History::default();
+        let mut nodes = vec![rect(1, 0.0), rect(2, 0.0)];
+        let mut change = |nodes: &mut Vec<Node>, index: usize| {
+            let before = nodes[index].clone();
+            nodes[index].translate(1.0, 0.0);
+            history.record_change(before, nodes[index].clone(), |node| node.translate(1.0, 0.0));
+        };
+        change(&mut nodes, 0);
+        change(&mut nodes, 0);
+        change(&mut nodes, 1);
+        change(&mut nodes, 0);
+
+        assert!(history.undo(&mut nodes));
+        assert_eq!((nodes[0].get_bounds().left, nodes[1].get_bounds().left), (2.0, 1.0));
+        assert!(history.undo(&mut nodes));
+        assert!(history.undo(&mut nodes));
+        assert_eq!(nodes[0].get_bounds().left, 0.0);
+        assert!(!history.can_undo());
+    }
+
+    #[test]
+    fn changes_during_a_transaction_stay_out_of_its_entry() {
+        let mut history = History::default();
+        let mut nodes = vec![rect(1, 0.0), rect(2, 0.0)];
+        history.begin(&nodes);
+        nodes[0].translate(5.0, 0.0);
+        let before = nodes[1].clone();
+        nodes[1].translate(1.0, 0.0);
+        history.record_change(before, nodes[1].clone(), |node| node.translate(1.0, 0.0));
+        history.commit(&nodes);
+
+        assert!(history.undo(&mut nodes));
+        assert_eq!((nodes[0].get_bounds().left, nodes[1].get_bounds().left), (0.0, 1.0));
+        assert!(history.undo(&mut nodes));
+        assert_eq!(nodes[1].get_bounds().left, 0.0);
+    }
+
+    #[test]
+    fn clearing_closes_open_transactions() {
+        let mut history = History::default();
+        let mut nodes = vec![rect(1, 0.0)];
+        history.begin(&nodes);
+        nodes[0].translate(1.0, 0.0);
+        history.clear();
+        assert!(!history.is_recording());
+        history.commit(&nodes);
+        assert!(!history.can_undo());
+    }
+
+    #[test]
+    fn the_oldest_steps_are_dropped_beyond_the_limit() {
+        let mut history = History::default();
+        history.set_limit(2);
+        let mut nodes = vec![rect(1, 0.0)];
+        for step in 1..=3 {
+            history.begin(&nodes);
+            nodes[0].translate(1.0, 0.0);
+            history.commit(&nodes);
+            assert_eq!(nodes[0].get_bounds().left, step as f64);
+        }
+
+        assert!(history.undo(&mut nodes));
+        assert!(history.undo(&mut nodes));
+        assert!(!history.undo(&mut nodes));
+        assert_eq!(nodes[0].get_bounds().left, 1.0);
+    }
+}
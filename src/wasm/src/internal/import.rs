@@ -0,0 +1,223 @@
+use std::cell::RefCell;
+
+use gloo_utils::format::JsValueSerdeExt;
+use wasm_bindgen::{JsCast, JsValue};
+
+use super::{
+    color::Color,
+    geometry::{Circle, Image, Line, Point, Rectangle, Text},
+    node::{Node, NodeId},
+    style::{Style, DEFAULT_FONT_SIZE},
+};
+
+/// Side of a dropped image whose size the host did not report.
+const DEFAULT_IMAGE_SIZE: f64 = 100.0;
+/// Distance between the tops of consecutive lines of dropped text, in font
+/// sizes.
+const TEXT_LINE_SPACING: f64 = 1.2;
+/// SVG places text by its baseline, nodes by their top; this is the usual
+/// ascent of a font in font sizes.
+const SVG_TEXT_ASCENT: f64 = 0.8;
+/// Elements whose content is never drawn by itself.
+const SVG_NON_RENDERED: &str = "defs, clipPath, mask, marker, pattern, symbol";
+
+/// Something dropped on the canvas, as read from the `DataTransfer` by the
+/// host.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub(crate) enum DropItem {
+    /// A bitmap; `src` is usually a data URL of the dropped file. A missing
+    /// side copies the other one.
+    Image { src: String, width: Option<f64>, height: Option<f64>, style: Option<Style> },
+    /// An SVG document, imported as editable shapes.
+    Svg { markup: String },
+    /// Plain text, one text node per non-empty line, black unless `style`
+    /// says otherwise.
+    Text { text: String, style: Option<Style> },
+}
+
+impl DropItem {
+    /// Items from the value given to `on_drop`: a single item or an array.
+    pub(crate) fn from_json(value: &JsValue) -> Result<Vec<Self>, String> {
+        let value: serde_json::Value = value.into_serde().map_err(|err| format!("invalid drop items: {}", err))?;
+        let items = match value {
+            serde_json::Value::Array(_) => serde_json::from_value(value),
+            _ => serde_json::from_value(value).map(|item| vec![item]),
+        };
+        items.map_err(|err| format!("invalid drop items: {}", err))
+    }
+
+    /// Nodes for the item in their own coordinates, with ids from `next_id`.
+    /// Text nodes are left unmeasured. SVG markup needs a DOM to be parsed.
+    pub(crate) fn to_nodes(&self, next_id: &mut impl FnMut() -> NodeId) -> Result<Vec<Node>, String> {
+        match self {
+            DropItem::Image { src, width, height, style } => {
+                let width = width.or(*height).unwrap_or(DEFAULT_IMAGE_SIZE);
+                let height = height.unwrap_or(width);
+                let rect = Rectangle { left: 0.0, top: 0.0, width, height };
+                let image = Image { src: src.clone(), rect };
+                Ok(vec![Node::Image(image, style.clone().unwrap_or_default(), true, next_id())])
+            }
+            DropItem::Svg { markup } => svg_nodes(markup, next_id),
+            DropItem::Text { text, style } => {
+                let mut base = Style::default();
+                base.fill_color(Color::rgb(0, 0, 0));
+                let style = base.patch(style.clone().unwrap_or_default());
+                let line_height = style.font_size.unwrap_or(DEFAULT_FONT_SIZE) * TEXT_LINE_SPACING;
+                let nodes = text
+                    .lines()
+                    .map(str::trim_end)
+                    .filter(|line| !line.is_empty())
+                    .enumerate()
+                    .map(|(index, line)| {
+                        let position = Point { x: 0.0, y: index as f64 * line_height };
+                        let text = Text { text: line.to_string(), position, shape: RefCell::new(None) };
+                        Node::Text(text, style.clone(), true, next_id())
+                    })
+                    .collect();
+                Ok(nodes)
+            }
+        }
+    }
+}
+
+/// Shapes of an SVG document: its rects, circles, lines and text with their
+/// fill and stroke. Transforms, groups' styles and other elements are not
+/// supported and are skipped or ignored.
+fn svg_nodes(markup: &str, next_id: &mut impl FnMut() -> NodeId) -> Result<Vec<Node>, String> {
+    let parser = web_sys::DomParser::new().map_err(|_| "importing SVG requires a DOM".to_string())?;
+    let document = parser
+        .parse_from_string(markup, web_sys::SupportedType::ImageSvgXml)
+        .map_err(|_| "invalid SVG".to_string())?;
+    if document.query_selector("parsererror").ok().flatten().is_some() {
+        return Err("invalid SVG".to_string());
+    }
+
+    let elements = document
+        .query_selector_all("rect, circle, line, text")
+        .map_err(|_| "invalid SVG".to_string())?;
+    let mut nodes = Vec::new();
+    for index in 0..elements.length() {
+        let Some(element) = elements.item(index).and_then(|node| node.dyn_into::<web_sys::Element>().ok()) else {
+            continue;
+        };
+        if element.closest(SVG_NON_RENDERED).ok().flatten().is_some() {
+            continue;
+        }
+        if let Some(node) = svg_node(&element, next_id) {
+            nodes.push(node);
+        }
+    }
+    Ok(nodes)
+}
+
+fn svg_node(element: &web_sys::Element, next_id: &mut impl FnMut() -> NodeId) -> Option<Node> {
+    let number = |name: &str| element.get_attribute(name).and_then(|value| parse_length(&value)).unwrap_or(0.0);
+    let tag = element.tag_name();
+    let style = svg_style(element, tag != "line");
+
+    let node = match tag.as_str() {
+        "rect" => {
+            let rect = Rectangle { left: number("x"), top: number("y"), width: number("width"), height: number("height") };
+            Node::Rect(rect, style, true, next_id())
+        }
+        "circle" => {
+            let circle = Circle { center: Point { x: number("cx"), y: number("cy") }, radius: number("r") };
+            Node::Circle(circle, style, true, next_id())
+        }
+        "line" => {
+            let line = Line {
+                start: Point { x: number("x1"), y: number("y1") },
+                end: Point { x: number("x2"), y: number("y2") },
+            };
+            Node::Line(line, style, true, next_id())
+        }
+        "text" => {
+            let text = element.text_content()?.trim().to_string();
+            if text.is_empty() {
+                return None;
+            }
+            let font_size = style.font_size.unwrap_or(DEFAULT_FONT_SIZE);
+            let position = Point { x: number("x"), y: number("y") - font_size * SVG_TEXT_ASCENT };
+            Node::Text(Text { text, position, shape: RefCell::new(None) }, style, true, next_id())
+        }
+        _ => return None,
+    };
+    Some(node)
+}
+
+/// Fill, stroke and font size of an SVG element, from its presentation
+/// attributes or its inline `style`, which wins. Fills default to black
+/// like in SVG, except for lines which cannot be filled.
+fn svg_style(element: &web_sys::Element, is_fillable: bool) -> Style {
+    let inline = element.get_attribute("style").unwrap_or_default();
+    let property = |name: &str| {
+        inline
+            .split(';')
+            .filter_map(|declaration| declaration.split_once(':'))
+            .find(|(key, _)| key.trim() == name)
+            .map(|(_, value)| value.trim().to_string())
+            .or_else(|| element.get_attribute(name))
+    };
+    let paint = |name: &str, default: Option<Color>| match property(name).as_deref().map(str::trim) {
+        None => default,
+        Some("none" | "transparent") => None,
+        Some(value) => value.parse().ok(),
+    };
+
+    let stroke_color = paint("stroke", None);
+    let stroke_width = stroke_color.map(|_| property("stroke-width").and_then(|value| parse_length(&value)).unwrap_or(1.0));
+    Style {
+        fill_color: if is_fillable { paint("fill", Some(Color::rgb(0, 0, 0))) } else { None },
+        stroke_color,
+        stroke_width,
+        font_size: property("font-size").and_then(|value| parse_length(&value)),
+        ..Default::default()
+    }
+}
+
+/// A length in user units; `px` is accepted, other units and percentages
+/// are not.
+fn parse_length(value: &str) -> Option<f64> {
+    let value = value.trim();
+    value.strip_suffix("px").unwrap_or(value).trim().parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text_positions(item: &DropItem) -> Vec<Point> {
+        let mut id = 0;
+        let nodes = item.to_nodes(&mut || { id += 1; id }).unwrap();
+        nodes
+            .iter()
+            .map(|node| match node {
+                Node::Text(text, ..) => text.position,
+                _ => panic!("expected a text node"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn dropped_text_lines_are_spaced_by_their_font_size() {
+        let item = DropItem::Text { text: "one\n\ntwo\nthree".to_string(), style: None };
+        let ys: Vec<f64> = text_positions(&item).iter().map(|point| point.y).collect();
+        assert_eq!(ys, vec![0.0, DEFAULT_FONT_SIZE * TEXT_LINE_SPACING, 2.0 * DEFAULT_FONT_SIZE * TEXT_LINE_SPACING]);
+
+        let style = Style { font_size: Some(40.0), ..Default::default() };
+        let item = DropItem::Text { text: "one\ntwo".to_string(), style: Some(style) };
+        let ys: Vec<f64> = text_positions(&item).iter().map(|point| point.y).collect();
+        assert_eq!(ys, vec![0.0, 40.0 * TEXT_LINE_SPACING]);
+    }
+
+    #[test]
+    fn dropped_images_copy_a_missing_side() {
+        let item = DropItem::Image { src: "a.png".to_string(), width: None, height: Some(30.0), style: None };
+        let nodes = item.to_nodes(&mut || 1).unwrap();
+        match &nodes[..] {
+            [Node::Image(image, ..)] => assert_eq!((image.rect.width, image.rect.height), (30.0, 30.0)),
+            _ => panic!("expected one image node"),
+        }
+    }
+}
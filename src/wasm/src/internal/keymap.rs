@@ -0,0 +1,224 @@
+use std::collections::HashMap;
+
+use super::{event::Modifiers, zorder::Restack};
+
+/// Distance the arrow keys move the selection by.
+pub(crate) const NUDGE_STEP: f64 = 1.0;
+/// Same, with shift held.
+pub(crate) const NUDGE_STEP_LARGE: f64 = 10.0;
+
+/// A key together with the modifiers that must be held, written like
+/// `"Shift+ArrowUp"` or `"Mod+D"`. `Ctrl`, `Cmd`, `Meta` and `Mod` all stand
+/// for the platform's command key, so one binding works on every OS.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub(crate) struct KeyChord {
+    key: String,
+    shift: bool,
+    command: bool,
+    alt: bool,
+}
+
+fn normalize_key(key: &str) -> String {
+    match key {
+        " " => "space".to_string(),
+        _ => key.to_ascii_lowercase(),
+    }
+}
+
+impl KeyChord {
+    pub(crate) fn parse(chord: &str) -> Result<Self, String> {
+        let mut parts: Vec<&str> = chord.split('+').map(str::trim).collect();
+        // "Ctrl++" binds the plus key.
+        if chord.ends_with("++") {
+            parts.truncate(parts.len() - 2);
+            parts.push("+");
+        }
+        let Some((key, modifiers)) = parts.split_last() else {
+            return Err(format!("invalid key chord `{}`", chord));
+        };
+        if key.is_empty() {
+            return Err(format!("invalid key chord `{}`", chord));
+        }
+
+        let mut result = Self {
+            key: normalize_key(key),
+            shift: false,
+            command: false,
+            alt: false,
+        };
+        for modifier in modifiers {
+            match modifier.to_ascii_lowercase().as_str() {
+                "shift" => result.shift = true,
+                "ctrl" | "control" | "cmd" | "command" | "meta" | "mod" => result.command = true,
+                "alt" | "option" => result.alt = true,
+                _ => return Err(format!("unknown modifier `{}` in key chord `{}`", modifier, chord)),
+            }
+        }
+        Ok(result)
+    }
+
+    pub(crate) fn from_event(key: &str, modifiers: Modifiers) -> Self {
+        Self {
+            key: normalize_key(key),
+            shift: modifiers.shift,
+            command: modifiers.command(),
+            alt: modifiers.alt,
+        }
+    }
+}
+
+/// Binds key chords to action names. Built-in actions are handled by the
+/// canvas itself; any other name refers to an action registered from JS.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(from = "Vec<(KeyChord, String)>", into = "Vec<(KeyChord, String)>")]
+pub(crate) struct Keymap {
+    bindings: HashMap<KeyChord, String>,
+}
+
+// JSON objects only take string keys, so the bindings travel as a list.
+impl From<Vec<(KeyChord, String)>> for Keymap {
+    fn from(bindings: Vec<(KeyChord, String)>) -> Self {
+        Self { bindings: bindings.into_iter().collect() }
+    }
+}
+
+impl From<Keymap> for Vec<(KeyChord, String)> {
+    fn from(keymap: Keymap) -> Self {
+        keymap.bindings.into_iter().collect()
+    }
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let mut keymap = Self { bindings: HashMap::new() };
+        for (chord, action) in [
+            ("ArrowLeft", "nudge_left"),
+            ("ArrowRight", "nudge_right"),
+            ("ArrowUp", "nudge_up"),
+            ("ArrowDown", "nudge_down"),
+            ("Shift+ArrowLeft", "nudge_left_large"),
+            ("Shift+ArrowRight", "nudge_right_large"),
+            ("Shift+ArrowUp", "nudge_up_large"),
+            ("Shift+ArrowDown", "nudge_down_large"),
+            ("Delete", "delete"),
+            ("Backspace", "delete"),
+            ("Escape", "deselect"),
+            ("Mod+Z", "undo"),
+            ("Mod+Shift+Z", "redo"),
+            ("Mod+Y", "redo"),
+            ("Mod+C", "copy"),
+            ("Mod+X", "cut"),
+            ("Mod+V", "paste"),
+            ("Mod+D", "duplicate"),
+            ("Mod+]", "bring_forward"),
+            ("Mod+[", "send_backward"),
+            // Shift turns the brackets into braces on most layouts.
+            ("Mod+Shift+]", "bring_to_front"),
+            ("Mod+Shift+}", "bring_to_front"),
+            ("Mod+Shift+[", "send_to_back"),
+            ("Mod+Shift+{", "send_to_back"),
+        ] {
+            keymap.bind(chord, action).expect("default key chords are valid");
+        }
+        keymap
+    }
+}
+
+impl Keymap {
+    pub(crate) fn bind(&mut self, chord: &str, action: &str) -> Result<(), String> {
+        self.bindings.insert(KeyChord::parse(chord)?, action.to_string());
+        Ok(())
+    }
+
+    pub(crate) fn unbind(&mut self, chord: &str) -> Result<(), String> {
+        self.bindings.remove(&KeyChord::parse(chord)?);
+        Ok(())
+    }
+
+    pub(crate) fn action(&self, key: &str, modifiers: Modifiers) -> Option<&str> {
+        self.bindings
+            .get(&KeyChord::from_event(key, modifiers))
+            .map(String::as_str)
+    }
+}
+
+/// Actions the canvas performs itself, by name.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum BuiltinAction {
+    Nudge(f64, f64),
+    Delete,
+    Deselect,
+    Undo,
+    Redo,
+    Copy,
+    Cut,
+    Paste,
+    Duplicate,
+    Restack(Restack),
+}
+
+impl BuiltinAction {
+    pub(crate) fn from_name(name: &str) -> Option<Self> {
+        let nudge = |dx: f64, dy: f64| Some(Self::Nudge(dx, dy));
+        match name {
+            "nudge_left" => nudge(-NUDGE_STEP, 0.0),
+            "nudge_right" => nudge(NUDGE_STEP, 0.0),
+            "nudge_up" => nudge(0.0, -NUDGE_STEP),
+            "nudge_down" => nudge(0.0, NUDGE_STEP),
+            "nudge_left_large" => nudge(-NUDGE_STEP_LARGE, 0.0),
+            "nudge_right_large" => nudge(NUDGE_STEP_LARGE, 0.0),
+            "nudge_up_large" => nudge(0.0, -NUDGE_STEP_LARGE),
+            "nudge_down_large" => nudge(0.0, NUDGE_STEP_LARGE),
+            "delete" => Some(Self::Delete),
+            "deselect" => Some(Self::Deselect),
+            "undo" => Some(Self::Undo),
+            "redo" => Some(Self::Redo),
+            "copy" => Some(Self::Copy),
+            "cut" => Some(Self::Cut),
+            "paste" => Some(Self::Paste),
+            "duplicate" => Some(Self::Duplicate),
+            "bring_forward" => Some(Self::Restack(Restack::Forward)),
+            "send_backward" => Some(Self::Restack(Restack::Backward)),
+            "bring_to_front" => Some(Self::Restack(Restack::Front)),
+            "send_to_back" => Some(Self::Restack(Restack::Back)),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn modifiers(shift: bool, ctrl: bool, meta: bool) -> Modifiers {
+        Modifiers { shift, ctrl, alt: false, meta }
+    }
+
+    #[test]
+    fn chords_parse_modifiers_and_keys() {
+        let chord = KeyChord::parse("Ctrl+Shift+Z").unwrap();
+        assert_eq!(chord, KeyChord::from_event("z", modifiers(true, true, false)));
+        assert_eq!(KeyChord::parse("Mod++").unwrap(), KeyChord::from_event("+", modifiers(false, false, true)));
+        assert_eq!(KeyChord::parse("Space").unwrap(), KeyChord::from_event(" ", Modifiers::default()));
+        assert!(KeyChord::parse("Hyper+A").is_err());
+        assert!(KeyChord::parse("Ctrl+").is_err());
+    }
+
+    #[test]
+    fn ctrl_and_cmd_both_trigger_mod_bindings() {
+        let mut keymap = Keymap::default();
+        keymap.bind("Mod+Shift+K", "my_action").unwrap();
+        assert_eq!(keymap.action("k", modifiers(true, true, false)), Some("my_action"));
+        assert_eq!(keymap.action("K", modifiers(true, false, true)), Some("my_action"));
+        assert_eq!(keymap.action("k", Modifiers::default()), None);
+    }
+
+    #[test]
+    fn bindings_can_be_replaced_and_removed() {
+        let mut keymap = Keymap::default();
+        keymap.bind("Mod+D", "my_action").unwrap();
+        assert_eq!(keymap.action("d", modifiers(false, true, false)), Some("my_action"));
+        keymap.unbind("Mod+D").unwrap();
+        assert_eq!(keymap.action("d", modifiers(false, true, false)), None);
+    }
+}
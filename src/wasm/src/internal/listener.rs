@@ -0,0 +1,139 @@
+use super::node::NodeId;
+
+/// Identifies a subscription, for removing it again with `off`.
+pub type ListenerId = u32;
+
+/// Interactions JS can subscribe to with `on`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum EventKind {
+    Select,
+    HoverEnter,
+    HoverLeave,
+    DragStart,
+    DragMove,
+    DragEnd,
+    Click,
+    DblClick,
+    DragEnter,
+    DragOver,
+    DragLeave,
+    Drop,
+    ContextMenu,
+}
+
+impl EventKind {
+    pub(crate) fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "select" => Some(Self::Select),
+            "hover_enter" => Some(Self::HoverEnter),
+            "hover_leave" => Some(Self::HoverLeave),
+            "drag_start" => Some(Self::DragStart),
+            "drag_move" => Some(Self::DragMove),
+            "drag_end" => Some(Self::DragEnd),
+            "click" => Some(Self::Click),
+            "dblclick" => Some(Self::DblClick),
+            "drag_enter" => Some(Self::DragEnter),
+            "drag_over" => Some(Self::DragOver),
+            "drag_leave" => Some(Self::DragLeave),
+            "drop" => Some(Self::Drop),
+            "context_menu" => Some(Self::ContextMenu),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Self::Select => "select",
+            Self::HoverEnter => "hover_enter",
+            Self::HoverLeave => "hover_leave",
+            Self::DragStart => "drag_start",
+            Self::DragMove => "drag_move",
+            Self::DragEnd => "drag_end",
+            Self::Click => "click",
+            Self::DblClick => "dblclick",
+            Self::DragEnter => "drag_enter",
+            Self::DragOver => "drag_over",
+            Self::DragLeave => "drag_leave",
+            Self::Drop => "drop",
+            Self::ContextMenu => "context_menu",
+        }
+    }
+}
+
+/// An interaction waiting to be delivered to JS once the input queue has
+/// been processed. Coordinates are in scene space.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Emitted {
+    pub(crate) kind: EventKind,
+    /// Node the interaction is about, if any.
+    pub(crate) node: Option<NodeId>,
+    /// Nodes whose own listeners hear about it, e.g. every dragged node.
+    pub(crate) targets: Vec<NodeId>,
+    pub(crate) x: f64,
+    pub(crate) y: f64,
+    /// Total drag offset for drag events, zero otherwise.
+    pub(crate) dx: f64,
+    pub(crate) dy: f64,
+}
+
+impl Emitted {
+    pub(crate) fn new(kind: EventKind, node: Option<NodeId>, x: f64, y: f64) -> Self {
+        Self {
+            kind,
+            node,
+            targets: node.into_iter().collect(),
+            x,
+            y,
+            dx: 0.0,
+            dy: 0.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Listener {
+    id: ListenerId,
+    kind: EventKind,
+    /// `None` for listeners on the whole canvas.
+    node: Option<NodeId>,
+    callback: js_sys::Function,
+}
+
+/// JS callbacks subscribed to canvas or node events.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Listeners {
+    listeners: Vec<Listener>,
+    next_id: ListenerId,
+}
+
+impl Listeners {
+    pub(crate) fn add(&mut self, kind: EventKind, node: Option<NodeId>, callback: js_sys::Function) -> ListenerId {
+        self.next_id += 1;
+        self.listeners.push(Listener { id: self.next_id, kind, node, callback });
+        self.next_id
+    }
+
+    pub(crate) fn remove(&mut self, id: ListenerId) {
+        self.listeners.retain(|listener| listener.id != id);
+    }
+
+    /// Callbacks to run for `emitted`: the canvas listeners first, then
+    /// those of each target node.
+    pub(crate) fn callbacks(&self, emitted: &Emitted) -> Vec<js_sys::Function> {
+        let matching = |node: Option<NodeId>| {
+            self.listeners
+                .iter()
+                .filter(move |listener| listener.kind == emitted.kind && listener.node == node)
+                .map(|listener| listener.callback.clone())
+        };
+        let mut callbacks: Vec<js_sys::Function> = matching(None).collect();
+        for &target in &emitted.targets {
+            callbacks.extend(matching(Some(target)));
+        }
+        callbacks
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.listeners.is_empty()
+    }
+}
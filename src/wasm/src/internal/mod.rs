@@ -3,4 +3,26 @@ pub mod geometry;
 pub mod style;
 pub mod context;
 pub mod event;
-pub mod log_utils;
\ No newline at end of file
+pub mod log_utils;
+pub mod text_edit;
+pub mod clip;
+pub mod path;
+pub mod color;
+pub mod theme;
+pub mod handle;
+pub mod selection;
+pub mod viewport;
+pub mod gesture;
+pub mod keymap;
+pub mod history;
+pub mod clipboard;
+pub mod snap;
+pub mod listener;
+pub mod cursor;
+pub mod drag;
+pub mod zorder;
+pub mod click;
+pub mod event_queue;
+pub mod recording;
+pub mod import;
+pub mod callback;
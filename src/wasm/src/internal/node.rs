@@ -1,31 +1,108 @@
-use super::{geometry::{Line, Rectangle, Circle, Text}, style::Style, context::Context, event::Event};
+use super::{geometry::{Line, Rectangle, Circle, Text, Image}, style::{PointerEvents, Style}, callback, context::Context, event::Event, clip::Clip, drag::DragOptions, zorder::Restack};
 use wasm_bindgen::prelude::*;
 
+/// Identifier of a node that stays the same while other nodes are added,
+/// removed or reordered. Unlike the index into `Context::nodes`.
+pub type NodeId = u32;
+
 #[derive(Debug, Clone, PartialEq, PartialOrd)]
 pub enum Node {
-    Line(Line, Style, bool),
-    Rect(Rectangle, Style, bool),
-    Circle(Circle, Style, bool),
-    Text(Text, Style, bool),
+    Line(Line, Style, bool, NodeId),
+    Rect(Rectangle, Style, bool, NodeId),
+    Circle(Circle, Style, bool, NodeId),
+    Text(Text, Style, bool, NodeId),
+    Image(Image, Style, bool, NodeId),
 }
 
 impl Node {
+    pub(crate) fn id(&self) -> NodeId {
+        match self {
+            Node::Line(.., id) => *id,
+            Node::Rect(.., id) => *id,
+            Node::Circle(.., id) => *id,
+            Node::Text(.., id) => *id,
+            Node::Image(.., id) => *id,
+        }
+    }
+
+    pub(crate) fn get_style(&self) -> &Style {
+        match self {
+            Node::Line(_, style, ..) => style,
+            Node::Rect(_, style, ..) => style,
+            Node::Circle(_, style, ..) => style,
+            Node::Text(_, style, ..) => style,
+            Node::Image(_, style, ..) => style,
+        }
+    }
+
+    /// Untransformed bounds of the node; `translate` and `rotation` from the
+    /// style are applied on top of this.
+    pub(crate) fn get_rect(&self) -> Rectangle {
+        match self {
+            Node::Line(line, ..) => line.get_rect(),
+            Node::Rect(rect, ..) => rect.get_rect(),
+            Node::Circle(circle, ..) => circle.get_rect(),
+            Node::Text(text, ..) => text.get_rect(),
+            Node::Image(image, ..) => image.get_rect(),
+        }
+    }
+
+    /// Axis-aligned bounds of the node on the canvas, after `translate` and
+    /// `rotation` are applied.
+    pub(crate) fn get_bounds(&self) -> Rectangle {
+        let rect = self.get_rect();
+        let style = self.get_style();
+        let corners = [
+            (rect.left, rect.top),
+            (rect.left + rect.width, rect.top),
+            (rect.left + rect.width, rect.top + rect.height),
+            (rect.left, rect.top + rect.height),
+        ]
+        .map(|(x, y)| style.to_world(x, y, rect));
+
+        let (mut min_x, mut min_y) = (f64::INFINITY, f64::INFINITY);
+        let (mut max_x, mut max_y) = (f64::NEG_INFINITY, f64::NEG_INFINITY);
+        for (x, y) in corners {
+            min_x = min_x.min(x);
+            min_y = min_y.min(y);
+            max_x = max_x.max(x);
+            max_y = max_y.max(y);
+        }
+        Rectangle { left: min_x, top: min_y, width: max_x - min_x, height: max_y - min_y }
+    }
+
+    /// Adds the outline of the node, in its untransformed space, to the
+    /// current path.
+    pub(crate) fn trace_path(&self, ctx: &web_sys::CanvasRenderingContext2d) {
+        match self {
+            Node::Circle(circle, ..) => {
+                let _ = ctx.arc(circle.center.x, circle.center.y, circle.radius, 0.0, 2.0 * std::f64::consts::PI);
+            }
+            _ => {
+                let rect = self.get_rect();
+                ctx.rect(rect.left, rect.top, rect.width, rect.height);
+            }
+        }
+    }
+
     pub(crate) fn get_style_mut(&mut self) -> &mut Style {
         match self {
             Node::Line(_, style, ..) => style,
             Node::Rect(_, style, ..) => style,
             Node::Circle(_, style, ..) => style,
             Node::Text(_, style, ..) => style,
+            Node::Image(_, style, ..) => style,
         }
     }
 
-    pub(crate) fn set_style(&mut self, style: JsValue) {
+    pub(crate) fn set_style(&mut self, style: Style) {
         let temp = self.clone();
         *self = match temp {
-            Node::Line(v, os, ..) => Node::Line(v, os.patch(style), true),
-            Node::Rect(v, os, ..) => Node::Rect(v, os.patch(style), true),
-            Node::Circle(v, os, ..) => Node::Circle(v, os.patch(style), true),
-            Node::Text(v, os, ..) =>  Node::Text(v, os.patch(style), true)
+            Node::Line(v, os, _, id) => Node::Line(v, os.patch(style), true, id),
+            Node::Rect(v, os, _, id) => Node::Rect(v, os.patch(style), true, id),
+            Node::Circle(v, os, _, id) => Node::Circle(v, os.patch(style), true, id),
+            Node::Text(v, os, _, id) =>  Node::Text(v, os.patch(style), true, id),
+            Node::Image(v, os, _, id) => Node::Image(v, os.patch(style), true, id),
         }
     }
 
@@ -34,13 +111,18 @@ impl Node {
             return false;
         }
 
-        let (x, y) = (event.mouse_x, event.mouse_y);
+        self.contains(event.mouse_x, event.mouse_y)
+    }
 
+    /// Whether the canvas point `(x, y)` falls on the node's shape, ignoring
+    /// any clip.
+    pub(crate) fn contains(&self, x: f64, y: f64) -> bool {
         match self {
             Node::Line(line, style, ..) => line.is_hovered(x, y, style),
             Node::Rect(rect, style, ..) => rect.is_hovered(x, y, style),
             Node::Circle(circle, style, ..) => circle.is_hovered(x, y, style),
             Node::Text(text, style, ..) => text.is_hovered(x, y, style),
+            Node::Image(image, style, ..) => image.is_hovered(x, y, style),
         }
     }
 
@@ -50,6 +132,7 @@ impl Node {
             Node::Rect(rect, ..) => rect.translate(x, y),
             Node::Circle(circle, ..) => circle.translate(x, y),
             Node::Text(text, ..) => text.translate(x, y),
+            Node::Image(image, ..) => image.translate(x, y),
         }
 
         self.set_is_dirty(true);
@@ -57,19 +140,21 @@ impl Node {
 
     pub(crate) fn set_is_dirty(&mut self, is_dirty: bool) {
         match self {
-            Node::Line(_, _, is_dirty_ref) => *is_dirty_ref = is_dirty,
-            Node::Rect(_, _, is_dirty_ref) => *is_dirty_ref = is_dirty,
-            Node::Circle(_, _, is_dirty_ref) => *is_dirty_ref = is_dirty,
-            Node::Text(_, _, is_dirty_ref) => *is_dirty_ref = is_dirty,
+            Node::Line(_, _, is_dirty_ref, _) => *is_dirty_ref = is_dirty,
+            Node::Rect(_, _, is_dirty_ref, _) => *is_dirty_ref = is_dirty,
+            Node::Circle(_, _, is_dirty_ref, _) => *is_dirty_ref = is_dirty,
+            Node::Text(_, _, is_dirty_ref, _) => *is_dirty_ref = is_dirty,
+            Node::Image(_, _, is_dirty_ref, _) => *is_dirty_ref = is_dirty,
         }
     }
 
     pub(crate) fn is_dirty(&self) -> bool {
         match self {
-            Node::Line(_, _, is_dirty_ref) => *is_dirty_ref,
-            Node::Rect(_, _, is_dirty_ref) => *is_dirty_ref,
-            Node::Circle(_, _, is_dirty_ref) => *is_dirty_ref,
-            Node::Text(_, _, is_dirty_ref) => *is_dirty_ref,
+            Node::Line(_, _, is_dirty_ref, _) => *is_dirty_ref,
+            Node::Rect(_, _, is_dirty_ref, _) => *is_dirty_ref,
+            Node::Circle(_, _, is_dirty_ref, _) => *is_dirty_ref,
+            Node::Text(_, _, is_dirty_ref, _) => *is_dirty_ref,
+            Node::Image(_, _, is_dirty_ref, _) => *is_dirty_ref,
         }
     }
 }
@@ -77,83 +162,234 @@ impl Node {
 #[wasm_bindgen]
 pub struct NodeRef {
     ptr: *mut Context,
-    id: isize,
+    id: NodeId,
 }
 
 impl NodeRef {
 
-    pub fn new(context: &Context, id: usize) -> Self {
+    pub fn new(context: &Context, id: NodeId) -> Self {
         Self {
             ptr: context as *const Context as *mut Context,
-            id: id as isize
+            id
         }
     }
 
+    /// Throws while a drag or wheel handler runs, since the canvas is still
+    /// busy with the event that called it.
     fn get_context(&self) -> &Context {
+        if callback::is_in_hook() {
+            wasm_bindgen::throw_str("nodes cannot be used from a drag or wheel handler");
+        }
         unsafe { &*self.ptr }
     }
     
     fn get_context_mut(&mut self) -> &mut Context {
+        if callback::is_in_hook() {
+            wasm_bindgen::throw_str("nodes cannot be used from a drag or wheel handler");
+        }
         unsafe { &mut *self.ptr }
     }
+
+    /// Changes the node through `f` as an undo step, see `Context::update_node`.
+    fn update(&mut self, f: impl Fn(&mut Node)) {
+        let id = self.id;
+        self.get_context_mut().update_node(id, f);
+    }
+
+    fn restack(&mut self, how: Restack) {
+        let id = self.id;
+        self.get_context_mut().restack(&[id], how);
+    }
+
+    fn set_clip(&mut self, clip: Option<Clip>) {
+        self.update(|node| node.get_style_mut().clip = clip.clone());
+    }
 }
 
 #[wasm_bindgen]
 impl NodeRef {
-    pub fn translate(&mut self, x: f64, y: f64) {
-        if self.id < 0 {
-            return;
-        }
+    pub fn id(&self) -> NodeId {
+        self.id
+    }
 
-        let id = self.id as usize;
-        if let Some(node) = self.get_context_mut().nodes.get_mut(id) {
+    pub fn translate(&mut self, x: f64, y: f64) {
+        self.update(|node| {
             node.get_style_mut().translate(x, y);
-            node.set_is_dirty(true);
-        }
+        });
     }
 
-    pub fn set_style(&mut self, style: JsValue) {
-        if self.id < 0 {
-            return;
-        }
-
-        let id = self.id as usize;
-        if let Some(node) = self.get_context_mut().nodes.get_mut(id) {
-            node.set_style(style);
-        }
+    pub fn set_style(&mut self, style: JsValue) -> Result<(), JsValue> {
+        let style = Style::from_json(&style)?;
+        self.update(|node| node.set_style(style.clone()));
+        Ok(())
     }
 
     pub fn rotation(&mut self, angle: f64, control_point: Option<String>, x: Option<f64>, y: Option<f64>) {
-        if self.id < 0 {
-            return;
-        }
+        self.update(|node| {
+            node.get_style_mut().rotation(angle, control_point.clone(), x, y);
+        });
+    }
 
-        let id = self.id as usize;
-        if let Some(node) = self.get_context_mut().nodes.get_mut(id) {
-            node.get_style_mut().rotation(angle, control_point, x, y);
-            node.set_is_dirty(true);
-        }
+    /// Draws the node above every other node.
+    pub fn bring_to_front(&mut self) {
+        self.restack(Restack::Front);
     }
 
-    pub fn remove(mut self) {
-        if self.id < 0 {
-            return;
-        }
+    /// Draws the node below every other node.
+    pub fn send_to_back(&mut self) {
+        self.restack(Restack::Back);
+    }
 
-        let id = self.id as usize;
-        self.get_context_mut().nodes.remove(id);
+    /// Moves the node one step up the draw order.
+    pub fn bring_forward(&mut self) {
+        self.restack(Restack::Forward);
     }
 
-    pub fn is_hovered(&self, x: f64, y: f64) -> bool {
-        if self.id < 0 {
-            return false;
-        }
+    /// Moves the node one step down the draw order.
+    pub fn send_backward(&mut self) {
+        self.restack(Restack::Backward);
+    }
+
+    /// Moves the node to `index` in the draw order, where 0 is drawn first
+    /// (at the back). Indices past the end bring it to the front.
+    pub fn set_z_index(&mut self, index: usize) {
+        self.restack(Restack::Index(index));
+    }
+
+    /// Position of the node in the draw order, 0 being the back.
+    pub fn get_z_index(&self) -> Option<usize> {
+        self.get_context().get_node_index(self.id)
+    }
+
+    /// Shows or hides the node. Hidden nodes are not drawn and cannot be hit
+    /// or selected.
+    pub fn set_visible(&mut self, visible: bool) {
+        self.update(|node| node.get_style_mut().visible = Some(visible));
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.get_context().get_node(self.id).is_some_and(|node| node.get_style().is_visible())
+    }
+
+    /// Locks the node in place: it stays visible and keeps the pointer from
+    /// reaching nodes below, but cannot be selected, dragged or edited.
+    pub fn set_locked(&mut self, locked: bool) {
+        self.update(|node| node.get_style_mut().locked = Some(locked));
+    }
+
+    pub fn is_locked(&self) -> bool {
+        self.get_context().get_node(self.id).is_some_and(|node| node.get_style().is_locked())
+    }
+
+    /// `"none"` lets the pointer through to whatever lies below the node;
+    /// `"auto"` makes it hittable again.
+    pub fn set_pointer_events(&mut self, value: String) -> Result<(), JsValue> {
+        let value = PointerEvents::from_str(&value)
+            .ok_or_else(|| JsValue::from_str(&format!("unknown pointer events value `{}`", value)))?;
+        self.update(|node| node.get_style_mut().pointer_events = Some(value));
+        Ok(())
+    }
+
+    pub fn get_pointer_events(&self) -> String {
+        self.get_context()
+            .get_node(self.id)
+            .and_then(|node| node.get_style().pointer_events)
+            .unwrap_or_default()
+            .name()
+            .to_string()
+    }
 
-        let id = self.id as usize;
-        if let Some(node) = self.get_context().nodes.get(id) {
-            return node.is_hovered(&Event::from_mouse_move(x, y, 0.0));
+    /// Clips the node to a rectangle in its own, untransformed, coordinates.
+    pub fn clip_rect(&mut self, left: f64, top: f64, width: f64, height: f64) {
+        self.set_clip(Some(Clip::Rect { left, top, width, height }));
+    }
+
+    /// Clips the node to a circle in its own, untransformed, coordinates.
+    pub fn clip_circle(&mut self, center_x: f64, center_y: f64, radius: f64) {
+        self.set_clip(Some(Clip::Circle { center_x, center_y, radius }));
+    }
+
+    /// Clips the node to an SVG path (`d` attribute syntax) in its own,
+    /// untransformed, coordinates. Headless canvases cannot hit-test paths
+    /// and ignore this clip for hit-testing.
+    pub fn clip_path(&mut self, d: String) {
+        self.set_clip(Some(Clip::Path { d }));
+    }
+
+    /// Clips the node to the current outline of `other`, following it as it
+    /// moves or rotates.
+    pub fn clip_to(&mut self, other: &NodeRef) {
+        if other.id != self.id {
+            self.set_clip(Some(Clip::Node { node: other.id }));
         }
+    }
+
+    pub fn clear_clip(&mut self) {
+        self.set_clip(None);
+    }
+
+    /// Lets `handler` take over wheel input while the pointer is over this
+    /// node, e.g. to scroll its content. It receives
+    /// `{ node, dx, dy, x, y, shift, ctrl, alt, meta }` with deltas in pixels
+    /// and the position in scene coordinates, and returns `true` when it
+    /// consumed the event; otherwise the view pans or zooms as usual. It runs
+    /// in the middle of the wheel event, so it must not use the canvas or
+    /// any node handle; those calls throw. Passing nothing removes the handler.
+    pub fn set_wheel_handler(&mut self, handler: Option<js_sys::Function>) {
+        let id = self.id;
+        let context = self.get_context_mut();
+        match handler {
+            Some(handler) => context.wheel_handlers.insert(id, handler),
+            None => context.wheel_handlers.remove(&id),
+        };
+    }
 
-        false
+    /// Sets how the node can be dragged:
+    /// `{ draggable, axis: "x" | "y", bounds }`, where `bounds` is either
+    /// `{ type: "rect", left, top, width, height }` in scene coordinates or
+    /// `{ type: "node", node }` to stay inside another node. Missing fields
+    /// fall back to a freely draggable node.
+    pub fn set_drag_options(&mut self, options: JsValue) -> Result<(), JsValue> {
+        let options = DragOptions::from_json(&options)?;
+        let id = self.id;
+        self.get_context_mut().drag_options.insert(id, options);
+        Ok(())
+    }
+
+    /// Lets `handler` adjust where the node goes while it is dragged. It
+    /// receives `{ node, x, y, dx, dy }`, the proposed top left corner of the
+    /// node's bounds after axis and bounds constraints plus the offset from
+    /// where the drag began, and may return another `{ x, y }`; anything else
+    /// keeps the proposal. It runs in the middle of the drag, so it must not
+    /// use the canvas or any node handle; those calls throw. Passing nothing
+    /// removes the handler.
+    pub fn set_drag_handler(&mut self, handler: Option<js_sys::Function>) {
+        let id = self.id;
+        let context = self.get_context_mut();
+        match handler {
+            Some(handler) => context.drag_handlers.insert(id, handler),
+            None => context.drag_handlers.remove(&id),
+        };
+    }
+
+    /// Like `Canvas.on`, but only for events involving this node. The
+    /// listener stays registered if the node is removed, so it works again
+    /// when the removal is undone; use `Canvas.off` to drop it.
+    pub fn on(&mut self, event: String, callback: js_sys::Function) -> Result<u32, JsValue> {
+        let id = self.id;
+        self.get_context_mut().subscribe(&event, Some(id), callback).map_err(|err| JsValue::from_str(&err))
+    }
+
+    pub fn remove(mut self) {
+        let id = self.id;
+        self.get_context_mut().record(|ctx| ctx.remove_node(id));
+    }
+
+    pub fn is_hovered(&self, x: f64, y: f64) -> bool {
+        let context = self.get_context();
+        match context.get_node_index(self.id) {
+            Some(index) => context.hit_test(index, &Event::from_mouse_move(x, y, 0.0)),
+            None => false,
+        }
     }
 }
@@ -0,0 +1,332 @@
+use std::f64::consts::PI;
+
+use super::geometry::Point;
+
+/// Straight segments each curve or arc is approximated with.
+const CURVE_SEGMENTS: usize = 16;
+
+/// Reads the numbers, flags and command letters of SVG path data.
+struct Tokens<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl Tokens<'_> {
+    fn skip_separators(&mut self) {
+        while self.data.get(self.position).is_some_and(|&c| c.is_ascii_whitespace() || c == b',') {
+            self.position += 1;
+        }
+    }
+
+    fn is_at_end(&mut self) -> bool {
+        self.skip_separators();
+        self.position >= self.data.len()
+    }
+
+    fn is_at_number(&mut self) -> bool {
+        self.skip_separators();
+        self.data.get(self.position).is_some_and(|&c| c.is_ascii_digit() || matches!(c, b'.' | b'-' | b'+'))
+    }
+
+    fn command(&mut self) -> Option<u8> {
+        self.skip_separators();
+        let command = *self.data.get(self.position).filter(|c| c.is_ascii_alphabetic())?;
+        self.position += 1;
+        Some(command)
+    }
+
+    fn number(&mut self) -> Option<f64> {
+        self.skip_separators();
+        let start = self.position;
+        let digits = |tokens: &mut Self| {
+            let from = tokens.position;
+            while tokens.data.get(tokens.position).is_some_and(u8::is_ascii_digit) {
+                tokens.position += 1;
+            }
+            tokens.position > from
+        };
+        let sign = |tokens: &mut Self| {
+            if tokens.data.get(tokens.position).is_some_and(|&c| c == b'-' || c == b'+') {
+                tokens.position += 1;
+            }
+        };
+
+        sign(self);
+        let mut has_digits = digits(self);
+        if self.data.get(self.position) == Some(&b'.') {
+            self.position += 1;
+            has_digits |= digits(self);
+        }
+        if !has_digits {
+            self.position = start;
+            return None;
+        }
+        if self.data.get(self.position).is_some_and(|&c| c == b'e' || c == b'E') {
+            let mantissa_end = self.position;
+            self.position += 1;
+            sign(self);
+            if !digits(self) {
+                self.position = mantissa_end;
+            }
+        }
+        std::str::from_utf8(&self.data[start..self.position]).ok()?.parse().ok()
+    }
+
+    fn point(&mut self) -> Option<Point> {
+        Some(Point { x: self.number()?, y: self.number()? })
+    }
+
+    /// Arc flags are a single `0` or `1` and may run into the next number.
+    fn flag(&mut self) -> Option<bool> {
+        self.skip_separators();
+        let flag = match self.data.get(self.position)? {
+            b'0' => false,
+            b'1' => true,
+            _ => return None,
+        };
+        self.position += 1;
+        Some(flag)
+    }
+}
+
+fn offset(point: Point, by: Point) -> Point {
+    Point { x: point.x + by.x, y: point.y + by.y }
+}
+
+fn reflect(control: Point, around: Point) -> Point {
+    Point { x: 2.0 * around.x - control.x, y: 2.0 * around.y - control.y }
+}
+
+fn cubic(points: &mut Vec<Point>, from: Point, c1: Point, c2: Point, to: Point) {
+    for step in 1..=CURVE_SEGMENTS {
+        let t = step as f64 / CURVE_SEGMENTS as f64;
+        let u = 1.0 - t;
+        let (a, b, c, d) = (u * u * u, 3.0 * u * u * t, 3.0 * u * t * t, t * t * t);
+        points.push(Point {
+            x: a * from.x + b * c1.x + c * c2.x + d * to.x,
+            y: a * from.y + b * c1.y + c * c2.y + d * to.y,
+        });
+    }
+}
+
+fn quadratic(points: &mut Vec<Point>, from: Point, control: Point, to: Point) {
+    for step in 1..=CURVE_SEGMENTS {
+        let t = step as f64 / CURVE_SEGMENTS as f64;
+        let u = 1.0 - t;
+        let (a, b, c) = (u * u, 2.0 * u * t, t * t);
+        points.push(Point {
+            x: a * from.x + b * control.x + c * to.x,
+            y: a * from.y + b * control.y + c * to.y,
+        });
+    }
+}
+
+/// Elliptical arc from `from` to `to`, following the endpoint to centre
+/// conversion of the SVG specification.
+#[allow(clippy::too_many_arguments)]
+fn arc(points: &mut Vec<Point>, from: Point, rx: f64, ry: f64, rotation: f64, large: bool, sweep: bool, to: Point) {
+    let (mut rx, mut ry) = (rx.abs(), ry.abs());
+    if rx == 0.0 || ry == 0.0 || (from.x == to.x && from.y == to.y) {
+        points.push(to);
+        return;
+    }
+
+    let (sin, cos) = rotation.to_radians().sin_cos();
+    let (hx, hy) = ((from.x - to.x) / 2.0, (from.y - to.y) / 2.0);
+    let (x1, y1) = (cos * hx + sin * hy, -sin * hx + cos * hy);
+    let lambda = (x1 * x1) / (rx * rx) + (y1 * y1) / (ry * ry);
+    if lambda > 1.0 {
+        rx *= lambda.sqrt();
+        ry *= lambda.sqrt();
+    }
+
+    let numerator = rx * rx * ry * ry - rx * rx * y1 * y1 - ry * ry * x1 * x1;
+    let denominator = rx * rx * y1 * y1 + ry * ry * x1 * x1;
+    let sign = if large == sweep { -1.0 } else { 1.0 };
+    let coefficient = sign * (numerator / denominator).max(0.0).sqrt();
+    let (cx1, cy1) = (coefficient * rx * y1 / ry, -coefficient * ry * x1 / rx);
+    let center = Point {
+        x: cos * cx1 - sin * cy1 + (from.x + to.x) / 2.0,
+        y: sin * cx1 + cos * cy1 + (from.y + to.y) / 2.0,
+    };
+
+    let angle = |ux: f64, uy: f64, vx: f64, vy: f64| (ux * vy - uy * vx).atan2(ux * vx + uy * vy);
+    let start = angle(1.0, 0.0, (x1 - cx1) / rx, (y1 - cy1) / ry);
+    let mut sweep_angle = angle((x1 - cx1) / rx, (y1 - cy1) / ry, (-x1 - cx1) / rx, (-y1 - cy1) / ry);
+    if !sweep && sweep_angle > 0.0 {
+        sweep_angle -= 2.0 * PI;
+    } else if sweep && sweep_angle < 0.0 {
+        sweep_angle += 2.0 * PI;
+    }
+
+    for step in 1..=CURVE_SEGMENTS {
+        let (s, c) = (start + sweep_angle * step as f64 / CURVE_SEGMENTS as f64).sin_cos();
+        points.push(Point {
+            x: center.x + rx * c * cos - ry * s * sin,
+            y: center.y + rx * c * sin + ry * s * cos,
+        });
+    }
+}
+
+/// Outline of the SVG path data `d` as one polygon per subpath, with curves
+/// and arcs flattened. Like browsers, it keeps whatever came before the
+/// first error in the data.
+pub(crate) fn flatten(d: &str) -> Vec<Vec<Point>> {
+    let mut tokens = Tokens { data: d.as_bytes(), position: 0 };
+    let mut subpaths = Vec::new();
+    let mut points: Vec<Point> = Vec::new();
+    let (mut current, mut start) = (Point { x: 0.0, y: 0.0 }, Point { x: 0.0, y: 0.0 });
+    // Second control point of the previous cubic or the control point of
+    // the previous quadratic, which `S` and `T` mirror.
+    let (mut last_cubic, mut last_quadratic): (Option<Point>, Option<Point>) = (None, None);
+
+    let Some(mut command) = tokens.command().filter(|command| command.eq_ignore_ascii_case(&b'M')) else {
+        return subpaths;
+    };
+    loop {
+        let origin = if command.is_ascii_lowercase() { current } else { Point { x: 0.0, y: 0.0 } };
+        let (cubic_control, quadratic_control) = (last_cubic.take(), last_quadratic.take());
+        let segment = (|| {
+            match command.to_ascii_uppercase() {
+                b'M' => {
+                    current = offset(tokens.point()?, origin);
+                    if points.len() > 1 {
+                        subpaths.push(std::mem::take(&mut points));
+                    }
+                    points = vec![current];
+                    start = current;
+                    // Further coordinate pairs are implicit line-tos.
+                    command = if command == b'm' { b'l' } else { b'L' };
+                    return Some(());
+                }
+                b'L' => current = offset(tokens.point()?, origin),
+                b'H' => current.x = tokens.number()? + origin.x,
+                b'V' => current.y = tokens.number()? + origin.y,
+                b'C' | b'S' => {
+                    let c1 = match command.to_ascii_uppercase() {
+                        b'C' => offset(tokens.point()?, origin),
+                        _ => cubic_control.map_or(current, |control| reflect(control, current)),
+                    };
+                    let (c2, to) = (offset(tokens.point()?, origin), offset(tokens.point()?, origin));
+                    cubic(&mut points, current, c1, c2, to);
+                    (current, last_cubic) = (to, Some(c2));
+                    return Some(());
+                }
+                b'Q' | b'T' => {
+                    let control = match command.to_ascii_uppercase() {
+                        b'Q' => offset(tokens.point()?, origin),
+                        _ => quadratic_control.map_or(current, |control| reflect(control, current)),
+                    };
+                    let to = offset(tokens.point()?, origin);
+                    quadratic(&mut points, current, control, to);
+                    (current, last_quadratic) = (to, Some(control));
+                    return Some(());
+                }
+                b'A' => {
+                    let (rx, ry, rotation) = (tokens.number()?, tokens.number()?, tokens.number()?);
+                    let (large, sweep) = (tokens.flag()?, tokens.flag()?);
+                    let to = offset(tokens.point()?, origin);
+                    arc(&mut points, current, rx, ry, rotation, large, sweep, to);
+                    current = to;
+                    return Some(());
+                }
+                b'Z' => {
+                    current = start;
+                    if points.len() > 1 {
+                        subpaths.push(std::mem::take(&mut points));
+                    }
+                    points = vec![start];
+                    return Some(());
+                }
+                _ => return None,
+            }
+            points.push(current);
+            Some(())
+        })();
+        if segment.is_none() {
+            break;
+        }
+
+        if command.eq_ignore_ascii_case(&b'Z') || !tokens.is_at_number() {
+            if tokens.is_at_end() {
+                break;
+            }
+            match tokens.command() {
+                Some(next) => command = next,
+                None => break,
+            }
+        }
+    }
+
+    if points.len() > 1 {
+        subpaths.push(points);
+    }
+    subpaths
+}
+
+/// Whether `point` is inside the flattened path under the nonzero fill rule,
+/// which canvases use by default. Open subpaths count as closed.
+pub(crate) fn contains(subpaths: &[Vec<Point>], point: Point) -> bool {
+    let mut winding = 0;
+    for subpath in subpaths {
+        for (index, from) in subpath.iter().enumerate() {
+            let to = subpath[(index + 1) % subpath.len()];
+            let side = (to.x - from.x) * (point.y - from.y) - (point.x - from.x) * (to.y - from.y);
+            if from.y <= point.y && to.y > point.y && side > 0.0 {
+                winding += 1;
+            } else if from.y > point.y && to.y <= point.y && side < 0.0 {
+                winding -= 1;
+            }
+        }
+    }
+    winding != 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn inside(d: &str, x: f64, y: f64) -> bool {
+        contains(&flatten(d), Point { x, y })
+    }
+
+    #[test]
+    fn straight_paths_in_absolute_and_relative_form() {
+        for d in ["M0 0 L10 0 L10 10 L0 10 Z", "m0,0 h10 v10 h-10 z", "M0 0 10 0 10 10 0 10"] {
+            assert!(inside(d, 5.0, 5.0), "{}", d);
+            assert!(!inside(d, 15.0, 5.0), "{}", d);
+            assert!(!inside(d, 5.0, -1.0), "{}", d);
+        }
+    }
+
+    #[test]
+    fn arcs_and_curves_are_followed() {
+        // A circle of radius 10 around (10, 10) from two arcs.
+        let circle = "M0 10 A10 10 0 0 1 20 10 A10 10 0 0 1 0 10 Z";
+        assert!(inside(circle, 10.0, 10.0));
+        assert!(inside(circle, 10.0, 1.0));
+        assert!(!inside(circle, 1.0, 1.0));
+
+        // The cubic bulges up to y = -7.5 between its ends.
+        let bump = "M0 0 C0 -10 10 -10 10 0 Z";
+        assert!(inside(bump, 5.0, -5.0));
+        assert!(!inside(bump, 5.0, -9.0));
+        assert!(inside("M0 0 Q5 -10 10 0 T20 0 Z", 5.0, -3.0));
+        assert!(inside("M0 0 Q5 -10 10 0 T20 0 Z", 15.0, 3.0));
+    }
+
+    #[test]
+    fn holes_follow_the_nonzero_rule() {
+        let outer = "M0 0 H30 V30 H0 Z";
+        assert!(!inside(&format!("{} M10 10 V20 H20 V10 Z", outer), 15.0, 15.0));
+        assert!(inside(&format!("{} M10 10 H20 V20 H10 Z", outer), 15.0, 15.0));
+    }
+
+    #[test]
+    fn data_after_an_error_is_ignored() {
+        assert!(inside("M0 0 H10 V10 H0 Z M20 20 X 30 30", 5.0, 5.0));
+        assert!(flatten("L10 10").is_empty());
+        assert!(flatten("").is_empty());
+        assert!(inside("M0 0 h1e1 v10 h-10z", 5.0, 5.0));
+    }
+}
@@ -0,0 +1,145 @@
+use super::{
+    click::ClickSettings,
+    clipboard::ClipboardData,
+    event::{DeltaMode, Modifiers, PointerInput},
+    import::DropItem,
+    keymap::Keymap,
+    node::{Node, NodeId},
+    selection::MarqueeMode,
+    snap::SnapSettings,
+    theme::SelectionTheme,
+    viewport::Viewport,
+};
+
+/// Marks text produced by `stop_recording`, like the clipboard format.
+const RECORDING_FORMAT: &str = "canvas-input";
+const RECORDING_VERSION: u32 = 2;
+
+/// One call into the canvas' input API, with the arguments it was given.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub(crate) enum Input {
+    MouseDown { x: f64, y: f64, ts: f64, modifiers: Option<Modifiers>, pointer: PointerInput },
+    MouseMove { x: f64, y: f64, ts: f64, modifiers: Option<Modifiers>, pointer: PointerInput },
+    MouseUp { x: f64, y: f64, ts: f64, modifiers: Option<Modifiers>, pointer: PointerInput },
+    PointerDown { id: i32, x: f64, y: f64, ts: f64, modifiers: Option<Modifiers>, pointer: PointerInput },
+    PointerMove { id: i32, x: f64, y: f64, ts: f64, modifiers: Option<Modifiers>, pointer: PointerInput },
+    PointerUp { id: i32, x: f64, y: f64, ts: f64, modifiers: Option<Modifiers>, pointer: PointerInput },
+    PointerCancel { id: i32, ts: f64 },
+    Wheel { dx: f64, dy: f64, x: f64, y: f64, mode: DeltaMode, modifiers: Option<Modifiers> },
+    KeyDown { key: String, ts: f64, modifiers: Option<Modifiers> },
+    KeyUp { key: String, ts: f64, modifiers: Option<Modifiers> },
+    KeyPress { key: String, ts: f64, modifiers: Option<Modifiers> },
+    DragOver { x: f64, y: f64 },
+    DragLeave,
+    Drop { x: f64, y: f64, items: Vec<DropItem> },
+    /// A `render` at time `now`, which processes everything queued before.
+    Frame { now: f64 },
+}
+
+/// Canvas state that the outcome of input depends on, restored before a
+/// replay.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub(crate) struct Setup {
+    pub(crate) selection: Vec<NodeId>,
+    /// Last id handed out, so nodes added during the replay get the same ids.
+    pub(crate) last_id: NodeId,
+    pub(crate) viewport: Viewport,
+    pub(crate) snap: SnapSettings,
+    pub(crate) clicks: ClickSettings,
+    /// Decides, through the handle size, what a press grabs.
+    pub(crate) theme: SelectionTheme,
+    pub(crate) keymap: Keymap,
+    pub(crate) marquee_mode: MarqueeMode,
+    pub(crate) min_zoom: f64,
+    pub(crate) max_zoom: f64,
+    /// Canvas size in pixels, which page-wise wheel scrolling depends on.
+    pub(crate) size: (f64, f64),
+}
+
+/// Input recorded from a canvas, together with the state it started from,
+/// so replaying it on a fresh canvas ends in the same scene.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub(crate) struct Recording {
+    format: String,
+    version: u32,
+    /// Nodes when the recording started, in the clipboard format.
+    pub(crate) scene: ClipboardData,
+    #[serde(flatten)]
+    pub(crate) setup: Setup,
+    pub(crate) inputs: Vec<Input>,
+}
+
+impl Recording {
+    pub(crate) fn new(nodes: &[Node], setup: Setup) -> Self {
+        Self {
+            format: RECORDING_FORMAT.to_string(),
+            version: RECORDING_VERSION,
+            scene: ClipboardData::from_nodes(nodes),
+            setup,
+            inputs: Vec::new(),
+        }
+    }
+
+    pub(crate) fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+
+    pub(crate) fn from_json(json: &str) -> Result<Self, String> {
+        let recording: Self = serde_json::from_str(json).map_err(|err| format!("invalid recording: {}", err))?;
+        if recording.format != RECORDING_FORMAT || recording.version > RECORDING_VERSION {
+            return Err(format!(
+                "unsupported recording format `{}` version {}",
+                recording.format, recording.version
+            ));
+        }
+        Ok(recording)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn recording() -> Recording {
+        let setup = Setup {
+            selection: vec![1],
+            last_id: 1,
+            viewport: Viewport::default(),
+            snap: SnapSettings::default(),
+            clicks: ClickSettings::default(),
+            theme: SelectionTheme::default(),
+            keymap: Keymap::default(),
+            marquee_mode: MarqueeMode::Contain,
+            min_zoom: 0.5,
+            max_zoom: 2.0,
+            size: (640.0, 480.0),
+        };
+        let mut recording = Recording::new(&[], setup);
+        recording.inputs = vec![
+            Input::PointerDown { id: 1, x: 1.0, y: 2.0, ts: 3.0, modifiers: None, pointer: PointerInput::default() },
+            Input::KeyDown { key: "a".to_string(), ts: 4.0, modifiers: Some(Modifiers::default()) },
+            Input::DragLeave,
+            Input::Frame { now: 5.0 },
+        ];
+        recording
+    }
+
+    #[test]
+    fn recordings_survive_json() {
+        let recording = recording();
+        assert_eq!(Recording::from_json(&recording.to_json()), Ok(recording));
+    }
+
+    #[test]
+    fn other_formats_and_newer_versions_are_rejected() {
+        let mut other = recording();
+        other.format = "canvas-clipboard".to_string();
+        assert!(Recording::from_json(&other.to_json()).is_err());
+
+        let mut newer = recording();
+        newer.version = RECORDING_VERSION + 1;
+        assert!(Recording::from_json(&newer.to_json()).is_err());
+        assert!(Recording::from_json("{}").is_err());
+    }
+}
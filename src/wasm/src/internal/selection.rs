@@ -0,0 +1,58 @@
+use super::{
+    geometry::{Point, Rectangle},
+    node::NodeId,
+};
+
+/// Which nodes a marquee drag picks up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum MarqueeMode {
+    /// Nodes whose bounds touch the marquee.
+    #[default]
+    Intersect,
+    /// Only nodes whose bounds lie entirely inside the marquee.
+    Contain,
+}
+
+impl MarqueeMode {
+    pub(crate) fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "intersect" => Some(Self::Intersect),
+            "contain" => Some(Self::Contain),
+            _ => None,
+        }
+    }
+}
+
+/// Rubber-band rectangle dragged out on empty canvas.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Marquee {
+    pub(crate) start: Point,
+    pub(crate) end: Point,
+    /// Selection from before the drag, kept when the drag is additive.
+    pub(crate) base: Vec<NodeId>,
+}
+
+impl Marquee {
+    pub(crate) fn new(x: f64, y: f64, base: Vec<NodeId>) -> Self {
+        let start = Point { x, y };
+        Self { start, end: start, base }
+    }
+
+    pub(crate) fn get_rect(&self) -> Rectangle {
+        Rectangle {
+            left: self.start.x.min(self.end.x),
+            top: self.start.y.min(self.end.y),
+            width: (self.end.x - self.start.x).abs(),
+            height: (self.end.y - self.start.y).abs(),
+        }
+    }
+
+    pub(crate) fn selects(&self, bounds: Rectangle, mode: MarqueeMode) -> bool {
+        let rect = self.get_rect();
+        match mode {
+            MarqueeMode::Intersect => rect.intersects(&bounds),
+            MarqueeMode::Contain => rect.contains_rect(&bounds),
+        }
+    }
+}
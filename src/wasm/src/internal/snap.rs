@@ -0,0 +1,276 @@
+use gloo_utils::format::JsValueSerdeExt;
+use wasm_bindgen::JsValue;
+
+use super::{
+    color::Color,
+    geometry::{Point, Rectangle},
+    theme::merge,
+};
+
+/// Distance under which two edges count as aligned when drawing guides.
+const ALIGNED_EPSILON: f64 = 0.5;
+/// Length of the end ticks of an equal-spacing indicator, in pixels.
+const SPACING_TICK: f64 = 4.0;
+
+/// How dragged nodes snap, configurable per canvas.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct SnapSettings {
+    pub enabled: bool,
+    /// Spacing of the grid the dragged bounds snap to; `None` for no grid.
+    pub grid_size: Option<f64>,
+    /// Snap to the edges and centres of the other nodes, and to equal gaps
+    /// between them.
+    pub to_objects: bool,
+    /// How close, in screen pixels, an edge has to come to snap.
+    pub threshold: f64,
+    pub guide_color: Color,
+}
+
+impl Default for SnapSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            grid_size: None,
+            to_objects: true,
+            threshold: 6.0,
+            guide_color: Color::from_hex(0xff3d8b),
+        }
+    }
+}
+
+impl SnapSettings {
+    /// Returns a copy of the settings with the (possibly partial) JS object
+    /// `patch` applied on top.
+    pub(crate) fn patch(&self, patch: &JsValue) -> Result<Self, JsValue> {
+        let patch: serde_json::Value = patch
+            .into_serde()
+            .map_err(|err| JsValue::from_str(&format!("invalid snap settings: {}", err)))?;
+        let mut settings = serde_json::to_value(self).map_err(|err| JsValue::from_str(&err.to_string()))?;
+        merge(&mut settings, patch);
+        serde_json::from_value(settings).map_err(|err| JsValue::from_str(&format!("invalid snap settings: {}", err)))
+    }
+}
+
+/// Temporary marks drawn while dragging, in scene coordinates.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Guide {
+    /// Edges or centres lined up along this segment.
+    Alignment { from: Point, to: Point },
+    /// One of two equal gaps on either side of the dragged nodes.
+    Spacing { from: Point, to: Point },
+}
+
+/// Interval of a rectangle along one axis.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Span {
+    start: f64,
+    end: f64,
+}
+
+impl Span {
+    fn x(rect: &Rectangle) -> Self {
+        Self { start: rect.left, end: rect.left + rect.width }
+    }
+
+    fn y(rect: &Rectangle) -> Self {
+        Self { start: rect.top, end: rect.top + rect.height }
+    }
+
+    fn anchors(self) -> [f64; 3] {
+        [self.start, (self.start + self.end) / 2.0, self.end]
+    }
+
+    fn overlaps(self, other: Span) -> bool {
+        self.start < other.end && other.start < self.end
+    }
+
+    fn shifted(self, offset: f64) -> Self {
+        Self { start: self.start + offset, end: self.end + offset }
+    }
+}
+
+/// Nearest neighbours before and after `moving` along an axis, among the
+/// `others` that overlap it on the cross axis.
+fn neighbours(moving: Span, cross: Span, others: &[(Span, Span)]) -> Option<(Span, Span)> {
+    let lined_up = || others.iter().filter(|(_, other_cross)| other_cross.overlaps(cross));
+    let before = lined_up()
+        .filter(|(span, _)| span.end <= moving.start + (moving.end - moving.start) / 2.0)
+        .max_by(|(a, _), (b, _)| a.end.total_cmp(&b.end))?;
+    let after = lined_up()
+        .filter(|(span, _)| span.start >= moving.start + (moving.end - moving.start) / 2.0)
+        .min_by(|(a, _), (b, _)| a.start.total_cmp(&b.start))?;
+    Some((before.0, after.0))
+}
+
+/// Correction along one axis that snaps `moving` to the closest of: another
+/// node's edge or centre, an equal gap between two neighbours, or the grid.
+fn snap_axis(moving: Span, cross: Span, others: &[(Span, Span)], settings: &SnapSettings, threshold: f64) -> f64 {
+    let mut best: Option<f64> = None;
+    let mut consider = |correction: f64| {
+        if correction.abs() <= threshold && best.is_none_or(|best| correction.abs() < best.abs()) {
+            best = Some(correction);
+        }
+    };
+
+    if settings.to_objects {
+        for (other, _) in others {
+            for target in other.anchors() {
+                for anchor in moving.anchors() {
+                    consider(target - anchor);
+                }
+            }
+        }
+        if let Some((before, after)) = neighbours(moving, cross, others) {
+            let size = moving.end - moving.start;
+            consider((before.end + after.start - size) / 2.0 - moving.start);
+        }
+    }
+
+    match (best, settings.grid_size) {
+        (Some(correction), _) => correction,
+        (None, Some(grid)) if grid > 0.0 => (moving.start / grid).round() * grid - moving.start,
+        _ => 0.0,
+    }
+}
+
+/// Guides for the final position of the dragged bounds along one axis.
+/// `point` builds a scene point from an (axis, cross axis) pair.
+fn axis_guides(
+    moving: Span,
+    cross: Span,
+    others: &[(Span, Span)],
+    point: impl Fn(f64, f64) -> Point,
+) -> Vec<Guide> {
+    let mut guides = Vec::new();
+    for (other, other_cross) in others {
+        for target in other.anchors() {
+            if moving.anchors().iter().any(|anchor| (anchor - target).abs() < ALIGNED_EPSILON) {
+                let from = cross.start.min(other_cross.start);
+                let to = cross.end.max(other_cross.end);
+                guides.push(Guide::Alignment { from: point(target, from), to: point(target, to) });
+            }
+        }
+    }
+
+    if let Some((before, after)) = neighbours(moving, cross, others) {
+        let (gap_before, gap_after) = (moving.start - before.end, after.start - moving.end);
+        if gap_before > 0.0 && (gap_before - gap_after).abs() < ALIGNED_EPSILON {
+            let middle = (cross.start + cross.end) / 2.0;
+            guides.push(Guide::Spacing { from: point(before.end, middle), to: point(moving.start, middle) });
+            guides.push(Guide::Spacing { from: point(moving.end, middle), to: point(after.start, middle) });
+        }
+    }
+    guides
+}
+
+/// Snaps the bounds of the dragged nodes against `others` (the bounds of
+/// every other visible node). Returns the correction to add to the drag
+/// offset and the guides to draw. `scale` is the view zoom, so the threshold
+/// stays the same on screen.
+pub(crate) fn snap(moving: Rectangle, others: &[Rectangle], settings: &SnapSettings, scale: f64) -> (Point, Vec<Guide>) {
+    let threshold = settings.threshold / scale;
+    let (x, y) = (Span::x(&moving), Span::y(&moving));
+    let by_x: Vec<(Span, Span)> = others.iter().map(|other| (Span::x(other), Span::y(other))).collect();
+    let by_y: Vec<(Span, Span)> = by_x.iter().map(|&(x, y)| (y, x)).collect();
+
+    let correction = Point {
+        x: snap_axis(x, y, &by_x, settings, threshold),
+        y: snap_axis(y, x, &by_y, settings, threshold),
+    };
+
+    let (x, y) = (x.shifted(correction.x), y.shifted(correction.y));
+    let mut guides = axis_guides(x, y, &by_x, |along, across| Point { x: along, y: across });
+    guides.extend(axis_guides(y, x, &by_y, |along, across| Point { x: across, y: along }));
+    (correction, guides)
+}
+
+impl Guide {
+    pub(crate) fn render(&self, ctx: &web_sys::CanvasRenderingContext2d, color: Color, scale: f64) {
+        let (from, to) = match self {
+            Guide::Alignment { from, to } | Guide::Spacing { from, to } => (from, to),
+        };
+
+        ctx.save();
+        ctx.set_stroke_style_str(&color.to_css());
+        ctx.set_line_width(1.0 / scale);
+        ctx.begin_path();
+        ctx.move_to(from.x, from.y);
+        ctx.line_to(to.x, to.y);
+
+        if let Guide::Spacing { .. } = self {
+            // Perpendicular ticks at both ends.
+            let (dx, dy) = (to.x - from.x, to.y - from.y);
+            let length = (dx * dx + dy * dy).sqrt().max(f64::EPSILON);
+            let (nx, ny) = (-dy / length * SPACING_TICK / scale, dx / length * SPACING_TICK / scale);
+            for end in [from, to] {
+                ctx.move_to(end.x - nx, end.y - ny);
+                ctx.line_to(end.x + nx, end.y + ny);
+            }
+        }
+        ctx.stroke();
+        ctx.restore();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(left: f64, top: f64, width: f64, height: f64) -> Rectangle {
+        Rectangle { left, top, width, height }
+    }
+
+    fn correction(moving: Rectangle, others: &[Rectangle], settings: &SnapSettings, scale: f64) -> (f64, f64) {
+        let (correction, _) = snap(moving, others, settings, scale);
+        (correction.x, correction.y)
+    }
+
+    #[test]
+    fn edges_and_centres_within_the_threshold_snap() {
+        let settings = SnapSettings::default();
+        let other = rect(100.0, 0.0, 50.0, 50.0);
+        // Right edge 4 short of the other's left edge.
+        assert_eq!(correction(rect(46.0, 200.0, 50.0, 50.0), &[other], &settings, 1.0), (4.0, 0.0));
+        // Centres 3 apart vertically.
+        assert_eq!(correction(rect(300.0, 3.0, 50.0, 50.0), &[other], &settings, 1.0), (0.0, -3.0));
+        // Too far on both axes.
+        assert_eq!(correction(rect(40.0, 200.0, 50.0, 50.0), &[other], &settings, 1.0), (0.0, 0.0));
+    }
+
+    #[test]
+    fn the_threshold_is_in_screen_pixels() {
+        let settings = SnapSettings::default();
+        let other = rect(100.0, 0.0, 50.0, 50.0);
+        let moving = rect(46.0, 200.0, 50.0, 50.0);
+        assert_eq!(correction(moving, &[other], &settings, 2.0), (0.0, 0.0));
+        assert_eq!(correction(moving, &[other], &settings, 0.5), (4.0, 0.0));
+    }
+
+    #[test]
+    fn the_grid_applies_when_no_node_is_close() {
+        let settings = SnapSettings { grid_size: Some(20.0), ..Default::default() };
+        assert_eq!(correction(rect(47.0, 12.0, 10.0, 10.0), &[], &settings, 1.0), (-7.0, 8.0));
+
+        let other = rect(45.0, 500.0, 10.0, 10.0);
+        assert_eq!(correction(rect(47.0, 12.0, 10.0, 10.0), &[other], &settings, 1.0), (-2.0, 8.0));
+    }
+
+    #[test]
+    fn equal_gaps_between_neighbours_snap_and_show_spacing_guides() {
+        let settings = SnapSettings::default();
+        let others = [rect(0.0, 0.0, 20.0, 20.0), rect(100.0, 0.0, 20.0, 20.0)];
+        let (correction, guides) = snap(rect(52.0, 0.0, 20.0, 20.0), &others, &settings, 1.0);
+        assert_eq!(correction.x, -2.0);
+        let spacing = guides.iter().filter(|guide| matches!(guide, Guide::Spacing { .. })).count();
+        assert_eq!(spacing, 2);
+    }
+
+    #[test]
+    fn disabled_object_snapping_ignores_other_nodes() {
+        let settings = SnapSettings { to_objects: false, ..Default::default() };
+        let other = rect(100.0, 0.0, 50.0, 50.0);
+        let (correction, guides) = snap(rect(46.0, 200.0, 50.0, 50.0), &[other], &settings, 1.0);
+        assert_eq!((correction.x, correction.y), (0.0, 0.0));
+        assert!(guides.is_empty());
+    }
+}
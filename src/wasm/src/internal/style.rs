@@ -1,7 +1,9 @@
 use gloo_utils::format::JsValueSerdeExt;
 use wasm_bindgen::JsValue;
 
-use super::geometry::Rectangle;
+use super::{clip::Clip, color::Color, cursor::Cursor, geometry::Rectangle};
+
+pub(crate) const DEFAULT_FONT_SIZE: f64 = 10.0;
 
 #[derive(Debug, Clone, Copy, PartialEq, PartialOrd, serde::Serialize, serde::Deserialize)]
 pub enum ControlPoint {
@@ -37,14 +39,50 @@ impl ControlPoint {
     }
 }
 
+/// Whether a node takes part in hit-testing, like the CSS property.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PointerEvents {
+    #[default]
+    Auto,
+    /// Clicks and hovers go through to whatever lies underneath.
+    None,
+}
+
+impl PointerEvents {
+    pub(crate) fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "auto" => Some(Self::Auto),
+            "none" => Some(Self::None),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Self::Auto => "auto",
+            Self::None => "none",
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, PartialOrd, serde::Serialize, serde::Deserialize, Default)]
 pub struct Style{
-    pub fill_color: Option<String>,
+    pub fill_color: Option<Color>,
     pub stroke_width: Option<f64>,
-    pub stroke_color: Option<String>,
+    pub stroke_color: Option<Color>,
     pub font_size: Option<f64>,
     pub rotation: Option<(f64, ControlPoint)>,
     pub translate: Option<(f64, f64)>,
+    pub clip: Option<Clip>,
+    /// Cursor shown while the pointer is over the node.
+    pub cursor: Option<Cursor>,
+    /// Hidden nodes are neither drawn nor hit. Visible unless set.
+    pub visible: Option<bool>,
+    /// Locked nodes are drawn and still block the pointer, but cannot be
+    /// selected, dragged or edited.
+    pub locked: Option<bool>,
+    pub pointer_events: Option<PointerEvents>,
 }
 
 impl Style {
@@ -52,7 +90,7 @@ impl Style {
         Self::default()
     }
     
-    pub fn fill_color(&mut self, color: String) -> &mut Self {
+    pub fn fill_color(&mut self, color: Color) -> &mut Self {
         self.fill_color = Some(color);
         self
     }
@@ -72,7 +110,7 @@ impl Style {
         self
     }
 
-    pub fn stroke(&mut self, width: f64, color: String) -> &mut Self {
+    pub fn stroke(&mut self, width: f64, color: Color) -> &mut Self {
         self.stroke_width = Some(width);
         self.stroke_color = Some(color);
         self
@@ -105,34 +143,129 @@ impl Style {
         self
     }
 
-    pub(crate) fn from_json(json: &JsValue) -> Self {
-        return json.into_serde().unwrap_or_default();
+    pub(crate) fn is_visible(&self) -> bool {
+        self.visible.unwrap_or(true)
+    }
+
+    pub(crate) fn is_locked(&self) -> bool {
+        self.locked.unwrap_or(false)
+    }
+
+    /// Whether the pointer can hit the node at all.
+    pub(crate) fn is_hittable(&self) -> bool {
+        self.is_visible() && self.pointer_events.unwrap_or_default() == PointerEvents::Auto
+    }
+
+    /// Parses a style object coming from JS. `undefined` and `null` yield
+    /// the default style; anything malformed, such as an unparsable color,
+    /// is reported back as an error.
+    pub(crate) fn from_json(json: &JsValue) -> Result<Self, JsValue> {
+        if json.is_undefined() || json.is_null() {
+            return Ok(Self::default());
+        }
+        json.into_serde()
+            .map_err(|err| JsValue::from_str(&format!("invalid style: {}", err)))
+    }
+
+    /// CSS font shorthand used for both drawing and measuring text. Falls back
+    /// to the canvas default of 10px when no font size is set.
+    pub(crate) fn font(&self) -> String {
+        format!("{}px sans-serif", self.font_size.unwrap_or(DEFAULT_FONT_SIZE))
     }
 
     pub(crate) fn apply_style(&self, ctx: &web_sys::CanvasRenderingContext2d, rect: Rectangle) {
-        self.fill_color.as_ref().map(|color| ctx.set_fill_style(&JsValue::from(color)));
-        self.stroke_width.as_ref().map(|width| ctx.set_line_width(*width));
-        self.stroke_color.as_ref().map(|color| ctx.set_stroke_style(&JsValue::from(color)));
-        self.font_size.as_ref().map(|size| ctx.set_font(&format!("{}px sans-serif", size)));
-        self.translate.as_ref().map(|(x, y)| ctx.translate(*x, *y));
-        self.rotation.as_ref().map(|(rotation, cp)| {
+        if let Some(color) = &self.fill_color {
+            ctx.set_fill_style_str(&color.to_css());
+        }
+        if let Some(width) = self.stroke_width {
+            ctx.set_line_width(width);
+        }
+        if let Some(color) = &self.stroke_color {
+            ctx.set_stroke_style_str(&color.to_css());
+        }
+        if self.font_size.is_some() {
+            ctx.set_font(&self.font());
+        }
+        self.apply_transform(ctx, rect);
+    }
+
+    /// Applies only `translate` and `rotation`, where `rect` is the
+    /// untransformed bounds the rotation's control point is resolved against.
+    pub(crate) fn apply_transform(&self, ctx: &web_sys::CanvasRenderingContext2d, rect: Rectangle) {
+        if let Some((x, y)) = self.translate {
+            let _ = ctx.translate(x, y);
+        }
+        if let Some((rotation, cp)) = self.rotation {
+            let Rectangle{ top, left, width, height } = rect;
+            let (ax, ay) = cp.resolve_coords(left, top, width, height);
+            let _ = ctx.translate(ax, ay);
+            let _ = ctx.rotate(rotation);
+            let _ = ctx.translate(-ax, -ay);
+        }
+    }
+
+    /// Reverts `apply_transform` without touching the rest of the drawing
+    /// state, e.g. to keep a clip region that was set up under the transform.
+    pub(crate) fn undo_transform(&self, ctx: &web_sys::CanvasRenderingContext2d, rect: Rectangle) {
+        if let Some((rotation, cp)) = self.rotation {
             let Rectangle{ top, left, width, height } = rect;
             let (ax, ay) = cp.resolve_coords(left, top, width, height);
             let _ = ctx.translate(ax, ay);
-            let _ = ctx.rotate(*rotation);
+            let _ = ctx.rotate(-rotation);
             let _ = ctx.translate(-ax, -ay);
-        });
-    }
-
-    pub(crate) fn patch(self, other: JsValue) -> Self {
-        let mut res = Self::default();
-        let other = Self::from_json(&other);
-        res.fill_color = other.fill_color.or(self.fill_color);
-        res.stroke_width = other.stroke_width.or(self.stroke_width);
-        res.stroke_color = other.stroke_color.or(self.stroke_color);
-        res.font_size = other.font_size.or(self.font_size);
-        res.rotation = other.rotation.or(self.rotation);
-        res.translate = other.translate.or(self.translate);
-        res
+        }
+        if let Some((x, y)) = self.translate {
+            let _ = ctx.translate(-x, -y);
+        }
+    }
+
+    /// Maps a canvas point into the node's untransformed space; the inverse of
+    /// `apply_transform`.
+    pub(crate) fn to_local(&self, x: f64, y: f64, rect: Rectangle) -> (f64, f64) {
+        let (tx, ty) = self.translate.unwrap_or_default();
+        let (x, y) = (x - tx, y - ty);
+        match self.rotation {
+            Some((rotation, cp)) => {
+                let Rectangle{ top, left, width, height } = rect;
+                let (ax, ay) = cp.resolve_coords(left, top, width, height);
+                let (dx, dy) = (x - ax, y - ay);
+                let (sin, cos) = (-rotation).sin_cos();
+                (ax + dx * cos - dy * sin, ay + dx * sin + dy * cos)
+            }
+            None => (x, y),
+        }
+    }
+
+    /// Maps a point in the node's untransformed space onto the canvas; the
+    /// inverse of `to_local`.
+    pub(crate) fn to_world(&self, x: f64, y: f64, rect: Rectangle) -> (f64, f64) {
+        let (tx, ty) = self.translate.unwrap_or_default();
+        let (x, y) = match self.rotation {
+            Some((rotation, cp)) => {
+                let Rectangle{ top, left, width, height } = rect;
+                let (ax, ay) = cp.resolve_coords(left, top, width, height);
+                let (dx, dy) = (x - ax, y - ay);
+                let (sin, cos) = rotation.sin_cos();
+                (ax + dx * cos - dy * sin, ay + dx * sin + dy * cos)
+            }
+            None => (x, y),
+        };
+        (x + tx, y + ty)
+    }
+
+    pub(crate) fn patch(self, other: Self) -> Self {
+        Self {
+            fill_color: other.fill_color.or(self.fill_color),
+            stroke_width: other.stroke_width.or(self.stroke_width),
+            stroke_color: other.stroke_color.or(self.stroke_color),
+            font_size: other.font_size.or(self.font_size),
+            rotation: other.rotation.or(self.rotation),
+            translate: other.translate.or(self.translate),
+            clip: other.clip.or(self.clip),
+            cursor: other.cursor.or(self.cursor),
+            visible: other.visible.or(self.visible),
+            locked: other.locked.or(self.locked),
+            pointer_events: other.pointer_events.or(self.pointer_events),
+        }
     }
 }
\ No newline at end of file
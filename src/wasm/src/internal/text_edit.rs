@@ -0,0 +1,214 @@
+use std::cell::RefCell;
+
+use super::{color::Color, context::Context, geometry::Text, node::NodeId, style::Style};
+
+const CARET_BLINK_PERIOD: f64 = 1000.0;
+const CARET_WIDTH: f64 = 1.5;
+
+/// State of an in-place edit of a `Text` node. Positions are char indices
+/// into the node's text; `anchor == caret` means there is no selection.
+/// Times come from the canvas clock, see `Context::now`.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct TextEditor {
+    pub(crate) node: NodeId,
+    pub(crate) original: String,
+    pub(crate) caret: usize,
+    pub(crate) anchor: usize,
+    pub(crate) is_selecting: bool,
+    blink_start: f64,
+    /// Char boundaries last measured, with the text and font they belong to.
+    boundaries: RefCell<Option<Boundaries>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Boundaries {
+    text: String,
+    font: String,
+    offsets: Vec<f64>,
+}
+
+fn byte_offset(text: &str, index: usize) -> usize {
+    text.char_indices()
+        .nth(index)
+        .map(|(offset, _)| offset)
+        .unwrap_or(text.len())
+}
+
+impl TextEditor {
+    pub(crate) fn new(node: NodeId, text: &str, now: f64) -> Self {
+        let len = text.chars().count();
+        Self {
+            node,
+            original: text.to_string(),
+            caret: len,
+            anchor: len,
+            is_selecting: false,
+            blink_start: now,
+            boundaries: RefCell::new(None),
+        }
+    }
+
+    pub(crate) fn selection(&self) -> Option<(usize, usize)> {
+        if self.caret == self.anchor {
+            return None;
+        }
+        Some((self.caret.min(self.anchor), self.caret.max(self.anchor)))
+    }
+
+    /// Shows the caret from `now` on, e.g. after it moved.
+    pub(crate) fn reset_blink(&mut self, now: f64) {
+        self.blink_start = now;
+    }
+
+    pub(crate) fn move_to(&mut self, index: usize, extend: bool) {
+        self.caret = index;
+        if !extend {
+            self.anchor = index;
+        }
+    }
+
+    pub(crate) fn move_left(&mut self, extend: bool) {
+        match self.selection() {
+            Some((start, _)) if !extend => self.move_to(start, false),
+            _ => self.move_to(self.caret.saturating_sub(1), extend),
+        }
+    }
+
+    pub(crate) fn move_right(&mut self, text: &str, extend: bool) {
+        let len = text.chars().count();
+        match self.selection() {
+            Some((_, end)) if !extend => self.move_to(end, false),
+            _ => self.move_to((self.caret + 1).min(len), extend),
+        }
+    }
+
+    fn delete_selection(&mut self, text: &mut String) -> bool {
+        if let Some((start, end)) = self.selection() {
+            let (start_byte, end_byte) = (byte_offset(text, start), byte_offset(text, end));
+            text.replace_range(start_byte..end_byte, "");
+            self.move_to(start, false);
+            return true;
+        }
+        false
+    }
+
+    pub(crate) fn insert(&mut self, text: &mut String, value: &str) {
+        self.delete_selection(text);
+        let offset = byte_offset(text, self.caret);
+        text.insert_str(offset, value);
+        self.move_to(self.caret + value.chars().count(), false);
+    }
+
+    pub(crate) fn backspace(&mut self, text: &mut String) {
+        if self.delete_selection(text) || self.caret == 0 {
+            return;
+        }
+        let (start, end) = (byte_offset(text, self.caret - 1), byte_offset(text, self.caret));
+        text.replace_range(start..end, "");
+        self.move_to(self.caret - 1, false);
+    }
+
+    pub(crate) fn delete(&mut self, text: &mut String) {
+        if self.delete_selection(text) || self.caret >= text.chars().count() {
+            return;
+        }
+        let (start, end) = (byte_offset(text, self.caret), byte_offset(text, self.caret + 1));
+        text.replace_range(start..end, "");
+    }
+
+    /// Offset of the boundary before each char, plus the end of the text,
+    /// measured from the text origin. Measured again only when the text or
+    /// font changed, since that takes one measurement per char.
+    fn boundaries(&self, ctx: &Context, text: &Text, style: &Style) -> Vec<f64> {
+        let font = style.font();
+        if let Some(cached) = self.boundaries.borrow().as_ref().filter(|cached| cached.text == text.text && cached.font == font) {
+            return cached.offsets.clone();
+        }
+
+        let mut offsets = vec![0.0];
+        let mut prefix = String::new();
+        for c in text.text.chars() {
+            prefix.push(c);
+            offsets.push(ctx.measure_text(&prefix, style).width);
+        }
+        *self.boundaries.borrow_mut() = Some(Boundaries { text: text.text.clone(), font, offsets: offsets.clone() });
+        offsets
+    }
+
+    /// Char index of the boundary closest to the canvas point `(x, y)`.
+    pub(crate) fn index_at(&self, ctx: &Context, text: &Text, style: &Style, x: f64, y: f64) -> usize {
+        let (lx, _) = style.to_local(x, y, text.get_rect());
+        let local_x = lx - text.position.x;
+        self.boundaries(ctx, text, style)
+            .iter()
+            .enumerate()
+            .min_by(|(_, a), (_, b)| (*a - local_x).abs().total_cmp(&(*b - local_x).abs()))
+            .map(|(index, _)| index)
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn render(&self, ctx: &Context, text: &Text, style: &Style) {
+        let extent = text.shape.borrow().unwrap_or_default();
+        let offsets = self.boundaries(ctx, text, style);
+        let offset_of = |index: usize| offsets.get(index).copied().unwrap_or_default();
+        let (x, top, height) = (text.position.x, text.position.y, extent.font_height());
+
+        ctx.context().save();
+        style.apply_transform(ctx.context(), text.get_rect());
+
+        if let Some((start, end)) = self.selection() {
+            let (sx, ex) = (offset_of(start), offset_of(end));
+            ctx.context().set_fill_style_str(&ctx.selection_theme.selected.color.with_alpha(0.35).to_css());
+            ctx.context().fill_rect(x + sx, top, ex - sx, height);
+        }
+
+        let elapsed = ctx.now - self.blink_start;
+        if elapsed % CARET_BLINK_PERIOD < CARET_BLINK_PERIOD / 2.0 {
+            let cx = x + offset_of(self.caret);
+            let color = style.fill_color.or(style.stroke_color).unwrap_or(Color::rgb(0, 0, 0));
+            ctx.context().set_fill_style_str(&color.to_css());
+            ctx.context().fill_rect(cx - CARET_WIDTH / 2.0, top, CARET_WIDTH, height);
+        }
+        ctx.context().restore();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::internal::geometry::Point;
+
+    fn text(value: &str) -> Text {
+        Text { text: value.to_string(), position: Point { x: 0.0, y: 0.0 }, shape: RefCell::new(None) }
+    }
+
+    #[test]
+    fn boundaries_are_measured_again_only_after_a_change() {
+        let ctx = Context::headless();
+        let editor = TextEditor::new(1, "abc", 0.0);
+        let style = Style::default();
+
+        let offsets = editor.boundaries(&ctx, &text("abc"), &style);
+        assert_eq!(offsets.len(), 4);
+        assert!(offsets.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(editor.boundaries.borrow().as_ref().map(|cached| cached.text.as_str()), Some("abc"));
+
+        assert_eq!(editor.boundaries(&ctx, &text("abcd"), &style).len(), 5);
+        let larger = Style { font_size: Some(40.0), ..Default::default() };
+        assert!(editor.boundaries(&ctx, &text("abcd"), &larger)[4] > editor.boundaries(&ctx, &text("abcd"), &style)[4]);
+    }
+
+    #[test]
+    fn edits_move_the_caret_and_replace_the_selection() {
+        let mut value = "hello".to_string();
+        let mut editor = TextEditor::new(1, &value, 0.0);
+        editor.move_to(1, false);
+        editor.move_to(4, true);
+        editor.insert(&mut value, "ipp");
+        assert_eq!((value.as_str(), editor.caret), ("hippo", 4));
+        editor.backspace(&mut value);
+        editor.move_left(false);
+        editor.delete(&mut value);
+        assert_eq!((value.as_str(), editor.caret), ("hio", 2));
+    }
+}
@@ -0,0 +1,152 @@
+use gloo_utils::format::JsValueSerdeExt;
+use wasm_bindgen::JsValue;
+
+use super::{color::Color, geometry::Rectangle};
+
+const ACCENT: Color = Color::from_hex(0x0ea5e9);
+
+/// Outline drawn around a hovered or selected node.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ChromeStyle {
+    pub color: Color,
+    pub line_width: f64,
+    /// Alternating dash and gap lengths; empty for a solid line.
+    pub dash: Vec<f64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HandleShape {
+    Square,
+    Circle,
+    None,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct HandleStyle {
+    pub shape: HandleShape,
+    pub size: f64,
+    pub fill_color: Color,
+    pub stroke_color: Color,
+    pub stroke_width: f64,
+}
+
+/// Appearance of the editor chrome drawn on top of the scene.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct SelectionTheme {
+    /// Gap between a node's bounds and its outline.
+    pub margin: f64,
+    pub hover: ChromeStyle,
+    pub selected: ChromeStyle,
+    pub handle: HandleStyle,
+}
+
+impl Default for SelectionTheme {
+    fn default() -> Self {
+        Self {
+            margin: 5.0,
+            hover: ChromeStyle {
+                color: ACCENT,
+                line_width: 1.0,
+                dash: Vec::new(),
+            },
+            selected: ChromeStyle {
+                color: ACCENT,
+                line_width: 2.5,
+                dash: Vec::new(),
+            },
+            handle: HandleStyle {
+                shape: HandleShape::Square,
+                size: 8.0,
+                fill_color: Color::rgb(255, 255, 255),
+                stroke_color: ACCENT,
+                stroke_width: 1.0,
+            },
+        }
+    }
+}
+
+/// Recursively overwrites the fields of `base` that are present in `patch`.
+pub(crate) fn merge(base: &mut serde_json::Value, patch: serde_json::Value) {
+    match (base, patch) {
+        (serde_json::Value::Object(base), serde_json::Value::Object(patch)) => {
+            for (key, value) in patch {
+                match base.get_mut(&key) {
+                    Some(slot) => merge(slot, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, patch) => *base = patch,
+    }
+}
+
+impl SelectionTheme {
+    /// Returns a copy of the theme with the (possibly partial) JS object
+    /// `patch` applied on top, e.g. `{ hover: { dash: [4, 2] } }`.
+    pub(crate) fn patch(&self, patch: &JsValue) -> Result<Self, JsValue> {
+        let patch: serde_json::Value = patch
+            .into_serde()
+            .map_err(|err| JsValue::from_str(&format!("invalid selection theme: {}", err)))?;
+        self.merged(patch).map_err(|err| JsValue::from_str(&err))
+    }
+
+    fn merged(&self, patch: serde_json::Value) -> Result<Self, String> {
+        let mut theme = serde_json::to_value(self).map_err(|err| err.to_string())?;
+        merge(&mut theme, patch);
+        serde_json::from_value(theme).map_err(|err| format!("invalid selection theme: {}", err))
+    }
+
+    /// Outline rectangle for a node with the given untransformed bounds.
+    pub(crate) fn outline(&self, rect: Rectangle) -> Rectangle {
+        Rectangle {
+            left: rect.left - self.margin,
+            top: rect.top - self.margin,
+            width: rect.width + self.margin * 2.0,
+            height: rect.height + self.margin * 2.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn patches_only_change_the_fields_they_name() {
+        let theme = SelectionTheme::default();
+        let patched = theme.merged(serde_json::json!({ "hover": { "dash": [4.0, 2.0] }, "margin": 2.0 })).unwrap();
+
+        assert_eq!(patched.hover.dash, vec![4.0, 2.0]);
+        assert_eq!(patched.margin, 2.0);
+        assert_eq!(patched.hover.color, theme.hover.color);
+        assert_eq!(patched.selected, theme.selected);
+        assert_eq!(patched.handle, theme.handle);
+    }
+
+    #[test]
+    fn patches_take_css_colors_and_handle_shapes() {
+        let patch = serde_json::json!({ "handle": { "shape": "circle", "fill_color": "red" } });
+        let patched = SelectionTheme::default().merged(patch).unwrap();
+        assert_eq!(patched.handle.shape, HandleShape::Circle);
+        assert_eq!(patched.handle.fill_color, Color::rgb(255, 0, 0));
+        assert_eq!(patched.handle.size, 8.0);
+    }
+
+    #[test]
+    fn invalid_patches_are_rejected() {
+        let theme = SelectionTheme::default();
+        assert!(theme.merged(serde_json::json!({ "handle": { "shape": "star" } })).is_err());
+        assert!(theme.merged(serde_json::json!({ "hover": { "color": "not a color" } })).is_err());
+        assert!(theme.merged(serde_json::json!({ "margin": "wide" })).is_err());
+    }
+
+    #[test]
+    fn outlines_grow_by_the_margin() {
+        let theme = SelectionTheme { margin: 4.0, ..Default::default() };
+        let outline = theme.outline(Rectangle { left: 10.0, top: 20.0, width: 30.0, height: 40.0 });
+        assert_eq!(outline, Rectangle { left: 6.0, top: 16.0, width: 38.0, height: 48.0 });
+    }
+}
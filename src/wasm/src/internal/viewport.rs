@@ -0,0 +1,66 @@
+use super::geometry::Point;
+
+/// Furthest the view can be zoomed out, unless configured otherwise.
+pub(crate) const DEFAULT_MIN_ZOOM: f64 = 0.1;
+/// Furthest the view can be zoomed in, unless configured otherwise.
+pub(crate) const DEFAULT_MAX_ZOOM: f64 = 10.0;
+
+/// Maps scene coordinates, which nodes live in, onto the screen: the scene
+/// is scaled and rotated around its origin, then shifted by the offset.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Viewport {
+    pub offset_x: f64,
+    pub offset_y: f64,
+    pub scale: f64,
+    /// Radians, clockwise.
+    pub rotation: f64,
+}
+
+impl Default for Viewport {
+    fn default() -> Self {
+        Self {
+            offset_x: 0.0,
+            offset_y: 0.0,
+            scale: 1.0,
+            rotation: 0.0,
+        }
+    }
+}
+
+impl Viewport {
+    pub(crate) fn to_screen(self, x: f64, y: f64) -> (f64, f64) {
+        let (sin, cos) = self.rotation.sin_cos();
+        let (x, y) = (x * self.scale, y * self.scale);
+        (x * cos - y * sin + self.offset_x, x * sin + y * cos + self.offset_y)
+    }
+
+    pub(crate) fn to_scene(self, x: f64, y: f64) -> (f64, f64) {
+        let (sin, cos) = self.rotation.sin_cos();
+        let (x, y) = (x - self.offset_x, y - self.offset_y);
+        ((x * cos + y * sin) / self.scale, (y * cos - x * sin) / self.scale)
+    }
+
+    /// Multiplies the current canvas transform by the viewport, so that
+    /// everything drawn afterwards can use scene coordinates.
+    pub(crate) fn apply(&self, ctx: &web_sys::CanvasRenderingContext2d) {
+        let _ = ctx.translate(self.offset_x, self.offset_y);
+        let _ = ctx.rotate(self.rotation);
+        let _ = ctx.scale(self.scale, self.scale);
+    }
+
+    /// Viewport with the given scale and rotation that moves the scene point
+    /// currently under the screen point `anchor` to the screen point `target`.
+    pub(crate) fn anchored(self, anchor: Point, target: Point, scale: f64, rotation: f64) -> Self {
+        let (sx, sy) = self.to_scene(anchor.x, anchor.y);
+        let mut viewport = Self {
+            offset_x: 0.0,
+            offset_y: 0.0,
+            scale,
+            rotation,
+        };
+        let (x, y) = viewport.to_screen(sx, sy);
+        viewport.offset_x = target.x - x;
+        viewport.offset_y = target.y - y;
+        viewport
+    }
+}
@@ -0,0 +1,105 @@
+use super::node::{Node, NodeId};
+
+/// Ways to move nodes up or down the draw order, where later nodes are
+/// drawn on top.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Restack {
+    /// Above every other node.
+    Front,
+    /// Below every other node.
+    Back,
+    /// One step up, past the next node that is not being moved.
+    Forward,
+    /// One step down.
+    Backward,
+    /// To this position in the draw order, clamped to the valid range.
+    Index(usize),
+}
+
+/// Moves the nodes with `ids` as described by `restack`, keeping their order
+/// relative to each other. Returns whether anything moved.
+pub(crate) fn restack(nodes: &mut Vec<Node>, ids: &[NodeId], restack: Restack) -> bool {
+    let before: Vec<NodeId> = nodes.iter().map(Node::id).collect();
+    let is_moved = |node: &Node| ids.contains(&node.id());
+
+    match restack {
+        Restack::Front => {
+            let (moved, rest): (Vec<Node>, Vec<Node>) = nodes.drain(..).partition(is_moved);
+            nodes.extend(rest);
+            nodes.extend(moved);
+        }
+        Restack::Back => {
+            let (moved, rest): (Vec<Node>, Vec<Node>) = nodes.drain(..).partition(is_moved);
+            nodes.extend(moved);
+            nodes.extend(rest);
+        }
+        Restack::Forward => {
+            for index in (0..nodes.len().saturating_sub(1)).rev() {
+                if is_moved(&nodes[index]) && !is_moved(&nodes[index + 1]) {
+                    nodes.swap(index, index + 1);
+                }
+            }
+        }
+        Restack::Backward => {
+            for index in 1..nodes.len() {
+                if is_moved(&nodes[index]) && !is_moved(&nodes[index - 1]) {
+                    nodes.swap(index, index - 1);
+                }
+            }
+        }
+        Restack::Index(index) => {
+            let (moved, mut rest): (Vec<Node>, Vec<Node>) = nodes.drain(..).partition(is_moved);
+            let index = index.min(rest.len());
+            rest.splice(index..index, moved);
+            *nodes = rest;
+        }
+    }
+
+    nodes.iter().map(Node::id).ne(before)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::internal::{geometry::Rectangle, style::Style};
+
+    fn nodes() -> Vec<Node> {
+        (1..=5)
+            .map(|id| Node::Rect(Rectangle { left: 0.0, top: 0.0, width: 10.0, height: 10.0 }, Style::default(), false, id))
+            .collect()
+    }
+
+    fn restacked(ids: &[NodeId], how: Restack) -> (bool, Vec<NodeId>) {
+        let mut nodes = nodes();
+        let is_moved = restack(&mut nodes, ids, how);
+        (is_moved, nodes.iter().map(Node::id).collect())
+    }
+
+    #[test]
+    fn scattered_nodes_move_together_in_their_own_order() {
+        assert_eq!(restacked(&[4, 2], Restack::Front), (true, vec![1, 3, 5, 2, 4]));
+        assert_eq!(restacked(&[4, 2], Restack::Back), (true, vec![2, 4, 1, 3, 5]));
+        assert_eq!(restacked(&[4, 2], Restack::Forward), (true, vec![1, 3, 2, 5, 4]));
+        assert_eq!(restacked(&[4, 2], Restack::Backward), (true, vec![2, 1, 4, 3, 5]));
+        assert_eq!(restacked(&[4, 2], Restack::Index(1)), (true, vec![1, 2, 4, 3, 5]));
+        assert_eq!(restacked(&[4, 2], Restack::Index(99)), (true, vec![1, 3, 5, 2, 4]));
+    }
+
+    #[test]
+    fn moves_that_change_nothing_return_false() {
+        assert_eq!(restacked(&[4, 5], Restack::Front), (false, vec![1, 2, 3, 4, 5]));
+        assert_eq!(restacked(&[1], Restack::Back), (false, vec![1, 2, 3, 4, 5]));
+        assert_eq!(restacked(&[5], Restack::Forward), (false, vec![1, 2, 3, 4, 5]));
+        assert_eq!(restacked(&[1, 2], Restack::Backward), (false, vec![1, 2, 3, 4, 5]));
+        assert_eq!(restacked(&[3], Restack::Index(2)), (false, vec![1, 2, 3, 4, 5]));
+        assert_eq!(restacked(&[], Restack::Front), (false, vec![1, 2, 3, 4, 5]));
+    }
+
+    #[test]
+    fn unknown_ids_are_ignored() {
+        assert_eq!(restacked(&[9], Restack::Front), (false, vec![1, 2, 3, 4, 5]));
+        assert_eq!(restacked(&[9], Restack::Index(0)), (false, vec![1, 2, 3, 4, 5]));
+        assert_eq!(restacked(&[2, 9], Restack::Front), (true, vec![1, 3, 4, 5, 2]));
+        assert_eq!(restacked(&[9, 4], Restack::Backward), (true, vec![1, 2, 4, 3, 5]));
+    }
+}
@@ -1,7 +1,16 @@
 mod utils;
 mod internal;
 
-use internal::{context::Context, style::Style};
+use internal::{
+    color::Color,
+    context::Context,
+    event::{DeltaMode, Modifiers, MouseButton, PointerInput, PointerType},
+    geometry::Rectangle,
+    import::DropItem,
+    selection::MarqueeMode,
+    style::Style,
+};
+use gloo_utils::format::JsValueSerdeExt;
 use wasm_bindgen::prelude::*;
 
 use crate::{internal::node::NodeRef, utils::set_panic_hook};
@@ -12,9 +21,16 @@ pub struct Canvas {
     inner: Context
 }
 
+fn pointer_input(button: Option<MouseButton>, pointer_type: Option<PointerType>) -> PointerInput {
+    PointerInput {
+        button: button.unwrap_or_default(),
+        pointer_type: pointer_type.unwrap_or_default(),
+    }
+}
+
 impl Canvas {
     fn make_node_ref(&self) -> NodeRef {
-        let id = self.inner.nodes.len() - 1;
+        let id = self.inner.nodes.last().map(|node| node.id()).unwrap_or_default();
         NodeRef::new(&self.inner, id)
     }
 }
@@ -22,13 +38,24 @@ impl Canvas {
 #[wasm_bindgen]
 impl Canvas {
     #[wasm_bindgen(constructor)]
-    pub fn new(id: String, background_color: Option<String>) -> Canvas {
+    pub fn new(id: String, background_color: Option<String>) -> Result<Canvas, JsValue> {
         set_panic_hook();
-        
+
+        let background_color = background_color
+            .map(|color| Color::parse(&color))
+            .transpose()?;
         let inner = Context::new(id, background_color).unwrap();
         
-        Self {
+        Ok(Self {
             inner
+        })
+    }
+
+    /// A canvas that handles input but draws nothing, for replaying
+    /// recordings where there is no DOM, e.g. in tests running under Node.
+    pub fn headless() -> Canvas {
+        Self {
+            inner: Context::headless()
         }
     }
 
@@ -37,68 +64,435 @@ impl Canvas {
     }
 
     pub fn get_js_canvas(&self) -> Option<web_sys::HtmlCanvasElement> {
-        self.inner.context.canvas()
+        self.inner.canvas()
     }
 
-    pub fn add_line(&mut self, start_x: f64, start_y: f64, end_x: f64, end_y: f64, style: JsValue) -> NodeRef {
+    pub fn add_line(&mut self, start_x: f64, start_y: f64, end_x: f64, end_y: f64, style: JsValue) -> Result<NodeRef, JsValue> {
         self.inner.add_line(
             (start_x, start_y).into(), 
             (end_x, end_y).into(),
-            Style::from_json(&style)
+            Style::from_json(&style)?
         );
-        self.make_node_ref()
+        Ok(self.make_node_ref())
     }
 
-    pub fn add_rect(&mut self, top: f64, left: f64, width: f64, height: f64, style: JsValue) -> NodeRef {
+    pub fn add_rect(&mut self, top: f64, left: f64, width: f64, height: f64, style: JsValue) -> Result<NodeRef, JsValue> {
         self.inner.add_rect(
             top, 
             left, 
             width, 
             height,
-            Style::from_json(&style)
+            Style::from_json(&style)?
         );
-        self.make_node_ref()
+        Ok(self.make_node_ref())
     }
 
-    pub fn add_circle(&mut self, center_x: f64, center_y: f64, radius: f64, style: JsValue) -> NodeRef {
+    pub fn add_circle(&mut self, center_x: f64, center_y: f64, radius: f64, style: JsValue) -> Result<NodeRef, JsValue> {
         self.inner.add_circle(
             (center_x, center_y).into(), 
             radius,
-            Style::from_json(&style)
+            Style::from_json(&style)?
         );
-        self.make_node_ref()
+        Ok(self.make_node_ref())
     }
 
-    pub fn add_text(&mut self, text: String, x: f64, y: f64, style: JsValue) -> NodeRef {
+    pub fn add_text(&mut self, text: String, x: f64, y: f64, style: JsValue) -> Result<NodeRef, JsValue> {
         self.inner.add_text(
             text,
             (x, y).into(),
-            Style::from_json(&style)
+            Style::from_json(&style)?
+        );
+        Ok(self.make_node_ref())
+    }
+
+    /// Adds a bitmap drawn into the given box; `src` is anything an `<img>`
+    /// accepts, such as a URL or a data URL. A grey box is drawn until it
+    /// has loaded.
+    pub fn add_image(&mut self, src: String, left: f64, top: f64, width: f64, height: f64, style: JsValue) -> Result<NodeRef, JsValue> {
+        self.inner.add_image(
+            src,
+            Rectangle { left, top, width, height },
+            Style::from_json(&style)?
         );
-        self.make_node_ref()
+        Ok(self.make_node_ref())
+    }
+
+    /// Updates the hover and selection chrome. Accepts a partial theme, e.g.
+    /// `{ margin: 8, selected: { color: "#f97316", dash: [6, 3] } }`.
+    pub fn set_selection_theme(&mut self, theme: JsValue) -> Result<(), JsValue> {
+        self.inner.selection_theme = self.inner.selection_theme.patch(&theme)?;
+        Ok(())
+    }
+
+    pub fn get_selection_theme(&self) -> JsValue {
+        JsValue::from_serde(&self.inner.selection_theme).unwrap_or(JsValue::NULL)
+    }
+
+    /// Updates how dragged nodes snap; takes a partial object such as
+    /// `{ grid_size: 20 }` or `{ enabled: false }`. See `get_snapping` for
+    /// every field.
+    pub fn set_snapping(&mut self, settings: JsValue) -> Result<(), JsValue> {
+        self.inner.snap = self.inner.snap.patch(&settings)?;
+        Ok(())
+    }
+
+    pub fn get_snapping(&self) -> JsValue {
+        JsValue::from_serde(&self.inner.snap).unwrap_or(JsValue::NULL)
+    }
+
+    /// Updates the limits that tell clicks, double clicks and drags apart;
+    /// takes a partial object such as `{ drag_threshold: 5 }`. See
+    /// `get_click_settings` for every field.
+    pub fn set_click_settings(&mut self, settings: JsValue) -> Result<(), JsValue> {
+        self.inner.clicks.settings = self.inner.clicks.settings.patch(&settings)?;
+        Ok(())
+    }
+
+    pub fn get_click_settings(&self) -> JsValue {
+        JsValue::from_serde(&self.inner.clicks.settings).unwrap_or(JsValue::NULL)
+    }
+
+    /// Chooses whether a marquee drag selects nodes it merely touches
+    /// (`"intersect"`, the default) or only those it fully encloses
+    /// (`"contain"`).
+    pub fn set_marquee_mode(&mut self, mode: String) -> Result<(), JsValue> {
+        self.inner.marquee_mode = MarqueeMode::from_str(&mode)
+            .ok_or_else(|| JsValue::from_str(&format!("unknown marquee mode `{}`", mode)))?;
+        Ok(())
+    }
+
+    /// Ids of the selected nodes, in the order they were selected.
+    pub fn get_selection(&self) -> Vec<u32> {
+        self.inner.selected_nodes.clone()
+    }
+
+    /// Returns the width, glyph bounds and font ascent/descent of `text` as it
+    /// would be drawn with `style`.
+    pub fn measure_text(&self, text: String, style: JsValue) -> Result<JsValue, JsValue> {
+        let extent = self.inner.measure_text(&text, &Style::from_json(&style)?);
+        Ok(JsValue::from_serde(&extent).unwrap_or(JsValue::NULL))
+    }
+
+    /// Queues a pointer event. `modifiers` is a bitmask of `Modifier` flags,
+    /// `button` defaults to the left button; when modifiers are omitted they
+    /// are tracked from key events instead. Double clicks are detected from
+    /// the click settings.
+    #[allow(clippy::too_many_arguments)]
+    pub fn on_mouse_down(
+        &mut self,
+        x: f64,
+        y: f64,
+        ts: f64,
+        modifiers: Option<u32>,
+        button: Option<MouseButton>,
+        pointer_type: Option<PointerType>,
+    ) {
+        let pointer = pointer_input(button, pointer_type);
+        self.inner.on_mouse_down(x, y, ts, modifiers.map(Modifiers::from_bits), pointer);
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn on_mouse_move(
+        &mut self,
+        x: f64,
+        y: f64,
+        ts: f64,
+        modifiers: Option<u32>,
+        button: Option<MouseButton>,
+        pointer_type: Option<PointerType>,
+    ) {
+        let pointer = pointer_input(button, pointer_type);
+        self.inner.on_mouse_move(x, y, ts, modifiers.map(Modifiers::from_bits), pointer);
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn on_mouse_up(
+        &mut self,
+        x: f64,
+        y: f64,
+        ts: f64,
+        modifiers: Option<u32>,
+        button: Option<MouseButton>,
+        pointer_type: Option<PointerType>,
+    ) {
+        let pointer = pointer_input(button, pointer_type);
+        self.inner.on_mouse_up(x, y, ts, modifiers.map(Modifiers::from_bits), pointer);
+    }
+
+    /// Queues an event of the pointer `pointer_id`, as reported by DOM
+    /// pointer events. A single pointer selects and drags like the mouse;
+    /// two pointers pan, pinch-zoom and rotate the view, a double-tap acts
+    /// as a double click and a long touch as a right click.
+    #[allow(clippy::too_many_arguments)]
+    pub fn on_pointer_down(
+        &mut self,
+        pointer_id: i32,
+        x: f64,
+        y: f64,
+        ts: f64,
+        modifiers: Option<u32>,
+        button: Option<MouseButton>,
+        pointer_type: Option<PointerType>,
+    ) {
+        let pointer = pointer_input(button, pointer_type);
+        self.inner.on_pointer_down(pointer_id, x, y, ts, modifiers.map(Modifiers::from_bits), pointer);
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn on_pointer_move(
+        &mut self,
+        pointer_id: i32,
+        x: f64,
+        y: f64,
+        ts: f64,
+        modifiers: Option<u32>,
+        button: Option<MouseButton>,
+        pointer_type: Option<PointerType>,
+    ) {
+        let pointer = pointer_input(button, pointer_type);
+        self.inner.on_pointer_move(pointer_id, x, y, ts, modifiers.map(Modifiers::from_bits), pointer);
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn on_pointer_up(
+        &mut self,
+        pointer_id: i32,
+        x: f64,
+        y: f64,
+        ts: f64,
+        modifiers: Option<u32>,
+        button: Option<MouseButton>,
+        pointer_type: Option<PointerType>,
+    ) {
+        let pointer = pointer_input(button, pointer_type);
+        self.inner.on_pointer_up(pointer_id, x, y, ts, modifiers.map(Modifiers::from_bits), pointer);
+    }
+
+    pub fn on_pointer_cancel(&mut self, pointer_id: i32, ts: f64) {
+        self.inner.on_pointer_cancel(pointer_id, ts);
+    }
+
+    /// Current pan, zoom and rotation of the view as
+    /// `{ offset_x, offset_y, scale, rotation }`.
+    pub fn get_viewport(&self) -> JsValue {
+        JsValue::from_serde(&self.inner.viewport).unwrap_or(JsValue::NULL)
+    }
+
+    pub fn reset_viewport(&mut self) {
+        self.inner.reset_viewport();
+    }
+
+    /// Handles a wheel event: plain scrolling pans the view, ctrl/cmd (and
+    /// trackpad pinches) zoom around `(x, y)`. `mode` is the DOM event's
+    /// `deltaMode` and defaults to pixels.
+    pub fn on_wheel(&mut self, dx: f64, dy: f64, x: f64, y: f64, mode: Option<DeltaMode>, modifiers: Option<u32>) {
+        self.inner.on_wheel(dx, dy, x, y, mode.unwrap_or_default(), modifiers.map(Modifiers::from_bits));
+    }
+
+    /// Limits how far the view can be zoomed out and in, as scale factors.
+    pub fn set_zoom_limits(&mut self, min: f64, max: f64) -> Result<(), JsValue> {
+        if !(min > 0.0 && min <= max) {
+            return Err(JsValue::from_str(&format!("invalid zoom limits {}..{}", min, max)));
+        }
+        self.inner.set_zoom_limits(min, max);
+        Ok(())
+    }
+
+    /// Binds a key chord such as `"Shift+ArrowUp"` or `"Mod+D"` to an action.
+    /// Built-in actions are `nudge_left`, `nudge_right`, `nudge_up`,
+    /// `nudge_down` (and their `_large` variants), `delete`, `deselect`,
+    /// `undo`, `redo`, `copy`, `cut`, `paste`, `duplicate`, `bring_forward`,
+    /// `send_backward`, `bring_to_front` and `send_to_back`; any other name
+    /// must be registered with `register_action`.
+    pub fn bind_key(&mut self, chord: String, action: String) -> Result<(), JsValue> {
+        self.inner.keymap.bind(&chord, &action).map_err(|err| JsValue::from_str(&err))
+    }
+
+    pub fn unbind_key(&mut self, chord: String) -> Result<(), JsValue> {
+        self.inner.keymap.unbind(&chord).map_err(|err| JsValue::from_str(&err))
+    }
+
+    /// Registers `callback` as the action `name`. It is called with the ids
+    /// of the selected nodes whenever a key chord bound to `name` is pressed.
+    /// Like listeners, it runs as a microtask once the current task's
+    /// synchronous JS has finished, so it may use the canvas; nothing runs if
+    /// the canvas has been freed by then. Its changes form a single undo step.
+    pub fn register_action(&mut self, name: String, callback: js_sys::Function) {
+        self.inner.actions.insert(name, callback);
+    }
+
+    /// Runs a built-in action (see `bind_key`) or one registered with
+    /// `register_action`, e.g. when picked from a context menu; registered
+    /// ones run later, as described there. Returns `false` when there is no
+    /// such action.
+    pub fn run_action(&mut self, name: String) -> bool {
+        self.inner.run_action(&name)
+    }
+
+    /// Built-in actions that currently apply, e.g. `delete` only while
+    /// something is selected.
+    pub fn get_available_actions(&self) -> Vec<String> {
+        self.inner.available_actions().into_iter().map(String::from).collect()
+    }
+
+    /// Calls `callback` whenever `event` happens anywhere on the canvas, one
+    /// of `select`, `hover_enter`, `hover_leave`, `drag_start`, `drag_move`,
+    /// `drag_end`, `click`, `dblclick`, `drag_enter`, `drag_over`,
+    /// `drag_leave`, `drop` or `context_menu`. It receives
+    /// `{ type, node, nodes, x, y, dx, dy }`: the node concerned (or `null`),
+    /// every node involved (the selection for `select` and `context_menu`,
+    /// the dragged nodes for drags), the pointer position in scene
+    /// coordinates and, for drags, the total offset. Callbacks run as a
+    /// microtask queued by the `render` that noticed the event, once the
+    /// current task's synchronous JS has finished, so they may use the
+    /// canvas. Returns an id for `off`.
+    ///
+    /// `context_menu` fires on right-button presses and touch long-presses,
+    /// after selecting the node under the pointer. Its payload adds
+    /// `screen_x` and `screen_y`, in canvas pixels, to place a menu at, and
+    /// `actions`, the built-in actions that apply, for `run_action`.
+    pub fn on(&mut self, event: String, callback: js_sys::Function) -> Result<u32, JsValue> {
+        self.inner.subscribe(&event, None, callback).map_err(|err| JsValue::from_str(&err))
+    }
+
+    /// Removes a listener added with `on`, here or on a node.
+    pub fn off(&mut self, listener: u32) {
+        self.inner.listeners.remove(listener);
+    }
+
+    /// Starts logging every input call, with its timestamp, together with
+    /// the current scene, selection, viewport, canvas size, selection theme,
+    /// key bindings, marquee mode, zoom limits and snap and click settings.
+    pub fn start_recording(&mut self) {
+        self.inner.start_recording();
+    }
+
+    /// Stops recording and returns the log as JSON, or nothing when no
+    /// recording was running.
+    pub fn stop_recording(&mut self) -> Option<String> {
+        self.inner.stop_recording()
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.inner.is_recording()
+    }
+
+    /// Replaces the scene and settings with the ones a recording started
+    /// from, resizing the canvas to the recorded size, and replays its input
+    /// frame by frame, so the canvas ends up where the recorded one did. Any
+    /// interaction in progress and the undo history are dropped. Listeners,
+    /// registered actions, JS hooks and drag options are not recorded; set
+    /// them up before replaying to reproduce their effect.
+    pub fn replay(&mut self, recording: String) -> Result<(), JsValue> {
+        self.inner.replay(&recording).map_err(|err| JsValue::from_str(&err))
+    }
+
+    /// Every node in the clipboard JSON format, e.g. to compare the scene
+    /// after a replay with the recorded one.
+    pub fn get_scene(&self) -> String {
+        self.inner.scene_json()
+    }
+
+    /// Reverts the latest change to the scene. Returns `false` when there is
+    /// nothing to undo or a drag, edit or transaction is still in progress.
+    pub fn undo(&mut self) -> bool {
+        self.inner.undo()
+    }
+
+    pub fn redo(&mut self) -> bool {
+        self.inner.redo()
+    }
+
+    pub fn can_undo(&self) -> bool {
+        self.inner.history.can_undo()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        self.inner.history.can_redo()
+    }
+
+    /// Groups every change until the matching `commit_transaction` into a
+    /// single undo step. Transactions nest.
+    pub fn begin_transaction(&mut self) {
+        self.inner.begin_transaction();
+    }
+
+    pub fn commit_transaction(&mut self) {
+        self.inner.commit_transaction();
+    }
+
+    /// Sets how many undo steps are kept, dropping the oldest beyond it.
+    pub fn set_history_limit(&mut self, limit: usize) {
+        self.inner.history.set_limit(limit);
+    }
+
+    pub fn clear_history(&mut self) {
+        self.inner.clear_history();
+    }
+
+    /// Copies the selected nodes and returns them as text for the system
+    /// clipboard, or `undefined` when nothing is selected.
+    pub fn copy_selection(&mut self) -> Option<String> {
+        self.inner.copy_selection()
+    }
+
+    /// Like `copy_selection`, then removes the selected nodes.
+    pub fn cut_selection(&mut self) -> Option<String> {
+        self.inner.cut_selection()
+    }
+
+    /// Pastes nodes copied from this or another canvas and returns their
+    /// ids. `(at_x, at_y)` is where the top-left of the pasted nodes goes,
+    /// in canvas pixels; `text` defaults to the last copy on this canvas.
+    pub fn paste(&mut self, at_x: Option<f64>, at_y: Option<f64>, text: Option<String>) -> Result<Vec<u32>, JsValue> {
+        let at = match (at_x, at_y) {
+            (Some(x), Some(y)) => Some(self.inner.viewport.to_scene(x, y).into()),
+            _ => None,
+        };
+        self.inner.paste(text.as_deref(), at).map_err(|err| JsValue::from_str(&err))
+    }
+
+    /// Copies the selected nodes next to the originals and selects the
+    /// copies, returning their ids.
+    pub fn duplicate(&mut self) -> Vec<u32> {
+        self.inner.duplicate()
     }
 
-    pub fn on_mouse_down(&mut self, x: f64, y: f64, ts: f64) {
-        self.inner.on_mouse_down(x, y, ts);
+    /// Call from `dragover` with the pointer position in canvas pixels, so
+    /// the node under the dragged data is highlighted and `drag_enter`,
+    /// `drag_over` and `drag_leave` listeners run.
+    pub fn on_drag_over(&mut self, x: f64, y: f64) {
+        self.inner.on_drag_over(x, y);
     }
 
-    pub fn on_mouse_move(&mut self, x: f64, y: f64, ts: f64) {
-        self.inner.on_mouse_move(x, y, ts);
+    /// Call from `dragleave` once the data has left the canvas element.
+    pub fn on_drag_leave(&mut self) {
+        self.inner.on_drag_leave();
     }
 
-    pub fn on_mouse_up(&mut self, x: f64, y: f64, ts: f64) {
-        self.inner.on_mouse_up(x, y, ts);
+    /// Imports data dropped at `(x, y)` in canvas pixels and returns the ids
+    /// of the new nodes, which are selected. `items` is one item or an array
+    /// of `{ type: "image", src, width?, height?, style? }` (a 100px square
+    /// unless sized), `{ type: "svg", markup }` (rects, circles, lines and
+    /// text become shapes) and `{ type: "text", text, style? }` (a text node
+    /// per line). Throws without adding anything if an item cannot be imported.
+    pub fn on_drop(&mut self, x: f64, y: f64, items: JsValue) -> Result<Vec<u32>, JsValue> {
+        let items = DropItem::from_json(&items).map_err(|err| JsValue::from_str(&err))?;
+        self.inner.on_drop(x, y, items).map_err(|err| JsValue::from_str(&err))
     }
 
-    pub fn on_key_down(&mut self, key: String, ts: f64) {
-        self.inner.on_key_down(key, ts);
+    /// Returns whether the canvas will act on the key, by typing it into the
+    /// edited text or running its key binding, so the host can call
+    /// `preventDefault` and keep the browser from acting on it too.
+    pub fn on_key_down(&mut self, key: String, ts: f64, modifiers: Option<u32>) -> bool {
+        self.inner.on_key_down(key, ts, modifiers.map(Modifiers::from_bits))
     }
 
-    pub fn on_key_up(&mut self, key: String, ts: f64) {
-        self.inner.on_key_up(key, ts);
+    pub fn on_key_up(&mut self, key: String, ts: f64, modifiers: Option<u32>) {
+        self.inner.on_key_up(key, ts, modifiers.map(Modifiers::from_bits));
     }
 
-    pub fn on_key_press(&mut self, key: String, ts: f64) {
-        self.inner.on_key_press(key, ts);
+    pub fn on_key_press(&mut self, key: String, ts: f64, modifiers: Option<u32>) {
+        self.inner.on_key_press(key, ts, modifiers.map(Modifiers::from_bits));
     }
 }